@@ -295,6 +295,7 @@ _NORMAL_DEPENDENCIES = {
             "cfg-expr": "@crate_index__cfg-expr-0.10.2//:cfg_expr",
             "clap": "@crate_index__clap-3.1.3//:clap",
             "crates-index": "@crate_index__crates-index-0.18.7//:crates_index",
+            "flate2": "@crate_index__flate2-1.0.22//:flate2",
             "hex": "@crate_index__hex-0.4.3//:hex",
             "pathdiff": "@crate_index__pathdiff-0.2.1//:pathdiff",
             "regex": "@crate_index__regex-1.5.4//:regex",
@@ -302,6 +303,7 @@ _NORMAL_DEPENDENCIES = {
             "serde": "@crate_index__serde-1.0.136//:serde",
             "serde_json": "@crate_index__serde_json-1.0.79//:serde_json",
             "sha2": "@crate_index__sha2-0.10.2//:sha2",
+            "tar": "@crate_index__tar-0.4.38//:tar",
             "tempfile": "@crate_index__tempfile-3.3.0//:tempfile",
             "tera": "@crate_index__tera-1.15.0//:tera",
             "textwrap": "@crate_index__textwrap-0.14.2//:textwrap",
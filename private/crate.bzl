@@ -73,20 +73,40 @@ def _annotation(
         compile_data = None,
         compile_data_glob = None,
         crate_features = None,
+        crate_features_override = None,
+        crate_features_remove = None,
+        crate_root = None,
         data = None,
         data_glob = None,
         deps = None,
+        disable_pipelining = None,
+        gen_binaries = None,
         gen_build_script = None,
+        override_target = None,
         patch_args = None,
         patch_tool = None,
         patches = None,
+        platform_annotations = None,
         proc_macro_deps = None,
         rustc_env = None,
         rustc_env_files = None,
         rustc_flags = None,
-        shallow_since = None):
+        shallow_since = None,
+        skip_default_annotations = None,
+        srcs_exclude = None,
+        tags = None,
+        visibility = None):
     """A collection of extra attributes and settings for a particular crate
 
+    Annotations are keyed by crate name which may be an exact name, `*` to match all crates, a glob where `*`
+    matches any sequence of characters and `?` matches any single character (e.g. `*-sys` or `windows-*`), or a
+    regular expression wrapped in slashes (e.g. `/^windows[-_]/`). When several annotations match a crate, they're
+    applied from the least to the most specific so the most specific take precedence for single values such as
+    `gen_build_script` and entries of dictionaries such as `rustc_env`. Names are ranked `*`, then regular
+    expressions, then globs, then exact names. Ties are broken by version where `*` ranks below other semver
+    requirements, which rank below exact versions. Annotations for exact names which match no crates are an
+    error while patterns which match no crates only produce a warning.
+
     Args:
         version (str, optional): The version or semver-conditions to match with a crate.
         additive_build_file_content (str, optional): Extra contents to write to the bottom of generated BUILD files.
@@ -107,17 +127,36 @@ def _annotation(
             attribute.
         crate_features (list, optional): A list of strings to add to a crate's `rust_library::crate_features`
             attribute.
+        crate_features_override (list, optional): A list of features to use in place of the features Cargo
+            resolved for the crate. Features enabled by these features are included and optional dependencies are
+            adjusted to match.
+        crate_features_remove (list, optional): A list of features to remove from the features Cargo resolved for
+            the crate. Optional dependencies which are no longer enabled by the remaining features are removed.
+        crate_root (str, optional): A path relative to the crate's manifest to set as the `crate_root` attribute
+            of a crate's `rust_library` or `rust_proc_macro` target.
         data (list, optional): A list of labels to add to a crate's `rust_library::data` attribute.
         data_glob (list, optional): A list of glob patterns to add to a crate's `rust_library::data` attribute.
         deps (list, optional): A list of labels to add to a crate's `rust_library::deps` attribute.
+        disable_pipelining (bool, optional): Whether or not to set a crate's `rust_library::disable_pipelining`
+            attribute.
+        gen_binaries (bool or list, optional): Whether or not to generate `rust_binary` targets for the crate's
+            binaries, or a list of the names of the binaries to generate targets for.
         gen_build_script (bool, optional): An authorative flag to determine whether or not to produce
             `cargo_build_script` targets for the current crate.
+        override_target (str, optional): The label of an existing target (e.g. a first-party fork or a prebuilt
+            library) which replaces the crate. No repository or BUILD file is generated for the crate and all
+            dependents use this label instead.
         patch_args (list, optional): The `patch_args` attribute of a Bazel repository rule. See
             [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)
         patch_tool (list, optional): The `patch_tool` attribute of a Bazel repository rule. See
             [http_archive.patch_tool](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_tool)
         patches (list, optional): The `patches` attribute of a Bazel repository rule. See
             [http_archive.patches](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patches)
+        platform_annotations (dict, optional): A mapping of platform triples or `cfg(...)` expressions to dicts of
+            annotation attributes which only apply to matching platforms and are rendered into `select` statements.
+            The supported attributes are `deps`, `proc_macro_deps`, `data`, `compile_data`, `rustc_env`,
+            `rustc_env_files`, `rustc_flags`, `build_script_deps`, `build_script_proc_macro_deps`,
+            `build_script_data`, `build_script_tools`, `build_script_env` and `build_script_rustc_env`.
         proc_macro_deps (list, optional): A list of labels to add to a crate's `rust_library::proc_macro_deps`
             attribute.
         rustc_env (dict, optional): Additional variables to set on a crate's `rust_library::rustc_env` attribute.
@@ -126,6 +165,15 @@ def _annotation(
         rustc_flags (list, optional): A list of strings to set on a crate's `rust_library::rustc_flags` attribute.
         shallow_since (str, optional): An optional timestamp used for crates originating from a git repository
             instead of a crate registry. This flag optimizes fetching the source code.
+        skip_default_annotations (bool, optional): Whether or not to skip the `default_annotations` of the
+            `crates_repository` or `crates_vendor` rule for the crate.
+        srcs_exclude (list, optional): A list of glob patterns to exclude from the `srcs` attribute of all of a
+            crate's targets.
+        tags (list, optional): A list of tags to add to all of a crate's targets, including build scripts.
+        visibility (list, optional): A list of labels to set as the `visibility` of a crate's library, proc-macro
+            and binary targets. These targets are public by default. Labels must be repository-qualified (eg.
+            `@//pkg:__pkg__`) as they're rendered into the crate's own repository. The package containing the
+            aliases to the crate's targets is always able to see them.
 
     Returns:
         string: A json encoded string containing the specified version and separately all other inputs.
@@ -150,18 +198,29 @@ def _annotation(
             compile_data = compile_data,
             compile_data_glob = compile_data_glob,
             crate_features = crate_features,
+            crate_features_override = crate_features_override,
+            crate_features_remove = crate_features_remove,
+            crate_root = crate_root,
             data = data,
             data_glob = data_glob,
             deps = deps,
+            disable_pipelining = disable_pipelining,
+            gen_binaries = gen_binaries,
             gen_build_script = gen_build_script,
+            override_target = override_target,
             patch_args = patch_args,
             patch_tool = patch_tool,
             patches = patches,
+            platform_annotations = platform_annotations,
             proc_macro_deps = proc_macro_deps,
             rustc_env = rustc_env,
             rustc_env_files = rustc_env_files,
             rustc_flags = rustc_flags,
             shallow_since = shallow_since,
+            skip_default_annotations = skip_default_annotations,
+            srcs_exclude = srcs_exclude,
+            tags = tags,
+            visibility = visibility,
         ),
     ))
 
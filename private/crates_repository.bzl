@@ -110,17 +110,41 @@ Environment Variables:
 | `CARGO_BAZEL_GENERATOR_SHA256` | The sha256 checksum of the file located at `CARGO_BAZEL_GENERATOR_URL` |
 | `CARGO_BAZEL_GENERATOR_URL` | The URL of a cargo-bazel binary. This variable takes precedence over attributes and can use `file://` for local paths |
 | `CARGO_BAZEL_ISOLATED` | An authorative flag as to whether or not the `CARGO_HOME` environment variable should be isolated from the host configuration |
-| `CARGO_BAZEL_REPIN` | An indicator that the dependencies represented by the rule should be regenerated. `REPIN` may also be used. |
+| `CARGO_BAZEL_REPIN` | An indicator that the dependencies represented by the rule should be regenerated. `REPIN` may also be used. When using a Cargo lockfile, this may instead be a comma separated list of package specs (`name` or `name@version`) to update only those crates (`cargo update --package <spec>`). |
+| `CARGO_BAZEL_REPIN_PRECISE` | When `CARGO_BAZEL_REPIN` names a single crate to update, the exact version to update it to (`cargo update --package <spec> --precise <version>`). |
 
 """,
     implementation = _crates_repository_impl,
     attrs = {
+        "annotation_files": attr.label_list(
+            doc = (
+                "Files containing maps of crate annotations shared with other workspaces, like the `annotations` " +
+                "field of a config. The directory of each file is passed to `cargo-bazel` so all `json` and TOML " +
+                "files of a directory should be listed (Eg. `glob([\"annotations/*.json\"])`). These are applied " +
+                "beneath all other annotations and are not required to match any crates."
+            ),
+            allow_files = [".json", ".toml"],
+        ),
         "annotations": attr.string_list_dict(
             doc = "Extra settings to apply to crates. See [crate.annotations](#crateannotations).",
         ),
+        "base_configs": attr.label_list(
+            doc = (
+                "`cargo-bazel` config files shared with other workspaces (Eg. an organisation wide base) which " +
+                "the config generated from this rule's attributes is layered over. Their annotations are not " +
+                "required to match any crates."
+            ),
+            allow_files = [".json", ".toml"],
+        ),
         "cargo_config": attr.label(
             doc = "A [Cargo configuration](https://doc.rust-lang.org/cargo/reference/config.html) file",
         ),
+        "default_annotations": attr.string(
+            doc = (
+                "Extra settings to apply to all third party crates beneath those in `annotations`. Crates may opt " +
+                "out with the `skip_default_annotations` annotation. See [crate.annotations](#crateannotations)."
+            ),
+        ),
         "extra_workspace_member_url_template": attr.string(
             doc = "The registry url to use when fetching extra workspace members",
             default = "https://crates.io/api/v1/crates/{name}/{version}/download",
@@ -133,6 +157,13 @@ Environment Variables:
                 "solve for this."
             ),
         ),
+        "generate_binaries": attr.bool(
+            doc = (
+                "Whether or not to generate `rust_binary` targets for the binaries of crates by default. " +
+                "The `gen_binaries` annotation can select specific binaries of a crate."
+            ),
+            default = True,
+        ),
         "generate_build_scripts": attr.bool(
             doc = (
                 "Whether or not to generate " +
@@ -167,6 +198,15 @@ Environment Variables:
             ),
             default = True,
         ),
+        "license_allowlist": attr.string_list(
+            doc = (
+                "SPDX license identifiers third party crates are permitted to use. If set, generation fails for " +
+                "any crate whose license expression cannot be satisfied by these licenses."
+            ),
+        ),
+        "license_denylist": attr.string_list(
+            doc = "SPDX license identifiers third party crates are never permitted to use.",
+        ),
         "lockfile": attr.label(
             doc = (
                 "The path to a file to use for reproducible renderings. Two kinds of lock files are supported, " +
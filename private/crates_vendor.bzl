@@ -1,7 +1,7 @@
 """Rules for vendoring Bazel targets into existing workspaces"""
 
 load("@rules_rust//rust/platform:triple_mappings.bzl", "SUPPORTED_PLATFORM_TRIPLES")
-load("//private:generate_utils.bzl", "collect_crate_annotations", "render_config")
+load("//private:generate_utils.bzl", "collect_crate_annotations", "collect_default_annotations", "license_policy", "render_config")
 load("//private:splicing_utils.bzl", "splicing_config")
 load("//private:urls.bzl", "CARGO_BAZEL_LABEL")
 
@@ -141,9 +141,15 @@ def _write_config_file(ctx):
     config_data = struct(
         annotations = annotations,
         rendering = rendering_config,
+        default_annotations = collect_default_annotations(ctx.attr.default_annotations, str(ctx.label)),
+        generate_binaries = ctx.attr.generate_binaries,
         generate_build_scripts = ctx.attr.generate_build_scripts,
         cargo_config = None,
         supported_platform_triples = ctx.attr.supported_platform_triples,
+        license_policy = license_policy(
+            ctx.attr.license_allowlist,
+            ctx.attr.license_denylist,
+        ),
     )
 
     config = _write_data_file(
@@ -156,8 +162,21 @@ def _write_config_file(ctx):
     )
 
     is_windows = _is_windows(ctx)
-    args = ["--config", _runfiles_path(config.short_path, is_windows)]
-    runfiles = [config] + ctx.files.manifests
+    args = []
+    for base_config in ctx.files.base_configs:
+        args.extend(["--base-config", _runfiles_path(base_config.short_path, is_windows)])
+    args.extend(["--config", _runfiles_path(config.short_path, is_windows)])
+
+    # Annotation files are collected by directory
+    annotations_dirs = []
+    for file in ctx.files.annotation_files:
+        annotations_dir = file.short_path.rpartition("/")[0]
+        if annotations_dir not in annotations_dirs:
+            annotations_dirs.append(annotations_dir)
+    for annotations_dir in annotations_dirs:
+        args.extend(["--annotations-dir", _runfiles_path(annotations_dir, is_windows)])
+
+    runfiles = [config] + ctx.files.manifests + ctx.files.base_configs + ctx.files.annotation_files
     return args, runfiles
 
 def _crates_vendor_impl(ctx):
@@ -238,11 +257,31 @@ def _crates_vendor_impl(ctx):
 
 crates_vendor = rule(
     implementation = _crates_vendor_impl,
-    doc = "A rule for defining Rust dependencies (crates) and writing targets for them to the current workspace",
+    doc = (
+        "A rule for defining Rust dependencies (crates) and writing targets for them to the current workspace. " +
+        "Running the target with `-- --check` verifies the vendored files are up to date without modifying them."
+    ),
     attrs = {
+        "annotation_files": attr.label_list(
+            doc = (
+                "Files containing maps of crate annotations shared with other workspaces, like the `annotations` " +
+                "field of a config. The directory of each file is passed to `cargo-bazel` so all `json` and TOML " +
+                "files of a directory should be listed (Eg. `glob([\"annotations/*.json\"])`). These are applied " +
+                "beneath all other annotations and are not required to match any crates."
+            ),
+            allow_files = [".json", ".toml"],
+        ),
         "annotations": attr.string_list_dict(
             doc = "Extra settings to apply to crates. See [crate.annotations](#crateannotations).",
         ),
+        "base_configs": attr.label_list(
+            doc = (
+                "`cargo-bazel` config files shared with other workspaces (Eg. an organisation wide base) which " +
+                "the config generated from this rule's attributes is layered over. Their annotations are not " +
+                "required to match any crates."
+            ),
+            allow_files = [".json", ".toml"],
+        ),
         "buildifier": attr.label(
             doc = "The path to a [buildifier](https://github.com/bazelbuild/buildtools/blob/5.0.1/buildifier/README.md) binary used to format generated BUILD files.",
             cfg = "exec",
@@ -259,6 +298,19 @@ crates_vendor = rule(
             allow_files = True,
             default = CARGO_BAZEL_LABEL,
         ),
+        "default_annotations": attr.string(
+            doc = (
+                "Extra settings to apply to all third party crates beneath those in `annotations`. Crates may opt " +
+                "out with the `skip_default_annotations` annotation. See [crate.annotations](#crateannotations)."
+            ),
+        ),
+        "generate_binaries": attr.bool(
+            doc = (
+                "Whether or not to generate `rust_binary` targets for the binaries of crates by default. " +
+                "The `gen_binaries` annotation can select specific binaries of a crate."
+            ),
+            default = True,
+        ),
         "generate_build_scripts": attr.bool(
             doc = (
                 "Whether or not to generate " +
@@ -266,6 +318,15 @@ crates_vendor = rule(
             ),
             default = True,
         ),
+        "license_allowlist": attr.string_list(
+            doc = (
+                "SPDX license identifiers third party crates are permitted to use. If set, generation fails for " +
+                "any crate whose license expression cannot be satisfied by these licenses."
+            ),
+        ),
+        "license_denylist": attr.string_list(
+            doc = "SPDX license identifiers third party crates are never permitted to use.",
+        ),
         "manifests": attr.label_list(
             doc = "A list of Cargo manifests (`Cargo.toml` files).",
             allow_files = ["Cargo.toml"],
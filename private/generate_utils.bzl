@@ -5,6 +5,7 @@ load(":common_utils.bzl", "CARGO_BAZEL_ISOLATED", "cargo_environ", "execute")
 CARGO_BAZEL_GENERATOR_SHA256 = "CARGO_BAZEL_GENERATOR_SHA256"
 CARGO_BAZEL_GENERATOR_URL = "CARGO_BAZEL_GENERATOR_URL"
 CARGO_BAZEL_REPIN = "CARGO_BAZEL_REPIN"
+CARGO_BAZEL_REPIN_PRECISE = "CARGO_BAZEL_REPIN_PRECISE"
 REPIN = "REPIN"
 
 GENERATOR_ENV_VARS = [
@@ -19,6 +20,7 @@ REPIN_ENV_VARS = [
 
 CRATES_REPOSITORY_ENVIRON = GENERATOR_ENV_VARS + REPIN_ENV_VARS + [
     CARGO_BAZEL_ISOLATED,
+    CARGO_BAZEL_REPIN_PRECISE,
 ]
 
 def get_generator(repository_ctx, host_triple):
@@ -176,6 +178,32 @@ def collect_crate_annotations(annotations, repository_name):
             crate_annotations.update({id: data})
     return crate_annotations
 
+def collect_default_annotations(annotation, repository_name):
+    """Deserialize and sanitize the annotation applied to all crates.
+
+    Args:
+        annotation (str): A serialized annotation or an empty string
+        repository_name (str): The name of the repository that owns the annotation
+
+    Returns:
+        dict: The default annotation or `None` if none was provided
+    """
+    if not annotation:
+        return None
+
+    version, data = json.decode(annotation)
+    if version != "*":
+        fail(
+            "Default annotations must have wildcard crate versions. " +
+            "Please update the `default_annotations` attribute of {}".format(repository_name),
+        )
+    if data.get("additive_build_file", None):
+        fail(
+            "Default annotations do not support `additive_build_file`. " +
+            "Please update the `default_annotations` attribute of {}".format(repository_name),
+        )
+    return data
+
 def _read_cargo_config(repository_ctx):
     if repository_ctx.attr.cargo_config:
         config = repository_ctx.path(repository_ctx.attr.cargo_config)
@@ -193,6 +221,24 @@ def _get_render_config(repository_ctx):
 
     return struct(**config)
 
+def license_policy(allow, deny):
+    """Generate the license policy section of a config file
+
+    Args:
+        allow (list): SPDX license identifiers which are permitted.
+        deny (list): SPDX license identifiers which are never permitted.
+
+    Returns:
+        struct: A license policy or `None` if no licenses were provided.
+    """
+    if not allow and not deny:
+        return None
+
+    return struct(
+        allow = allow,
+        deny = deny,
+    )
+
 def generate_config(repository_ctx):
     """Generate a config file from various attributes passed to the rule.
 
@@ -217,11 +263,17 @@ def generate_config(repository_ctx):
         data.update({"additive_build_file_content": "\n".join(content) if content else None})
 
     config = struct(
+        generate_binaries = repository_ctx.attr.generate_binaries,
         generate_build_scripts = repository_ctx.attr.generate_build_scripts,
         annotations = annotations,
+        default_annotations = collect_default_annotations(repository_ctx.attr.default_annotations, repository_ctx.name),
         cargo_config = _read_cargo_config(repository_ctx),
         rendering = _get_render_config(repository_ctx),
         supported_platform_triples = repository_ctx.attr.supported_platform_triples,
+        license_policy = license_policy(
+            repository_ctx.attr.license_allowlist,
+            repository_ctx.attr.license_denylist,
+        ),
     )
 
     config_path = repository_ctx.path("cargo-bazel.json")
@@ -238,6 +290,30 @@ def generate_config(repository_ctx):
         info = config,
     )
 
+def get_config_layer_args(repository_ctx):
+    """Collect `cargo-bazel` arguments for the shared config layers and annotation files of a rule
+
+    Args:
+        repository_ctx (repository_ctx): The rule's context object.
+
+    Returns:
+        list: `--base-config` and `--annotations-dir` arguments
+    """
+    args = []
+    for base_config in repository_ctx.attr.base_configs:
+        args.extend(["--base-config", repository_ctx.path(base_config)])
+
+    # Annotation files are collected by directory
+    annotations_dirs = []
+    for annotation_file in repository_ctx.attr.annotation_files:
+        annotations_dir = str(repository_ctx.path(annotation_file).dirname)
+        if annotations_dir not in annotations_dirs:
+            annotations_dirs.append(annotations_dir)
+    for annotations_dir in annotations_dirs:
+        args.extend(["--annotations-dir", annotations_dir])
+
+    return args
+
 def get_lockfile(repository_ctx):
     """Locate the lockfile and identify the it's type (Cargo or Bazel).
 
@@ -260,6 +336,50 @@ def get_lockfile(repository_ctx):
         kind = kind,
     )
 
+def get_update_requests(repository_ctx):
+    """Collect requests to update specific crates from the repin environment variables
+
+    Repin variables may be set to a comma separated list of package specs (`name` or
+    `name@version`) instead of a boolean to update only those crates in an existing Cargo lockfile.
+
+    Args:
+        repository_ctx (repository_ctx): The rule's context object.
+
+    Returns:
+        list: A list of `name` or `name@version` strings.
+    """
+    requests = []
+    for var in REPIN_ENV_VARS:
+        value = repository_ctx.os.environ.get(var, "")
+        if value.lower() in ["", "true", "yes", "1", "on", "false", "no", "0", "off"]:
+            continue
+        requests.extend([request.strip() for request in value.split(",") if request.strip()])
+
+    return requests
+
+def get_update_precise(repository_ctx):
+    """Collect the exact version to update a single crate to from `CARGO_BAZEL_REPIN_PRECISE`
+
+    Args:
+        repository_ctx (repository_ctx): The rule's context object.
+
+    Returns:
+        str: The requested version or `None` if no exact version was requested.
+    """
+    precise = repository_ctx.os.environ.get(CARGO_BAZEL_REPIN_PRECISE, "").strip()
+    if not precise:
+        return None
+
+    update_requests = get_update_requests(repository_ctx)
+    if len(update_requests) != 1:
+        fail("`{}` requires `{}` to name exactly one crate to update. Got: {}".format(
+            CARGO_BAZEL_REPIN_PRECISE,
+            CARGO_BAZEL_REPIN,
+            update_requests,
+        ))
+
+    return precise
+
 def determine_repin(repository_ctx, generator, lockfile_path, lockfile_kind, config, splicing_manifest, cargo, rustc):
     """Use the `cargo-bazel` binary to determine whether or not dpeendencies need to be re-pinned
 
@@ -282,6 +402,10 @@ def determine_repin(repository_ctx, generator, lockfile_path, lockfile_kind, con
         if repository_ctx.os.environ.get(var, "").lower() in ["true", "yes", "1", "on"]:
             return True
 
+    # Requests to update specific crates also require repinning
+    if get_update_requests(repository_ctx):
+        return True
+
     # Cargo lockfiles should always be repinned.
     if lockfile_kind == "cargo":
         return True
@@ -300,7 +424,9 @@ def determine_repin(repository_ctx, generator, lockfile_path, lockfile_kind, con
         cargo,
         "--rustc",
         rustc,
-    ]
+        "--format",
+        "json",
+    ] + get_config_layer_args(repository_ctx)
 
     env = {
         "CARGO": str(cargo),
@@ -320,9 +446,10 @@ def determine_repin(repository_ctx, generator, lockfile_path, lockfile_kind, con
     # If it was determined repinning should occur but there was no
     # flag indicating repinning was requested, an error is raised
     # since repinning should be an explicit action
-    if result.stdout.strip().lower() == "repin":
+    query = json.decode(result.stdout)
+    if query["repin"]:
         # buildifier: disable=print
-        print(result.stderr)
+        print("Repin required ({}): {}".format(query["reason"], query["message"]))
         fail((
             "The current `lockfile` is out of date for '{}'. Please re-run " +
             "bazel using `CARGO_BAZEL_REPIN=true` if this is expected " +
@@ -380,7 +507,7 @@ def execute_generator(
         cargo,
         "--rustc",
         rustc,
-    ]
+    ] + get_config_layer_args(repository_ctx)
 
     env = {
         "RUST_BACKTRACE": "full",
@@ -1,6 +1,6 @@
 """A helper module solving for complex select statements in rendered cargo-bazel modules"""
 
-def select_with_or(input_dict, no_match_error = ""):
+def select_with_or(input_dict, no_match_error = "", deduplicate = True):
     """Drop-in replacement for `select()` that supports ORed keys.
 
     This is notably different from [@bazel_skylib//lib:selects.bzl%selects.with_or][swo] in that
@@ -32,6 +32,9 @@ def select_with_or(input_dict, no_match_error = ""):
             `("//foo:config1", "//foo:config2", ...)` to signify
             `//foo:config1` OR `//foo:config2` OR `...`.
         no_match_error: Optional custom error to report if no condition matches.
+        deduplicate: Whether or not to remove duplicate entries from the values of each key. Order
+            sensitive values such as flags should not be deduplicated. Values of ORed keys are
+            concatenated in the order the keys appear in `input_dict`.
 
     Returns:
         A native `select()`
@@ -49,6 +52,9 @@ def select_with_or(input_dict, no_match_error = ""):
         else:
             output_dict[key] = list(value)
 
+    if not deduplicate:
+        return select(output_dict, no_match_error = no_match_error)
+
     # return a dict with deduped lists
     return select(
         {key: depset(value).to_list() for key, value in output_dict.items()},
@@ -1,6 +1,7 @@
 """Utilities directly related to the `splicing` step of `cargo-bazel`."""
 
 load(":common_utils.bzl", "cargo_environ", "execute")
+load(":generate_utils.bzl", "get_update_precise", "get_update_requests")
 
 def splicing_config(resolver_version = "1"):
     """arious settings used to configure Cargo manifest splicing behavior.
@@ -181,6 +182,18 @@ def splice_workspace_manifest(repository_ctx, generator, lockfile, splicing_mani
             lockfile.path,
         ])
 
+    # Updating specific crates is done by seeding `cargo update` with the existing Cargo lockfile.
+    update_requests = get_update_requests(repository_ctx)
+    if update_requests:
+        if lockfile.kind != "cargo":
+            fail("Updating specific crates ({}) requires a Cargo lockfile".format(update_requests))
+        for request in update_requests:
+            arguments.extend(["--update", request])
+
+    update_precise = get_update_precise(repository_ctx)
+    if update_precise:
+        arguments.extend(["--precise", update_precise])
+
     env = {
         "CARGO": str(cargo),
         "RUSTC": str(rustc),
@@ -3,21 +3,44 @@
 # Each source file is tracked as a target so the `cargo_bootstrap_repository`
 # rule will know to automatically rebuild if any of the sources changed.
 CARGO_BAZEL_SRCS = [
+    "@cargo_bazel//:src/audit.rs",
+    "@cargo_bazel//:src/audit/cvss.rs",
     "@cargo_bazel//:src/cli.rs",
+    "@cargo_bazel//:src/cli/audit.rs",
+    "@cargo_bazel//:src/cli/diff.rs",
+    "@cargo_bazel//:src/cli/dupes.rs",
     "@cargo_bazel//:src/cli/generate.rs",
+    "@cargo_bazel//:src/cli/graph.rs",
+    "@cargo_bazel//:src/cli/licenses.rs",
+    "@cargo_bazel//:src/cli/migrate_raze.rs",
+    "@cargo_bazel//:src/cli/mirror.rs",
+    "@cargo_bazel//:src/cli/populate_cache.rs",
     "@cargo_bazel//:src/cli/query.rs",
+    "@cargo_bazel//:src/cli/sbom.rs",
+    "@cargo_bazel//:src/cli/schema.rs",
     "@cargo_bazel//:src/cli/splice.rs",
+    "@cargo_bazel//:src/cli/tree.rs",
+    "@cargo_bazel//:src/cli/validate.rs",
     "@cargo_bazel//:src/cli/vendor.rs",
+    "@cargo_bazel//:src/cli/why.rs",
     "@cargo_bazel//:src/config.rs",
+    "@cargo_bazel//:src/config/validation.rs",
     "@cargo_bazel//:src/context.rs",
     "@cargo_bazel//:src/context/crate_context.rs",
+    "@cargo_bazel//:src/context/duplicates.rs",
+    "@cargo_bazel//:src/context/graph.rs",
     "@cargo_bazel//:src/context/platforms.rs",
+    "@cargo_bazel//:src/context/tree.rs",
     "@cargo_bazel//:src/lib.rs",
+    "@cargo_bazel//:src/licenses.rs",
     "@cargo_bazel//:src/lockfile.rs",
+    "@cargo_bazel//:src/lockfile/diff.rs",
     "@cargo_bazel//:src/main.rs",
     "@cargo_bazel//:src/metadata.rs",
     "@cargo_bazel//:src/metadata/dependency.rs",
     "@cargo_bazel//:src/metadata/metadata_annotation.rs",
+    "@cargo_bazel//:src/mirror.rs",
+    "@cargo_bazel//:src/raze.rs",
     "@cargo_bazel//:src/rendering.rs",
     "@cargo_bazel//:src/rendering/template_engine.rs",
     "@cargo_bazel//:src/rendering/templates/crate_build_file.j2",
@@ -28,6 +51,7 @@ CARGO_BAZEL_SRCS = [
     "@cargo_bazel//:src/rendering/templates/partials/crate/build_script.j2",
     "@cargo_bazel//:src/rendering/templates/partials/crate/common_attrs.j2",
     "@cargo_bazel//:src/rendering/templates/partials/crate/deps.j2",
+    "@cargo_bazel//:src/rendering/templates/partials/crate/extra_deps.j2",
     "@cargo_bazel//:src/rendering/templates/partials/crate/library.j2",
     "@cargo_bazel//:src/rendering/templates/partials/crate/proc_macro.j2",
     "@cargo_bazel//:src/rendering/templates/partials/header.j2",
@@ -38,12 +62,16 @@ CARGO_BAZEL_SRCS = [
     "@cargo_bazel//:src/rendering/templates/partials/starlark/glob.j2",
     "@cargo_bazel//:src/rendering/templates/partials/starlark/selectable_dict.j2",
     "@cargo_bazel//:src/rendering/templates/partials/starlark/selectable_list.j2",
+    "@cargo_bazel//:src/rendering/templates/partials/starlark/selectable_ordered_list.j2",
     "@cargo_bazel//:src/rendering/templates/vendor_module.j2",
+    "@cargo_bazel//:src/sbom.rs",
+    "@cargo_bazel//:src/schema.rs",
     "@cargo_bazel//:src/splicing.rs",
     "@cargo_bazel//:src/splicing/cargo_config.rs",
     "@cargo_bazel//:src/splicing/splicer.rs",
     "@cargo_bazel//:src/test.rs",
     "@cargo_bazel//:src/utils.rs",
+    "@cargo_bazel//:src/utils/diff.rs",
     "@cargo_bazel//:src/utils/starlark.rs",
     "@cargo_bazel//:src/utils/starlark/glob.rs",
     "@cargo_bazel//:src/utils/starlark/label.rs",
@@ -0,0 +1,684 @@
+//! Offline auditing of a [Context] against a local clone of the
+//! [RustSec advisory database](https://github.com/rustsec/advisory-db)
+
+pub mod cvss;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::config::CrateId;
+use crate::context::Context;
+use crate::metadata::SourceAnnotation;
+use crate::mirror::index_path;
+
+use self::cvss::Severity;
+
+/// The `[advisory]` table of an advisory file
+#[derive(Debug, Deserialize)]
+struct RawAdvisoryMetadata {
+    id: String,
+    package: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    cvss: Option<String>,
+    #[serde(default)]
+    informational: Option<String>,
+    #[serde(default)]
+    withdrawn: Option<String>,
+}
+
+/// The `[versions]` table of an advisory file
+#[derive(Debug, Default, Deserialize)]
+struct RawAdvisoryVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// The contents of an advisory file
+#[derive(Debug, Deserialize)]
+struct RawAdvisory {
+    advisory: RawAdvisoryMetadata,
+    #[serde(default)]
+    versions: RawAdvisoryVersions,
+}
+
+/// A single security advisory for a crate
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Advisory {
+    /// The advisory's identifier. Eg. `RUSTSEC-2020-0071`
+    pub id: String,
+
+    /// The name of the affected crate
+    pub package: String,
+
+    /// A short summary of the advisory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// The date the advisory was reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+
+    /// A URL with more information
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Either `vulnerability` or the kind of informational advisory (Eg. `unmaintained`)
+    pub kind: String,
+
+    /// The advisory's CVSS v3 vector
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cvss: Option<String>,
+
+    /// The base score of the CVSS vector
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+
+    /// The severity rating of the CVSS score
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+
+    /// Versions which contain a fix
+    #[serde(serialize_with = "serialize_version_reqs")]
+    pub patched: Vec<VersionReq>,
+
+    /// Versions which were never affected
+    #[serde(serialize_with = "serialize_version_reqs")]
+    pub unaffected: Vec<VersionReq>,
+}
+
+fn serialize_version_reqs<S: Serializer>(reqs: &[VersionReq], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(reqs.iter().map(|req| req.to_string()))
+}
+
+impl Advisory {
+    /// Parse an advisory from either the Markdown (`.md`) format, where the metadata is a
+    /// fenced `toml` block followed by a `# Title`, or the legacy `.toml` format.
+    fn parse(content: &str, markdown: bool) -> Result<Option<Self>> {
+        let (front_matter, title) = match markdown {
+            true => {
+                let body = content
+                    .trim_start()
+                    .strip_prefix("```toml")
+                    .context("Advisory does not begin with a toml block")?;
+                let (front_matter, rest) = body
+                    .split_once("\n```")
+                    .context("Advisory toml block is not terminated")?;
+                let title = rest
+                    .lines()
+                    .find_map(|line| line.strip_prefix("# "))
+                    .map(|title| title.trim().to_owned());
+                (front_matter, title)
+            }
+            false => (content, None),
+        };
+
+        let raw: RawAdvisory = toml::from_str(front_matter)?;
+
+        // Withdrawn advisories are no longer considered valid
+        if raw.advisory.withdrawn.is_some() {
+            return Ok(None);
+        }
+
+        let parse_reqs = |reqs: &[String]| -> Result<Vec<VersionReq>> {
+            reqs.iter()
+                .map(|req| {
+                    VersionReq::parse(req)
+                        .with_context(|| format!("Invalid version requirement '{}'", req))
+                })
+                .collect()
+        };
+
+        // Unsupported vectors (Eg. CVSS v4) leave the score unknown rather than failing to
+        // load the whole database
+        let score = match &raw.advisory.cvss {
+            Some(vector) => match cvss::base_score(vector) {
+                Ok(score) => Some(score),
+                Err(err) => {
+                    eprintln!(
+                        "Warning: The severity of {} is unknown: {}",
+                        raw.advisory.id, err
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        Ok(Some(Self {
+            id: raw.advisory.id,
+            package: raw.advisory.package,
+            title: raw.advisory.title.or(title),
+            date: raw.advisory.date,
+            url: raw.advisory.url,
+            kind: raw
+                .advisory
+                .informational
+                .unwrap_or_else(|| "vulnerability".to_owned()),
+            cvss: raw.advisory.cvss,
+            score,
+            severity: score.map(Severity::from_score),
+            patched: parse_reqs(&raw.versions.patched)?,
+            unaffected: parse_reqs(&raw.versions.unaffected)?,
+        }))
+    }
+
+    /// Determine whether or not the given version is affected by the advisory
+    pub fn affects(&self, version: &Version) -> bool {
+        !self
+            .patched
+            .iter()
+            .chain(self.unaffected.iter())
+            .any(|req| req.matches(version))
+    }
+
+    /// Whether or not the advisory describes a vulnerability as opposed to
+    /// being informational (Eg. `unmaintained`)
+    pub fn is_vulnerability(&self) -> bool {
+        self.kind == "vulnerability"
+    }
+}
+
+/// A collection of advisories loaded from a local checkout of the advisory database
+#[derive(Debug, Default)]
+pub struct AdvisoryDatabase {
+    /// A mapping of crate names to advisories
+    advisories: BTreeMap<String, Vec<Advisory>>,
+}
+
+impl AdvisoryDatabase {
+    /// Load all advisories from a checkout of the advisory database. The path may either
+    /// be the root of the repository or its `crates` directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let crates_dir = match path.join("crates").is_dir() {
+            true => path.join("crates"),
+            false => path.to_path_buf(),
+        };
+
+        let mut advisories: BTreeMap<String, Vec<Advisory>> = BTreeMap::new();
+        let crate_dirs = fs::read_dir(&crates_dir).with_context(|| {
+            format!("Failed to read advisory database: {}", crates_dir.display())
+        })?;
+        for crate_dir in crate_dirs {
+            let crate_dir = crate_dir?.path();
+            if !crate_dir.is_dir() {
+                continue;
+            }
+
+            for entry in fs::read_dir(&crate_dir)? {
+                let file = entry?.path();
+                let markdown = match file.extension().and_then(|ext| ext.to_str()) {
+                    Some("md") => true,
+                    Some("toml") => false,
+                    _ => continue,
+                };
+
+                let content = fs::read_to_string(&file)?;
+                let advisory = Advisory::parse(&content, markdown)
+                    .with_context(|| format!("Failed to parse advisory: {}", file.display()))?;
+                if let Some(advisory) = advisory {
+                    advisories
+                        .entry(advisory.package.clone())
+                        .or_default()
+                        .push(advisory);
+                }
+            }
+        }
+
+        if advisories.is_empty() {
+            bail!("No advisories were found in {}", crates_dir.display());
+        }
+
+        for list in advisories.values_mut() {
+            list.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+
+        Ok(Self { advisories })
+    }
+
+    /// Find all advisories which affect the given crate
+    pub fn advisories_for(&self, crate_id: &CrateId) -> Vec<&Advisory> {
+        let version = match Version::parse(&crate_id.version) {
+            Ok(version) => version,
+            Err(_) => return Vec::new(),
+        };
+
+        self.advisories
+            .get(&crate_id.name)
+            .map(|advisories| {
+                advisories
+                    .iter()
+                    .filter(|advisory| advisory.affects(&version))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// An advisory which affects a crate in a [Context]
+#[derive(Debug, Serialize)]
+pub struct Finding<'a> {
+    /// The affected crate
+    #[serde(rename = "crate")]
+    pub crate_id: &'a CrateId,
+
+    /// The advisory affecting the crate
+    pub advisory: &'a Advisory,
+
+    /// The shortest path from each workspace member to the affected crate
+    pub paths: Vec<String>,
+}
+
+/// A crate whose pinned version was yanked from its registry
+#[derive(Debug, Serialize)]
+pub struct YankedCrate<'a> {
+    /// The yanked crate
+    #[serde(rename = "crate")]
+    pub crate_id: &'a CrateId,
+
+    /// The shortest path from each workspace member to the yanked crate
+    pub paths: Vec<String>,
+}
+
+/// A version entry in a registry index file
+#[derive(Debug, Deserialize)]
+struct IndexVersion {
+    vers: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// Find the crates in a [Context] whose pinned versions are marked as yanked in a local
+/// checkout of a registry index (Eg. https://github.com/rust-lang/crates.io-index).
+/// Crates which are not downloaded from a registry or are missing from the index are skipped.
+pub fn find_yanked_crates(context: &Context, index_dir: &Path) -> Result<BTreeSet<CrateId>> {
+    let mut yanked = BTreeSet::new();
+    for (id, ctx) in context.crates.iter() {
+        if !matches!(ctx.repository, Some(SourceAnnotation::Http { .. })) {
+            continue;
+        }
+
+        let path = index_dir.join(index_path(&id.name));
+        if !path.exists() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read index file: {}", path.display()))?;
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let entry: IndexVersion = serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse index file: {}", path.display()))?;
+            if entry.yanked && entry.vers == id.version {
+                yanked.insert(id.clone());
+            }
+        }
+    }
+
+    Ok(yanked)
+}
+
+/// Render the shortest path from each workspace member to a crate
+fn render_paths(context: &Context, crate_id: &CrateId) -> Vec<String> {
+    context
+        .shortest_dependency_paths(crate_id)
+        .iter()
+        .map(|path| {
+            std::iter::once(path[0].source.to_string())
+                .chain(path.iter().map(|edge| edge.target.to_string()))
+                .collect::<Vec<String>>()
+                .join(" -> ")
+        })
+        .collect()
+}
+
+/// The results of auditing a [Context]
+#[derive(Debug, Serialize)]
+pub struct AuditReport<'a> {
+    pub findings: Vec<Finding<'a>>,
+
+    /// Crates whose pinned versions were yanked. See [find_yanked_crates].
+    pub yanked: Vec<YankedCrate<'a>>,
+}
+
+impl<'a> AuditReport<'a> {
+    pub fn new(
+        context: &'a Context,
+        database: &'a AdvisoryDatabase,
+        yanked: &BTreeSet<CrateId>,
+    ) -> Self {
+        let findings = context
+            .crates
+            .keys()
+            .flat_map(|id| {
+                database
+                    .advisories_for(id)
+                    .into_iter()
+                    .map(move |advisory| (id, advisory))
+            })
+            .map(|(id, advisory)| Finding {
+                crate_id: id,
+                advisory,
+                paths: render_paths(context, id),
+            })
+            .collect();
+
+        let yanked = context
+            .crates
+            .keys()
+            .filter(|id| yanked.contains(id))
+            .map(|id| YankedCrate {
+                crate_id: id,
+                paths: render_paths(context, id),
+            })
+            .collect();
+
+        Self { findings, yanked }
+    }
+
+    /// Collect the findings which should cause an audit to fail. Vulnerabilities fail
+    /// when they meet the severity threshold or have no severity to compare. Informational
+    /// advisories only fail when `deny_informational` is set.
+    pub fn failures(&self, threshold: Severity, deny_informational: bool) -> Vec<&Finding<'a>> {
+        self.findings
+            .iter()
+            .filter(|finding| match finding.advisory.is_vulnerability() {
+                true => finding
+                    .advisory
+                    .severity
+                    .map(|severity| severity >= threshold)
+                    .unwrap_or(true),
+                false => deny_informational,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for AuditReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.findings.is_empty() {
+            true => writeln!(f, "No advisories found")?,
+            false => writeln!(f, "Found {} advisories", self.findings.len())?,
+        }
+        for finding in self.findings.iter() {
+            let advisory = finding.advisory;
+            writeln!(f)?;
+            write!(f, "{} ({}", advisory.id, advisory.kind)?;
+            if let (Some(severity), Some(score)) = (advisory.severity, advisory.score) {
+                write!(f, ", severity: {} {:.1}", severity, score)?;
+            }
+            writeln!(f, ")")?;
+            writeln!(f, "  Crate:   {}", finding.crate_id)?;
+            if let Some(title) = &advisory.title {
+                writeln!(f, "  Title:   {}", title)?;
+            }
+            if let Some(url) = &advisory.url {
+                writeln!(f, "  URL:     {}", url)?;
+            }
+            let patched: Vec<String> = advisory.patched.iter().map(|r| r.to_string()).collect();
+            match patched.is_empty() {
+                true => writeln!(f, "  Patched: no patched versions")?,
+                false => writeln!(f, "  Patched: {}", patched.join(", "))?,
+            }
+            if !finding.paths.is_empty() {
+                writeln!(f, "  Paths:")?;
+                for path in finding.paths.iter() {
+                    writeln!(f, "    {}", path)?;
+                }
+            }
+        }
+
+        for yanked in self.yanked.iter() {
+            writeln!(f)?;
+            writeln!(f, "{} (yanked)", yanked.crate_id)?;
+            if !yanked.paths.is_empty() {
+                writeln!(f, "  Paths:")?;
+                for path in yanked.paths.iter() {
+                    writeln!(f, "    {}", path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::config::Config;
+    use crate::metadata::Annotations;
+
+    fn mock_context() -> Context {
+        let annotations = Annotations::new(
+            crate::test::metadata::build_scripts(),
+            crate::test::lockfile::build_scripts(),
+            Config::default(),
+        )
+        .unwrap();
+
+        Context::new(annotations).unwrap()
+    }
+
+    fn mock_database() -> (tempfile::TempDir, AdvisoryDatabase) {
+        let dir = tempfile::tempdir().unwrap();
+
+        let write = |name: &str, file: &str, content: &str| {
+            let crate_dir = dir.path().join("crates").join(name);
+            fs::create_dir_all(&crate_dir).unwrap();
+            fs::write(crate_dir.join(file), content).unwrap();
+        };
+
+        write(
+            "libc",
+            "RUSTSEC-0000-0001.md",
+            r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "libc"
+date = "2022-01-01"
+url = "https://example.com/RUSTSEC-0000-0001"
+cvss = "CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:N/A:N"
+
+[versions]
+patched = [">= 0.2.113"]
+unaffected = ["< 0.2.0"]
+```
+
+# Something is wrong with libc
+
+A description
+"#,
+        );
+        write(
+            "libc",
+            "RUSTSEC-0000-0002.md",
+            r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0002"
+package = "libc"
+date = "2022-01-01"
+withdrawn = "2022-02-01"
+
+[versions]
+patched = []
+```
+
+# A withdrawn advisory
+"#,
+        );
+        write(
+            "cfg-if",
+            "RUSTSEC-0000-0003.toml",
+            r#"[advisory]
+id = "RUSTSEC-0000-0003"
+package = "cfg-if"
+title = "cfg-if is unmaintained"
+date = "2022-01-01"
+informational = "unmaintained"
+
+[versions]
+patched = []
+"#,
+        );
+        write(
+            "openssl",
+            "RUSTSEC-0000-0004.md",
+            r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0004"
+package = "openssl"
+date = "2022-01-01"
+
+[versions]
+patched = [">= 0.10.35"]
+```
+
+# Already patched
+"#,
+        );
+
+        let database = AdvisoryDatabase::load(dir.path()).unwrap();
+        (dir, database)
+    }
+
+    #[test]
+    fn parse_markdown_advisory() {
+        let (_dir, database) = mock_database();
+        let advisory = &database.advisories["libc"][0];
+
+        assert_eq!(advisory.id, "RUSTSEC-0000-0001");
+        assert_eq!(
+            advisory.title,
+            Some("Something is wrong with libc".to_owned())
+        );
+        assert_eq!(advisory.kind, "vulnerability");
+        assert_eq!(advisory.score, Some(5.9));
+        assert_eq!(advisory.severity, Some(Severity::Medium));
+
+        // Withdrawn advisories are ignored
+        assert_eq!(database.advisories["libc"].len(), 1);
+    }
+
+    #[test]
+    fn version_matching() {
+        let (_dir, database) = mock_database();
+        let advisory = &database.advisories["libc"][0];
+
+        assert!(advisory.affects(&Version::parse("0.2.112").unwrap()));
+        assert!(!advisory.affects(&Version::parse("0.2.113").unwrap()));
+        assert!(!advisory.affects(&Version::parse("0.1.0").unwrap()));
+    }
+
+    #[test]
+    fn audit_context() {
+        let context = mock_context();
+        let (_dir, database) = mock_database();
+        let report = AuditReport::new(&context, &database, &BTreeSet::new());
+
+        let found: Vec<(String, &str)> = report
+            .findings
+            .iter()
+            .map(|f| (f.crate_id.to_string(), f.advisory.id.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("cfg-if 1.0.0".to_owned(), "RUSTSEC-0000-0003"),
+                ("libc 0.2.112".to_owned(), "RUSTSEC-0000-0001"),
+            ]
+        );
+
+        let libc = &report.findings[1];
+        assert!(libc
+            .paths
+            .contains(&"build-scripts 0.1.0 -> openssl 0.10.36 -> libc 0.2.112".to_owned()));
+    }
+
+    #[test]
+    fn unsupported_cvss_vector() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path().join("crates/libc");
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(
+            crate_dir.join("RUSTSEC-0000-0005.toml"),
+            r#"[advisory]
+id = "RUSTSEC-0000-0005"
+package = "libc"
+date = "2022-01-01"
+cvss = "CVSS:4.0/AV:N/AC:L/AT:N/PR:N/UI:N/VC:H/VI:H/VA:H/SC:N/SI:N/SA:N"
+
+[versions]
+patched = []
+"#,
+        )
+        .unwrap();
+
+        let database = AdvisoryDatabase::load(dir.path()).unwrap();
+        let advisory = &database.advisories["libc"][0];
+        assert_eq!(advisory.score, None);
+        assert_eq!(advisory.severity, None);
+    }
+
+    #[test]
+    fn yanked_crates() {
+        let context = mock_context();
+        let (_dir, database) = mock_database();
+
+        let index = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = index.path().join(index_path(name));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "libc",
+            concat!(
+                "{\"name\":\"libc\",\"vers\":\"0.2.111\",\"yanked\":false}\n",
+                "{\"name\":\"libc\",\"vers\":\"0.2.112\",\"yanked\":true}\n",
+            ),
+        );
+        write(
+            "cfg-if",
+            "{\"name\":\"cfg-if\",\"vers\":\"1.0.0\",\"yanked\":false}\n",
+        );
+
+        let yanked = find_yanked_crates(&context, index.path()).unwrap();
+        assert_eq!(
+            yanked,
+            BTreeSet::from([CrateId::new("libc".to_owned(), "0.2.112".to_owned())])
+        );
+
+        let report = AuditReport::new(&context, &database, &yanked);
+        assert_eq!(report.yanked.len(), 1);
+        assert!(report.yanked[0]
+            .paths
+            .contains(&"build-scripts 0.1.0 -> openssl 0.10.36 -> libc 0.2.112".to_owned()));
+        assert!(report.to_string().contains("libc 0.2.112 (yanked)"));
+    }
+
+    #[test]
+    fn audit_thresholds() {
+        let context = mock_context();
+        let (_dir, database) = mock_database();
+        let report = AuditReport::new(&context, &database, &BTreeSet::new());
+
+        assert_eq!(report.failures(Severity::Medium, false).len(), 1);
+        assert_eq!(report.failures(Severity::High, false).len(), 0);
+        assert_eq!(report.failures(Severity::High, true).len(), 1);
+        assert_eq!(report.failures(Severity::Low, true).len(), 2);
+    }
+}
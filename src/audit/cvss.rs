@@ -0,0 +1,199 @@
+//! Scoring of [CVSS v3](https://www.first.org/cvss/v3.1/specification-document) vectors
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+
+/// The qualitative severity rating of a CVSS score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    None,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Determine the severity rating of a CVSS base score
+    pub fn from_score(score: f64) -> Self {
+        if score >= 9.0 {
+            Self::Critical
+        } else if score >= 7.0 {
+            Self::High
+        } else if score >= 4.0 {
+            Self::Medium
+        } else if score > 0.0 {
+            Self::Low
+        } else {
+            Self::None
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            "critical" => Ok(Self::Critical),
+            _ => bail!("Unknown Severity: '{}'", s),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self {
+            Self::None => "none",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        };
+        f.write_str(severity)
+    }
+}
+
+/// Compute the base score of a CVSS v3 vector string.
+/// Eg. `CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H`
+pub fn base_score(vector: &str) -> Result<f64> {
+    let mut parts = vector.split('/');
+    match parts.next() {
+        Some("CVSS:3.0") | Some("CVSS:3.1") => {}
+        _ => bail!("Unsupported CVSS vector: '{}'", vector),
+    }
+
+    let metrics: BTreeMap<&str, &str> = parts
+        .map(|part| {
+            part.split_once(':')
+                .ok_or_else(|| anyhow!("Malformed CVSS metric '{}' in '{}'", part, vector))
+        })
+        .collect::<Result<_>>()?;
+
+    let metric = |name: &str| -> Result<&str> {
+        metrics
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("CVSS vector '{}' is missing the '{}' metric", vector, name))
+    };
+    let invalid = |name: &str| anyhow!("Invalid value for '{}' in CVSS vector '{}'", name, vector);
+
+    let scope_changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return Err(invalid("S")),
+    };
+
+    let attack_vector = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return Err(invalid("AV")),
+    };
+    let attack_complexity = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return Err(invalid("AC")),
+    };
+    let privileges_required = match (metric("PR")?, scope_changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return Err(invalid("PR")),
+    };
+    let user_interaction = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return Err(invalid("UI")),
+    };
+    let impact_metric = |name: &str| -> Result<f64> {
+        match metric(name)? {
+            "H" => Ok(0.56),
+            "L" => Ok(0.22),
+            "N" => Ok(0.0),
+            _ => Err(invalid(name)),
+        }
+    };
+
+    let iss = 1.0
+        - ((1.0 - impact_metric("C")?) * (1.0 - impact_metric("I")?) * (1.0 - impact_metric("A")?));
+    let impact = match scope_changed {
+        false => 6.42 * iss,
+        true => 7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15),
+    };
+    let exploitability =
+        8.22 * attack_vector * attack_complexity * privileges_required * user_interaction;
+
+    if impact <= 0.0 {
+        return Ok(0.0);
+    }
+
+    Ok(match scope_changed {
+        false => round_up((impact + exploitability).min(10.0)),
+        true => round_up((1.08 * (impact + exploitability)).min(10.0)),
+    })
+}
+
+/// The `Roundup` function as defined by the CVSS v3.1 specification
+fn round_up(value: f64) -> f64 {
+    let int_input = (value * 100_000.0).round() as i64;
+    if int_input % 10_000 == 0 {
+        int_input as f64 / 100_000.0
+    } else {
+        ((int_input / 10_000) + 1) as f64 / 10.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scores() {
+        assert_eq!(
+            base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H").unwrap(),
+            9.8
+        );
+        assert_eq!(
+            base_score("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:N/A:N").unwrap(),
+            5.9
+        );
+        assert_eq!(
+            base_score("CVSS:3.0/AV:N/AC:L/PR:L/UI:N/S:C/C:L/I:L/A:N").unwrap(),
+            6.4
+        );
+        assert_eq!(
+            base_score("CVSS:3.1/AV:L/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N").unwrap(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn invalid_vectors() {
+        assert!(base_score("CVSS:2.0/AV:N").is_err());
+        assert!(base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H").is_err());
+        assert!(base_score("CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H").is_err());
+    }
+
+    #[test]
+    fn severities() {
+        assert_eq!(Severity::from_score(0.0), Severity::None);
+        assert_eq!(Severity::from_score(3.9), Severity::Low);
+        assert_eq!(Severity::from_score(4.0), Severity::Medium);
+        assert_eq!(Severity::from_score(7.5), Severity::High);
+        assert_eq!(Severity::from_score(9.8), Severity::Critical);
+        assert!(Severity::High > Severity::Medium);
+    }
+}
@@ -1,22 +1,64 @@
 //! Command line interface entry points and utilities
 
+mod audit;
+mod diff;
+mod dupes;
 mod generate;
+mod graph;
+mod licenses;
+mod migrate_raze;
+mod mirror;
+mod populate_cache;
 mod query;
+mod sbom;
+mod schema;
 mod splice;
+mod tree;
+mod validate;
 mod vendor;
+mod why;
 
+use std::str::FromStr;
+
+use anyhow::bail;
 use clap::Parser;
 
+use self::audit::AuditOptions;
+use self::diff::DiffOptions;
+use self::dupes::DupesOptions;
 use self::generate::GenerateOptions;
+use self::graph::GraphOptions;
+use self::licenses::LicensesOptions;
+use self::migrate_raze::MigrateRazeOptions;
+use self::mirror::MirrorOptions;
+use self::populate_cache::PopulateCacheOptions;
 use self::query::QueryOptions;
+use self::sbom::SbomOptions;
+use self::schema::SchemaOptions;
 use self::splice::SpliceOptions;
+use self::tree::TreeOptions;
+use self::validate::ValidateOptions;
 use self::vendor::VendorOptions;
+use self::why::WhyOptions;
 
 // Entrypoints
+pub use audit::audit;
+pub use diff::diff;
+pub use dupes::dupes;
 pub use generate::generate;
+pub use graph::graph;
+pub use licenses::licenses;
+pub use migrate_raze::migrate_raze;
+pub use mirror::mirror;
+pub use populate_cache::populate_cache;
 pub use query::query;
+pub use sbom::sbom;
+pub use schema::schema;
 pub use splice::splice;
+pub use tree::tree;
+pub use validate::validate;
 pub use vendor::vendor;
+pub use why::why;
 
 #[derive(Parser, Debug)]
 #[clap(name = "cargo-bazel", about, version)]
@@ -32,6 +74,67 @@ pub enum Options {
 
     /// Vendor BUILD files to the workspace with either repository definitions or `cargo vendor` generated sources.
     Vendor(VendorOptions),
+
+    /// Compare two Bazel lockfiles and report how the pinned crates changed.
+    Diff(DiffOptions),
+
+    /// Explain why a crate is included in the dependency graph of a Bazel lockfile.
+    Why(WhyOptions),
+
+    /// Print the dependency tree of a workspace member for a single platform triple.
+    Tree(TreeOptions),
+
+    /// Export the dependency graph of a Bazel lockfile as Graphviz DOT or json.
+    Graph(GraphOptions),
+
+    /// Report the licenses of all third party crates in a Bazel lockfile.
+    Licenses(LicensesOptions),
+
+    /// Audit a Bazel lockfile against a local clone of the RustSec advisory database.
+    Audit(AuditOptions),
+
+    /// Validate a config file, reporting all problems at once.
+    Validate(ValidateOptions),
+
+    /// Translate cargo-raze settings from Cargo manifests into a config file.
+    MigrateRaze(MigrateRazeOptions),
+
+    /// List crates which appear at more than one version in a Bazel lockfile.
+    Dupes(DupesOptions),
+
+    /// Export a software bill of materials in the CycloneDX or SPDX format.
+    Sbom(SbomOptions),
+
+    /// Export the `.crate` files of pinned crates into a local Cargo registry.
+    Mirror(MirrorOptions),
+
+    /// Copy the `.crate` files of pinned crates into a Bazel repository cache.
+    PopulateCache(PopulateCacheOptions),
+
+    /// Print the JSON Schemas of config files, splicing manifests and Bazel lockfiles.
+    Schema(SchemaOptions),
+}
+
+/// The format used for reports printed to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+
+    /// Machine readable json
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown OutputFormat: '{}'", s),
+        }
+    }
 }
 
 // Convenience wrappers to avoid dependencies in the binary
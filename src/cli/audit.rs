@@ -0,0 +1,90 @@
+//! The cli entrypoint for the `audit` subcommand
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::audit::cvss::Severity;
+use crate::audit::{find_yanked_crates, AdvisoryDatabase, AuditReport};
+use crate::cli::OutputFormat;
+use crate::context::Context;
+
+/// Command line options for the `audit` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct AuditOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The path to a local clone of the RustSec advisory database
+    /// (https://github.com/rustsec/advisory-db)
+    #[clap(long)]
+    pub advisory_db: PathBuf,
+
+    /// The minimum severity of vulnerabilities which cause the audit to fail. One of
+    /// `none`, `low`, `medium`, `high`, or `critical`. Vulnerabilities without a CVSS
+    /// score always fail the audit.
+    #[clap(long, default_value = "low")]
+    pub severity_threshold: Severity,
+
+    /// If set, informational advisories (Eg. `unmaintained` crates) also fail the audit
+    #[clap(long)]
+    pub deny_informational: bool,
+
+    /// The path to a local clone of the crates.io index
+    /// (https://github.com/rust-lang/crates.io-index) used to report yanked crates
+    #[clap(long)]
+    pub registry_index: Option<PathBuf>,
+
+    /// If set, yanked crates also fail the audit. Requires `--registry-index`.
+    #[clap(long, requires = "registry_index")]
+    pub deny_yanked: bool,
+
+    /// The format of the report. Either `text` or `json`.
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+/// Report all advisories affecting crates in a lockfile
+pub fn audit(opt: AuditOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let database = AdvisoryDatabase::load(&opt.advisory_db)?;
+    let yanked = match &opt.registry_index {
+        Some(index) => find_yanked_crates(&context, index)?,
+        None => BTreeSet::new(),
+    };
+    let report = AuditReport::new(&context, &database, &yanked);
+
+    match opt.format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    let mut failures: Vec<String> = report
+        .failures(opt.severity_threshold, opt.deny_informational)
+        .iter()
+        .map(|finding| format!("{} ({})", finding.advisory.id, finding.crate_id))
+        .collect();
+    if opt.deny_yanked {
+        failures.extend(
+            report
+                .yanked
+                .iter()
+                .map(|yanked| format!("yanked ({})", yanked.crate_id)),
+        );
+    }
+    if !failures.is_empty() {
+        bail!(
+            "{} issues failed the audit: {}",
+            failures.len(),
+            failures.join(", ")
+        );
+    }
+
+    Ok(())
+}
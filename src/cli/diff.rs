@@ -0,0 +1,44 @@
+//! The cli entrypoint for the `diff` subcommand
+
+use std::path::PathBuf;
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::cli::OutputFormat;
+use crate::context::Context;
+use crate::lockfile::diff::LockfileDiff;
+
+/// Command line options for the `diff` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct DiffOptions {
+    /// The path to the original Bazel lockfile
+    #[clap(long)]
+    pub old: PathBuf,
+
+    /// The path to the updated Bazel lockfile
+    #[clap(long)]
+    pub new: PathBuf,
+
+    /// The format of the report. Either `text` or `json`.
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+/// Print a report of the differences between two lockfiles
+pub fn diff(opt: DiffOptions) -> Result<()> {
+    let old = Context::try_from_path(&opt.old)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.old.display()))?;
+    let new = Context::try_from_path(&opt.new)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.new.display()))?;
+
+    let diff = LockfileDiff::new(&old, &new);
+
+    match opt.format {
+        OutputFormat::Text => print!("{}", diff),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+    }
+
+    Ok(())
+}
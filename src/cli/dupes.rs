@@ -0,0 +1,53 @@
+//! The cli entrypoint for the `dupes` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::cli::OutputFormat;
+use crate::context::duplicates::DuplicatesReport;
+use crate::context::Context;
+
+/// Command line options for the `dupes` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct DupesOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The path to a Cargo metadata `json` file of the spliced workspace. This is required
+    /// to report the semver requirements of dependents and unification hints.
+    #[clap(long)]
+    pub metadata: Option<PathBuf>,
+
+    /// The format of the report. Either `text` or `json`.
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+/// Report all crates which appear at more than one version
+pub fn dupes(opt: DupesOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let metadata = match &opt.metadata {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to load Cargo Metadata: {}", path.display()))?;
+            Some(serde_json::from_str(&content).context("Unable to deserialize Cargo metadata")?)
+        }
+        None => None,
+    };
+
+    let report = DuplicatesReport::new(&context, metadata.as_ref());
+
+    match opt.format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    Ok(())
+}
@@ -1,16 +1,19 @@
 //! The cli entrypoint for the `generate` subcommand
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Result};
 use clap::Parser;
 
 use crate::config::Config;
 use crate::context::Context;
-use crate::lockfile::{is_cargo_lockfile, lock_context, write_lockfile, LockfileKind};
+use crate::licenses::enforce_license_policy;
+use crate::lockfile::{
+    is_cargo_lockfile, lock_context, lockfile_content, write_lockfile, LockfileKind,
+};
 use crate::metadata::load_metadata;
 use crate::metadata::Annotations;
-use crate::rendering::{write_outputs, Renderer};
+use crate::rendering::{check_outputs, write_outputs, OutputCheck, Renderer};
 use crate::splicing::SplicingManifest;
 
 /// Command line options for the `generate` subcommand
@@ -25,9 +28,22 @@ pub struct GenerateOptions {
     #[clap(long, env = "RUSTC")]
     pub rustc: Option<PathBuf>,
 
-    /// The config file with information about the Bazel and Cargo workspace
+    /// The config file with information about the Bazel and Cargo workspace. If provided
+    /// multiple times, each config is layered over the ones before it.
+    #[clap(long, required = true)]
+    pub config: Vec<PathBuf>,
+
+    /// A config shared between workspaces (Eg. an organisation wide base) which all `--config`
+    /// layers are applied over. Its annotations are not required to match any crates. May be
+    /// provided multiple times.
+    #[clap(long)]
+    pub base_config: Vec<PathBuf>,
+
+    /// A directory of `json` files containing crate annotations to apply beneath those of
+    /// the configs. These annotations are not required to match any crates. May be provided
+    /// multiple times.
     #[clap(long)]
-    pub config: PathBuf,
+    pub annotations_dir: Vec<PathBuf>,
 
     /// A generated manifest of splicing inputs
     #[clap(long)]
@@ -61,11 +77,30 @@ pub struct GenerateOptions {
     /// If true, outputs will be printed instead of written to disk.
     #[clap(long)]
     pub dry_run: bool,
+
+    /// If true, outputs are compared to the files on disk instead of being written.
+    /// A diff of any out of date files is printed and a non-zero exit code is returned.
+    #[clap(long)]
+    pub check: bool,
+}
+
+/// Print the results of checking generated outputs and fail if anything is out of date.
+fn report_check(check: &OutputCheck) -> Result<()> {
+    if check.is_empty() {
+        return Ok(());
+    }
+
+    print!("{}", check);
+    bail!(
+        "Generated files are out of date ({} stale, {} missing). Rerun without `--check` to update them.",
+        check.stale.len(),
+        check.missing.len()
+    )
 }
 
 pub fn generate(opt: GenerateOptions) -> Result<()> {
     // Load the config
-    let config = Config::try_from_path(&opt.config)?;
+    let config = Config::try_from_layers(&opt.base_config, &opt.config, &opt.annotations_dir)?;
 
     // Determine if the dependencies need to be repinned.
     let mut should_repin = opt.repin;
@@ -79,9 +114,19 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
     if !should_repin {
         let context = Context::try_from_path(opt.lockfile)?;
 
+        // Ensure all crates satisfy the license policy
+        if let Some(policy) = &config.license_policy {
+            enforce_license_policy(policy, &context)?;
+        }
+
         // Render build files
         let outputs = Renderer::new(config.rendering).render(&context)?;
 
+        // Compare the outputs to what's on disk if requested
+        if opt.check {
+            return report_check(&check_outputs(&outputs, &opt.repository_dir)?);
+        }
+
         // Write the outputs to disk
         write_outputs(outputs, &opt.repository_dir, opt.dry_run)?;
 
@@ -123,11 +168,22 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
     // Generate renderable contexts for earch package
     let context = Context::new(annotations)?;
 
+    // Ensure all crates satisfy the license policy
+    if let Some(policy) = &config.license_policy {
+        enforce_license_policy(policy, &context)?;
+    }
+
     // Render build files
     let outputs = Renderer::new(render_config).render(&context)?;
 
-    // Write outputs
-    write_outputs(outputs, &opt.repository_dir, opt.dry_run)?;
+    // Compare the outputs to what's on disk if requested
+    let mut check = match opt.check {
+        true => Some(check_outputs(&outputs, &opt.repository_dir)?),
+        false => {
+            write_outputs(outputs, &opt.repository_dir, opt.dry_run)?;
+            None
+        }
+    };
 
     // Ensure Bazel lockfiles are written to disk so future generations can be short-circuted.
     if matches!(opt.lockfile_kind, LockfileKind::Bazel) {
@@ -135,8 +191,16 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
 
         let lockfile = lock_context(context, &config, &splicing_manifest, cargo_bin, rustc_bin)?;
 
-        write_lockfile(lockfile, &opt.lockfile, opt.dry_run)?;
+        match &mut check {
+            Some(check) => {
+                check.compare(Path::new(""), &opt.lockfile, &lockfile_content(&lockfile)?)?
+            }
+            None => write_lockfile(lockfile, &opt.lockfile, opt.dry_run)?,
+        }
     }
 
-    Ok(())
+    match check {
+        Some(check) => report_check(&check),
+        None => Ok(()),
+    }
 }
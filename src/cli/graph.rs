@@ -0,0 +1,69 @@
+//! The cli entrypoint for the `graph` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::context::Context;
+
+/// Command line options for the `graph` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct GraphOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The format of the graph. Either `dot` or `json`.
+    #[clap(long, default_value = "dot")]
+    pub format: GraphFormat,
+
+    /// An optional path to write the graph to. If unset, the graph is printed to stdout.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// The formats a dependency graph can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// The Graphviz DOT language
+    Dot,
+
+    /// A json node and edge list
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "dot" => Ok(Self::Dot),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown GraphFormat: '{}'", s),
+        }
+    }
+}
+
+/// Export the dependency graph of a lockfile
+pub fn graph(opt: GraphOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let graph = context.dependency_graph();
+    let content = match opt.format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Json => serde_json::to_string_pretty(&graph)? + "\n",
+    };
+
+    match &opt.output {
+        Some(path) => fs::write(path, content)
+            .with_context(|| format!("Failed to write graph: {}", path.display()))?,
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
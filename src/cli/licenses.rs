@@ -0,0 +1,82 @@
+//! The cli entrypoint for the `licenses` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::licenses::{enforce_license_policy, LicenseReport};
+
+/// Command line options for the `licenses` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct LicensesOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The format of the report. One of `csv`, `json`, or `markdown`.
+    #[clap(long, default_value = "markdown")]
+    pub format: LicenseReportFormat,
+
+    /// An optional path to write the report to. If unset, the report is printed to stdout.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+
+    /// An optional config file whose license policy should be enforced
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+}
+
+/// The formats a license report can be written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LicenseReportFormat {
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl FromStr for LicenseReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => bail!("Unknown LicenseReportFormat: '{}'", s),
+        }
+    }
+}
+
+/// Write a report of the licenses used by all third party crates
+pub fn licenses(opt: LicensesOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let report = LicenseReport::new(&context);
+    let content = match opt.format {
+        LicenseReportFormat::Csv => report.to_csv(),
+        LicenseReportFormat::Json => serde_json::to_string_pretty(&report)? + "\n",
+        LicenseReportFormat::Markdown => report.to_markdown(),
+    };
+
+    match &opt.output {
+        Some(path) => fs::write(path, content)
+            .with_context(|| format!("Failed to write report: {}", path.display()))?,
+        None => print!("{}", content),
+    }
+
+    if let Some(config) = &opt.config {
+        let config = Config::try_from_path(config)?;
+        if let Some(policy) = &config.license_policy {
+            enforce_license_policy(policy, &context)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! The cli entrypoint for the `migrate-raze` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::raze::RazeMigration;
+
+/// Command line options for the `migrate-raze` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct MigrateRazeOptions {
+    /// Cargo manifests (`Cargo.toml` files) containing `[*.metadata.raze]` settings
+    #[clap(long = "manifest", required = true)]
+    pub manifests: Vec<PathBuf>,
+
+    /// An optional path to write the config to. If unset, the config is printed to stdout.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Remove all `null` values from a json object so only meaningful settings are rendered
+fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// Translate cargo-raze settings into a cargo-bazel config
+pub fn migrate_raze(opt: MigrateRazeOptions) -> Result<()> {
+    let migration = RazeMigration::from_manifests(&opt.manifests)?;
+
+    for setting in migration.unsupported.iter() {
+        eprintln!("Warning: {}", setting);
+    }
+
+    let mut config = serde_json::to_value(&migration.config)?;
+    strip_nulls(&mut config);
+    let content = serde_json::to_string_pretty(&config)? + "\n";
+
+    match &opt.output {
+        Some(path) => fs::write(path, content)
+            .with_context(|| format!("Failed to write config: {}", path.display()))?,
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
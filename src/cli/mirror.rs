@@ -0,0 +1,68 @@
+//! The cli entrypoint for the `mirror` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::context::Context;
+use crate::mirror::{mirror_crates, render_cargo_config};
+
+/// Command line options for the `mirror` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct MirrorOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// A directory containing `.crate` files (Eg. `~/.cargo/registry/cache`). The directory
+    /// is searched recursively.
+    #[clap(long)]
+    pub crates_dir: PathBuf,
+
+    /// The directory in which to create the local registry
+    #[clap(long)]
+    pub output: PathBuf,
+
+    /// The path to write a Cargo config which replaces crates.io with the local registry.
+    /// If unset, the config is printed to stdout.
+    #[clap(long)]
+    pub cargo_config: Option<PathBuf>,
+}
+
+/// Export the `.crate` files of all pinned crates into a local Cargo registry
+pub fn mirror(opt: MirrorOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    fs::create_dir_all(&opt.output)
+        .with_context(|| format!("Failed to create directory: {}", opt.output.display()))?;
+    let registry_dir = opt.output.canonicalize()?;
+
+    let report = mirror_crates(&context, &opt.crates_dir, &registry_dir)?;
+    eprint!("{}", report);
+
+    if report.is_incomplete() {
+        bail!(
+            "Failed to mirror {} crate(s) into {}",
+            report.failures(),
+            registry_dir.display()
+        );
+    }
+
+    let cargo_config = render_cargo_config(&registry_dir);
+    match &opt.cargo_config {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, cargo_config)
+                .with_context(|| format!("Failed to write file: {}", path.display()))?
+        }
+        None => print!("{}", cargo_config),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! The cli entrypoint for the `populate-cache` subcommand
+
+use std::env;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::context::Context;
+use crate::mirror::populate_cache as populate;
+
+/// Command line options for the `populate-cache` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct PopulateCacheOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// A directory containing `.crate` files. The directory is searched recursively.
+    /// Defaults to `$CARGO_HOME/registry/cache`.
+    #[clap(long)]
+    pub crates_dir: Option<PathBuf>,
+
+    /// The path to a Bazel repository cache (see `bazel info repository_cache`)
+    #[clap(long)]
+    pub repository_cache: PathBuf,
+}
+
+/// Locate the default directory of `.crate` files downloaded by Cargo
+fn default_crates_dir() -> Result<PathBuf> {
+    let cargo_home = match env::var_os("CARGO_HOME") {
+        Some(cargo_home) => PathBuf::from(cargo_home),
+        None => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".cargo"),
+            None => bail!("Unable to locate CARGO_HOME. Please pass `--crates-dir`"),
+        },
+    };
+
+    Ok(cargo_home.join("registry").join("cache"))
+}
+
+/// Copy the `.crate` files of all pinned crates into a Bazel repository cache
+pub fn populate_cache(opt: PopulateCacheOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let crates_dir = match opt.crates_dir {
+        Some(dir) => dir,
+        None => default_crates_dir()?,
+    };
+
+    let report = populate(&context, &crates_dir, &opt.repository_cache)?;
+    eprint!("{}", report);
+
+    if report.is_incomplete() {
+        bail!(
+            "Failed to copy {} crate(s) into {}",
+            report.failures(),
+            opt.repository_cache.display()
+        );
+    }
+
+    Ok(())
+}
@@ -5,10 +5,12 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::Parser;
+use serde::Serialize;
 
+use crate::cli::OutputFormat;
 use crate::config::Config;
 use crate::context::Context;
-use crate::lockfile::Digest;
+use crate::lockfile::{Digest, DigestInputs};
 use crate::splicing::SplicingManifest;
 
 /// Command line options for the `query` subcommand
@@ -19,9 +21,22 @@ pub struct QueryOptions {
     #[clap(long)]
     pub lockfile: PathBuf,
 
-    /// The config file with information about the Bazel and Cargo workspace
+    /// The config file with information about the Bazel and Cargo workspace. If provided
+    /// multiple times, each config is layered over the ones before it.
+    #[clap(long, required = true)]
+    pub config: Vec<PathBuf>,
+
+    /// A config shared between workspaces (Eg. an organisation wide base) which all `--config`
+    /// layers are applied over. Its annotations are not required to match any crates. May be
+    /// provided multiple times.
+    #[clap(long)]
+    pub base_config: Vec<PathBuf>,
+
+    /// A directory of `json` files containing crate annotations to apply beneath those of
+    /// the configs. These annotations are not required to match any crates. May be provided
+    /// multiple times.
     #[clap(long)]
-    pub config: PathBuf,
+    pub annotations_dir: Vec<PathBuf>,
 
     /// A generated manifest of splicing inputs
     #[clap(long)]
@@ -34,30 +49,130 @@ pub struct QueryOptions {
     /// The path to a rustc binary for use with Cargo
     #[clap(long, env = "RUSTC")]
     pub rustc: PathBuf,
+
+    /// The format of the result. Either `text` or `json`.
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+/// The reason a lockfile needs to be re-pinned
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RepinReason {
+    /// The lockfile could not be read
+    MissingLockfile,
+
+    /// The lockfile could not be deserialized
+    UnparsableLockfile,
+
+    /// The lockfile has no digest
+    MissingDigest,
+
+    /// The digest in the lockfile does not match the current inputs
+    DigestMismatch,
+}
+
+/// Details about why a lockfile needs to be re-pinned
+#[derive(Debug, Default, Serialize)]
+struct RepinDetails {
+    /// The digest stored in the lockfile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_digest: Option<Digest>,
+
+    /// The digest of the current inputs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_digest: Option<Digest>,
+
+    /// The categories of inputs which changed. This is unset when the lockfile does not
+    /// record the inputs of its digest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changed_inputs: Option<Vec<&'static str>>,
+}
+
+/// The result of a query
+#[derive(Debug, Serialize)]
+struct QueryResult {
+    repin: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<RepinReason>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<RepinDetails>,
+}
+
+impl QueryResult {
+    fn repin(reason: RepinReason, message: String, details: RepinDetails) -> Self {
+        Self {
+            repin: true,
+            reason: Some(reason),
+            message: Some(message),
+            details: Some(details),
+        }
+    }
 }
 
 /// Determine if the current lockfile needs to be re-pinned
 pub fn query(opt: QueryOptions) -> Result<()> {
+    let result = check_lockfile(&opt)?;
+
+    match opt.format {
+        OutputFormat::Text => {
+            if let Some(message) = &result.message {
+                eprintln!("{}", message);
+            }
+            if result.repin {
+                println!("repin");
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+    }
+
+    Ok(())
+}
+
+fn check_lockfile(opt: &QueryOptions) -> Result<QueryResult> {
     // Read the lockfile
     let content = match fs::read_to_string(&opt.lockfile) {
         Ok(c) => c,
-        Err(_) => return announce_repin("Unable to read lockfile"),
+        Err(e) => {
+            return Ok(QueryResult::repin(
+                RepinReason::MissingLockfile,
+                format!("Unable to read lockfile: {}", e),
+                RepinDetails::default(),
+            ))
+        }
     };
 
     // Deserialize it so we can easily compare it with
     let lockfile: Context = match serde_json::from_str(&content) {
         Ok(ctx) => ctx,
-        Err(_) => return announce_repin("Could not load lockfile"),
+        Err(e) => {
+            return Ok(QueryResult::repin(
+                RepinReason::UnparsableLockfile,
+                format!("Could not load lockfile: {}", e),
+                RepinDetails::default(),
+            ))
+        }
     };
 
     // Check to see if a digest has been set
     let digest = match &lockfile.checksum {
         Some(d) => d.clone(),
-        None => return announce_repin("No digest provided in lockfile"),
+        None => {
+            return Ok(QueryResult::repin(
+                RepinReason::MissingDigest,
+                "No digest provided in lockfile".to_owned(),
+                RepinDetails::default(),
+            ))
+        }
     };
 
     // Load the config file
-    let config = Config::try_from_path(&opt.config)?;
+    let config = Config::try_from_layers(&opt.base_config, &opt.config, &opt.annotations_dir)?;
 
     let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)?;
 
@@ -70,18 +185,42 @@ pub fn query(opt: QueryOptions) -> Result<()> {
         &opt.rustc,
     )?;
     if digest != expected {
-        return announce_repin(&format!(
-            "Digests do not match: {:?} != {:?}",
-            digest, expected
+        // Identify which inputs changed if the lockfile recorded them
+        let changed_inputs = match &lockfile.checksum_inputs {
+            Some(inputs) => {
+                let current = DigestInputs::new(
+                    &lockfile,
+                    &config,
+                    &splicing_manifest,
+                    &opt.cargo,
+                    &opt.rustc,
+                )?;
+                Some(inputs.changes(&current))
+            }
+            None => None,
+        };
+
+        let mut message = format!("Digests do not match: {:?} != {:?}", digest, expected);
+        if let Some(changes) = &changed_inputs {
+            message.push_str(&format!(". Changed inputs: {}", changes.join(", ")));
+        }
+
+        return Ok(QueryResult::repin(
+            RepinReason::DigestMismatch,
+            message,
+            RepinDetails {
+                old_digest: Some(digest),
+                new_digest: Some(expected),
+                changed_inputs,
+            },
         ));
     }
 
     // There is no need to repin
-    Ok(())
-}
-
-fn announce_repin(reason: &str) -> Result<()> {
-    eprintln!("{}", reason);
-    println!("repin");
-    Ok(())
+    Ok(QueryResult {
+        repin: false,
+        reason: None,
+        message: None,
+        details: None,
+    })
 }
@@ -0,0 +1,110 @@
+//! The cli entrypoint for the `sbom` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::config::CrateId;
+use crate::context::Context;
+use crate::sbom::Sbom;
+
+/// Command line options for the `sbom` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct SbomOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The format of the SBOM. Either `cyclonedx` or `spdx`.
+    #[clap(long, default_value = "cyclonedx")]
+    pub format: SbomFormat,
+
+    /// The name or workspace path of a workspace member to describe. Only the crates it
+    /// depends on are included. If unset, the whole workspace is described.
+    #[clap(long)]
+    pub member: Option<String>,
+
+    /// The creation time of the SBOM in seconds since the Unix epoch. Defaults to the current time.
+    #[clap(long, env = "SOURCE_DATE_EPOCH")]
+    pub timestamp: Option<u64>,
+
+    /// An optional path to write the SBOM to. If unset, the SBOM is printed to stdout.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// The formats an SBOM can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// A CycloneDX 1.4 json document
+    CycloneDx,
+
+    /// An SPDX 2.3 json document
+    Spdx,
+}
+
+impl FromStr for SbomFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cyclonedx" => Ok(Self::CycloneDx),
+            "spdx" => Ok(Self::Spdx),
+            _ => bail!("Unknown SbomFormat: '{}'", s),
+        }
+    }
+}
+
+/// Write a software bill of materials for the crates in a lockfile
+pub fn sbom(opt: SbomOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let root = match &opt.member {
+        Some(member) => {
+            let members: Vec<&CrateId> = context
+                .workspace_members
+                .iter()
+                .filter(|(id, path)| &id.name == member || *path == member)
+                .map(|(id, _)| id)
+                .collect();
+            match members.as_slice() {
+                [root] => Some(*root),
+                [] => bail!("No workspace member named '{}' was found", member),
+                _ => bail!(
+                    "Multiple workspace members match '{}': {:?}",
+                    member,
+                    members
+                ),
+            }
+        }
+        None => None,
+    };
+
+    let timestamp = match opt.timestamp {
+        Some(timestamp) => timestamp,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("The system clock is set before the Unix epoch")?
+            .as_secs(),
+    };
+
+    let sbom = Sbom::new(&context, root, timestamp);
+    let content = match opt.format {
+        SbomFormat::CycloneDx => serde_json::to_string_pretty(&sbom.to_cyclonedx())?,
+        SbomFormat::Spdx => serde_json::to_string_pretty(&sbom.to_spdx())?,
+    } + "\n";
+
+    match &opt.output {
+        Some(path) => fs::write(path, content)
+            .with_context(|| format!("Failed to write SBOM: {}", path.display()))?,
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
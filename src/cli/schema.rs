@@ -0,0 +1,57 @@
+//! The cli entrypoint for the `schema` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::schema::{schema as render_schema, SchemaKind};
+
+/// Command line options for the `schema` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct SchemaOptions {
+    /// The file to produce a schema for. Either `config`, `splicing-manifest` or `lockfile`.
+    /// If unset, schemas are produced for all files and `--output-dir` is required.
+    #[clap(long)]
+    pub kind: Option<SchemaKind>,
+
+    /// An optional directory to write schemas to. If unset, the schema is printed to stdout.
+    #[clap(long)]
+    pub output_dir: Option<PathBuf>,
+}
+
+/// Print or write the JSON Schemas of files read and written by `cargo-bazel`
+pub fn schema(opt: SchemaOptions) -> Result<()> {
+    let kinds = match opt.kind {
+        Some(kind) => vec![kind],
+        None => vec![
+            SchemaKind::Config,
+            SchemaKind::SplicingManifest,
+            SchemaKind::Lockfile,
+        ],
+    };
+
+    let output_dir = match &opt.output_dir {
+        Some(dir) => dir,
+        None => match kinds.as_slice() {
+            [kind] => {
+                println!("{}", serde_json::to_string_pretty(&render_schema(*kind))?);
+                return Ok(());
+            }
+            _ => bail!("`--output-dir` is required when `--kind` is unset"),
+        },
+    };
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create directory: {}", output_dir.display()))?;
+    for kind in kinds {
+        let path = output_dir.join(kind.file_name());
+        let content = serde_json::to_string_pretty(&render_schema(kind))? + "\n";
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write schema: {}", path.display()))?;
+    }
+
+    Ok(())
+}
@@ -5,7 +5,7 @@ use std::path::PathBuf;
 use clap::Parser;
 
 use crate::cli::Result;
-use crate::metadata::{write_metadata, Generator, MetadataGenerator};
+use crate::metadata::{write_metadata, CargoUpdateRequest, Generator, MetadataGenerator};
 use crate::splicing::{
     generate_lockfile, ExtraManifestsManifest, Splicer, SplicingManifest, WorkspaceMetadata,
 };
@@ -26,6 +26,16 @@ pub struct SpliceOptions {
     #[clap(long)]
     pub cargo_lockfile: Option<PathBuf>,
 
+    /// Crates to update in the provided Cargo lockfile, leaving all other crates pinned.
+    /// Accepts a package spec such as `name` or `name@version` (`cargo update --package`).
+    #[clap(long = "update")]
+    pub updates: Vec<String>,
+
+    /// The exact version to update the crate given to `--update` to (`cargo update --precise`).
+    /// Requires exactly one `--update`.
+    #[clap(long, requires = "updates")]
+    pub precise: Option<String>,
+
     /// The directory in which to build the workspace. A `Cargo.toml` file
     /// should always be produced within this directory.
     #[clap(long)]
@@ -66,8 +76,13 @@ pub fn splice(opt: SpliceOptions) -> Result<()> {
     let manifest_path = splicer.splice_workspace()?;
 
     // Generate a lockfile
-    let cargo_lockfile =
-        generate_lockfile(&manifest_path, &opt.cargo_lockfile, &opt.cargo, &opt.rustc)?;
+    let cargo_lockfile = generate_lockfile(
+        &manifest_path,
+        &opt.cargo_lockfile,
+        &CargoUpdateRequest::new_list(&opt.updates, &opt.precise)?,
+        &opt.cargo,
+        &opt.rustc,
+    )?;
 
     // Write the registry url info to the manifest now that a lockfile has been generated
     WorkspaceMetadata::write_registry_urls(&cargo_lockfile, &manifest_path)?;
@@ -0,0 +1,76 @@
+//! The cli entrypoint for the `tree` subcommand
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::config::CrateId;
+use crate::context::tree::DependencyTree;
+use crate::context::Context;
+
+/// Command line options for the `tree` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct TreeOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The platform triple to resolve conditional dependencies for
+    #[clap(long)]
+    pub platform: String,
+
+    /// The name or workspace path of the workspace member to render. May be omitted
+    /// if the workspace contains a single member.
+    pub member: Option<String>,
+}
+
+/// Print the dependency tree of a workspace member for a single platform
+pub fn tree(opt: TreeOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let members: Vec<&CrateId> = context
+        .workspace_members
+        .iter()
+        .filter(|(id, path)| match &opt.member {
+            Some(member) => &id.name == member || *path == member,
+            None => true,
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    let root = match members.as_slice() {
+        [root] => *root,
+        [] => match &opt.member {
+            Some(member) => bail!("No workspace member named '{}' was found", member),
+            None => bail!("The lockfile contains no workspace members"),
+        },
+        _ => bail!(
+            "Multiple workspace members match, please select one of: {}",
+            members
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    };
+
+    // Platforms which satisfy none of the lockfile's conditions are most likely typos
+    if !context.conditions.is_empty()
+        && !context
+            .conditions
+            .values()
+            .any(|triples| triples.contains(&opt.platform))
+    {
+        eprintln!(
+            "Warning: '{}' does not satisfy any conditions in the lockfile. Only unconditional dependencies are shown.",
+            opt.platform
+        );
+    }
+
+    print!("{}", DependencyTree::new(&context, root, &opt.platform));
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! The cli entrypoint for the `validate` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::cli::OutputFormat;
+use crate::config::validation::validate_config;
+use crate::config::Config;
+
+/// Command line options for the `validate` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct ValidateOptions {
+    /// The config file with information about the Bazel and Cargo workspace. If provided
+    /// multiple times, each config is layered over the ones before it.
+    #[clap(long, required = true)]
+    pub config: Vec<PathBuf>,
+
+    /// A config shared between workspaces (Eg. an organisation wide base) which all `--config`
+    /// layers are applied over. Its annotations are not required to match any crates. May be
+    /// provided multiple times.
+    #[clap(long)]
+    pub base_config: Vec<PathBuf>,
+
+    /// A directory of `json` files containing crate annotations to apply beneath those of
+    /// the configs. These annotations are not required to match any crates. May be provided
+    /// multiple times.
+    #[clap(long)]
+    pub annotations_dir: Vec<PathBuf>,
+
+    /// The path to a Cargo metadata `json` file of the spliced workspace. If unset,
+    /// annotations are not checked against the packages in the workspace.
+    #[clap(long)]
+    pub metadata: Option<PathBuf>,
+
+    /// The format of the report. Either `text` or `json`.
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+/// Report all problems with a config file
+pub fn validate(opt: ValidateOptions) -> Result<()> {
+    let config = Config::try_from_layers(&opt.base_config, &opt.config, &opt.annotations_dir)?;
+
+    let metadata = match &opt.metadata {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to load Cargo Metadata: {}", path.display()))?;
+            Some(serde_json::from_str(&content).context("Unable to deserialize Cargo metadata")?)
+        }
+        None => None,
+    };
+
+    let errors = validate_config(&config, metadata.as_ref());
+
+    match opt.format {
+        OutputFormat::Text => {
+            for error in errors.iter() {
+                println!("{}", error);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&errors)?),
+    }
+
+    if !errors.is_empty() {
+        let configs: Vec<String> = opt
+            .config
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        bail!(
+            "Found {} problem(s) in {}",
+            errors.len(),
+            configs.join(", ")
+        );
+    }
+
+    Ok(())
+}
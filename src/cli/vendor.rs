@@ -1,6 +1,6 @@
 //! The cli entrypoint for the `vendor` subcommand
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{self, ExitStatus};
@@ -10,9 +10,12 @@ use clap::Parser;
 
 use crate::config::{Config, VendorMode};
 use crate::context::Context;
-use crate::metadata::{Annotations, VendorGenerator};
+use crate::licenses::enforce_license_policy;
+use crate::metadata::{Annotations, CargoUpdateRequest, VendorGenerator};
 use crate::metadata::{Generator, MetadataGenerator};
-use crate::rendering::{render_module_label, write_outputs, Renderer};
+use crate::rendering::{
+    check_outputs, find_extra_outputs, render_module_label, write_outputs, Renderer,
+};
 use crate::splicing::{
     generate_lockfile, ExtraManifestsManifest, Splicer, SplicingManifest, WorkspaceMetadata,
 };
@@ -33,9 +36,22 @@ pub struct VendorOptions {
     #[clap(long)]
     pub buildifier: Option<PathBuf>,
 
-    /// The config file with information about the Bazel and Cargo workspace
+    /// The config file with information about the Bazel and Cargo workspace. If provided
+    /// multiple times, each config is layered over the ones before it.
+    #[clap(long, required = true)]
+    pub config: Vec<PathBuf>,
+
+    /// A config shared between workspaces (Eg. an organisation wide base) which all `--config`
+    /// layers are applied over. Its annotations are not required to match any crates. May be
+    /// provided multiple times.
+    #[clap(long)]
+    pub base_config: Vec<PathBuf>,
+
+    /// A directory of `json` files containing crate annotations to apply beneath those of
+    /// the configs. These annotations are not required to match any crates. May be provided
+    /// multiple times.
     #[clap(long)]
-    pub config: PathBuf,
+    pub annotations_dir: Vec<PathBuf>,
 
     /// A generated manifest of splicing inputs
     #[clap(long)]
@@ -45,6 +61,16 @@ pub struct VendorOptions {
     #[clap(long)]
     pub cargo_lockfile: Option<PathBuf>,
 
+    /// Crates to update in the provided Cargo lockfile, leaving all other crates pinned.
+    /// Accepts a package spec such as `name` or `name@version` (`cargo update --package`).
+    #[clap(long = "update")]
+    pub updates: Vec<String>,
+
+    /// The exact version to update the crate given to `--update` to (`cargo update --precise`).
+    /// Requires exactly one `--update`.
+    #[clap(long, requires = "updates")]
+    pub precise: Option<String>,
+
     /// A [Cargo config](https://doc.rust-lang.org/cargo/reference/config.html#configuration)
     /// file to use when gathering metadata
     #[clap(long)]
@@ -66,6 +92,11 @@ pub struct VendorOptions {
     /// If true, outputs will be printed instead of written to disk.
     #[clap(long)]
     pub dry_run: bool,
+
+    /// If true, vendored files are compared to the rendered outputs instead of being written.
+    /// A diff of any out of date files is printed and a non-zero exit code is returned.
+    #[clap(long)]
+    pub check: bool,
 }
 
 /// Run buildifier on a given file.
@@ -83,6 +114,54 @@ fn buildifier_format(bin: &Path, file: &Path) -> Result<ExitStatus> {
     Ok(status)
 }
 
+/// Compare rendered outputs to the vendored files in a workspace without modifying anything.
+fn check_vendored_outputs(
+    outputs: BTreeMap<PathBuf, String>,
+    workspace_dir: &Path,
+    vendor_package: &Path,
+    buildifier: &Option<PathBuf>,
+) -> Result<()> {
+    // Outputs on disk are formatted so the rendered outputs must be too.
+    let outputs = match buildifier {
+        Some(buildifier_bin) => {
+            let format_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+            let file_names: BTreeSet<PathBuf> = outputs.keys().cloned().collect();
+            write_outputs(outputs, format_dir.as_ref(), false)?;
+            file_names
+                .into_iter()
+                .map(|file| {
+                    let file_path = format_dir.as_ref().join(&file);
+                    buildifier_format(buildifier_bin, &file_path).with_context(|| {
+                        format!("Failed to run buildifier on {}", file_path.display())
+                    })?;
+                    let content = fs::read_to_string(&file_path)?;
+                    Ok((file, content))
+                })
+                .collect::<Result<BTreeMap<PathBuf, String>>>()?
+        }
+        None => outputs,
+    };
+
+    let mut check = check_outputs(&outputs, workspace_dir)?;
+
+    // Only search for extra files when vendoring into a dedicated package
+    if vendor_package.components().next().is_some() {
+        check.extra = find_extra_outputs(&outputs, workspace_dir, vendor_package)?;
+    }
+
+    if check.is_empty() {
+        return Ok(());
+    }
+
+    print!("{}", check);
+    bail!(
+        "Vendored files are out of date ({} stale, {} missing, {} extra). Rerun without `--check` to update them.",
+        check.stale.len(),
+        check.missing.len(),
+        check.extra.len()
+    )
+}
+
 pub fn vendor(opt: VendorOptions) -> Result<()> {
     // Load the all config files required for splicing a workspace
     let splicing_manifest =
@@ -106,8 +185,13 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
         .context("Failed to splice workspace")?;
 
     // Generate a lockfile
-    let cargo_lockfile =
-        generate_lockfile(&manifest_path, &opt.cargo_lockfile, &opt.cargo, &opt.rustc)?;
+    let cargo_lockfile = generate_lockfile(
+        &manifest_path,
+        &opt.cargo_lockfile,
+        &CargoUpdateRequest::new_list(&opt.updates, &opt.precise)?,
+        &opt.cargo,
+        &opt.rustc,
+    )?;
 
     // Write the registry url info to the manifest now that a lockfile has been generated
     WorkspaceMetadata::write_registry_urls(&cargo_lockfile, &manifest_path)?;
@@ -119,7 +203,7 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
         .generate(&manifest_path.as_path_buf())?;
 
     // Load the config from disk
-    let config = Config::try_from_path(&opt.config)?;
+    let config = Config::try_from_layers(&opt.base_config, &opt.config, &opt.annotations_dir)?;
 
     // Annotate metadata
     let annotations = Annotations::new(cargo_metadata, cargo_lockfile, config.clone())?;
@@ -127,17 +211,33 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
     // Generate renderable contexts for earch package
     let context = Context::new(annotations)?;
 
+    // Ensure all crates satisfy the license policy
+    if let Some(policy) = &config.license_policy {
+        enforce_license_policy(policy, &context)?;
+    }
+
     // Render build files
     let outputs = Renderer::new(config.rendering.clone()).render(&context)?;
 
     // Cache the file names for potential use with buildifier
     let file_names: BTreeSet<PathBuf> = outputs.keys().cloned().collect();
 
-    // First ensure vendoring and rendering happen in a clean directory
+    // Determine the package all vendored content is written to
     let vendor_dir_label = render_module_label(&config.rendering.crates_module_template, "BUILD")?;
-    let vendor_dir = opt
-        .workspace_dir
-        .join(vendor_dir_label.package.unwrap_or_default());
+    let vendor_package = PathBuf::from(vendor_dir_label.package.unwrap_or_default());
+
+    // Compare the outputs to what's on disk if requested
+    if opt.check {
+        return check_vendored_outputs(
+            outputs,
+            &opt.workspace_dir,
+            &vendor_package,
+            &opt.buildifier,
+        );
+    }
+
+    // First ensure vendoring and rendering happen in a clean directory
+    let vendor_dir = opt.workspace_dir.join(vendor_package);
     if vendor_dir.exists() {
         fs::remove_dir_all(&vendor_dir)
             .with_context(|| format!("Failed to delete {}", vendor_dir.display()))?;
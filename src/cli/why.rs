@@ -0,0 +1,90 @@
+//! The cli entrypoint for the `why` subcommand
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::cli::OutputFormat;
+use crate::config::CrateId;
+use crate::context::graph::DependentNode;
+use crate::context::Context;
+
+/// Command line options for the `why` subcommand
+#[derive(Parser, Debug)]
+#[clap(about, version)]
+pub struct WhyOptions {
+    /// The path to a Bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The name of the crate to explain
+    pub crate_name: String,
+
+    /// An optional version of the crate to explain. If unset, all versions are explained.
+    pub crate_version: Option<String>,
+
+    /// The format of the report. Either `text` or `json`.
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+/// Print the crates through which the requested crate is pulled into the dependency graph
+pub fn why(opt: WhyOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile: {}", opt.lockfile.display()))?;
+
+    let crate_ids: Vec<&CrateId> = context
+        .crates
+        .keys()
+        .filter(|id| id.name == opt.crate_name)
+        .filter(|id| match &opt.crate_version {
+            Some(version) => &id.version == version,
+            None => true,
+        })
+        .collect();
+
+    if crate_ids.is_empty() {
+        match &opt.crate_version {
+            Some(version) => bail!(
+                "No crate matching '{} {}' was found in the lockfile",
+                opt.crate_name,
+                version
+            ),
+            None => bail!(
+                "No crate named '{}' was found in the lockfile",
+                opt.crate_name
+            ),
+        }
+    }
+
+    let trees: Vec<DependentNode> = crate_ids
+        .into_iter()
+        .map(|id| context.dependents_tree(id))
+        .collect();
+
+    match opt.format {
+        OutputFormat::Text => {
+            for tree in trees.iter() {
+                print_tree(tree);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&trees)?),
+    }
+
+    Ok(())
+}
+
+fn print_tree(tree: &DependentNode) {
+    if tree.workspace_member {
+        println!("{} is a workspace member", tree.crate_id);
+    } else if tree.dependents.is_empty() {
+        println!(
+            "{} is not depended on by any workspace member",
+            tree.crate_id
+        );
+    } else {
+        print!("{}", tree);
+    }
+    println!();
+}
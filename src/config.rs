@@ -1,16 +1,19 @@
 //! A module for configuration information
 
+pub mod validation;
+
 use std::collections::{BTreeMap, BTreeSet};
 use std::convert::AsRef;
 use std::iter::Sum;
 use std::ops::Add;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{fmt, fs};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use cargo_lock::package::source::GitReference;
 use cargo_metadata::Package;
-use semver::VersionReq;
+use regex::Regex;
+use semver::{Version, VersionReq};
 use serde::de::Visitor;
 use serde::{Deserialize, Serialize, Serializer};
 
@@ -138,11 +141,47 @@ pub enum Checksumish {
     },
 }
 
+/// A selection of the binary (`bin`) targets of a crate to generate `rust_binary` targets for.
+/// This is represented as either a boolean or a list of binary names.
+#[derive(Debug, Hash, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum GenBinaries {
+    /// Generate either all or none of the binaries
+    All(bool),
+
+    /// Only generate the binaries with these names
+    Some(BTreeSet<String>),
+}
+
+impl Default for GenBinaries {
+    fn default() -> Self {
+        Self::All(true)
+    }
+}
+
+impl GenBinaries {
+    /// Whether or not a target should be generated for the binary named `name`
+    pub fn includes(&self, name: &str) -> bool {
+        match self {
+            Self::All(all) => *all,
+            Self::Some(names) => names.contains(name),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
 #[derive(Debug, Default, Hash, Deserialize, Serialize, Clone)]
 pub struct CrateAnnotations {
     /// Determins whether or not Cargo build scripts should be generated for the current package
     pub gen_build_script: Option<bool>,
 
+    /// Determines which binaries of the current package have targets generated for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gen_binaries: Option<GenBinaries>,
+
     /// Additional data to pass to
     /// [deps](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-deps) attribute.
     pub deps: Option<BTreeSet<String>>,
@@ -155,6 +194,16 @@ pub struct CrateAnnotations {
     /// [crate_features](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-crate_features) attribute.
     pub crate_features: Option<BTreeSet<String>>,
 
+    /// Features to remove from those Cargo resolved for the crate. Optional dependencies
+    /// which are no longer enabled by the remaining features are also removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crate_features_remove: Option<BTreeSet<String>>,
+
+    /// Features to use in place of those Cargo resolved for the crate. Features enabled by
+    /// these features are included and optional dependencies are adjusted to match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crate_features_override: Option<BTreeSet<String>>,
+
     /// Additional data to pass to  the target's
     /// [data](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-data) attribute.
     pub data: Option<BTreeSet<String>>,
@@ -183,6 +232,36 @@ pub struct CrateAnnotations {
     /// [rustc_flags](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-rustc_flags) attribute.
     pub rustc_flags: Option<Vec<String>>,
 
+    /// Additional tags to pass to the
+    /// [tags](https://bazel.build/reference/be/common-definitions#common.tags) attribute
+    /// of all targets generated for the crate, including build scripts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<BTreeSet<String>>,
+
+    /// An optional [visibility](https://bazel.build/concepts/visibility) to set on the
+    /// library, proc-macro and binary targets of the crate. Targets are public by default.
+    /// Labels must be repository-qualified (eg. `@//pkg:__pkg__`) as they're rendered into the
+    /// crate's own repository. The package aliasing the crate's targets and the packages of
+    /// crates which depend on it are always added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<BTreeSet<String>>,
+
+    /// An optional path, relative to the crate's manifest, to set as the
+    /// [crate_root](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-crate_root)
+    /// of the crate's library or proc-macro target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crate_root: Option<String>,
+
+    /// Glob patterns to exclude from the `srcs` attribute of all targets generated for the crate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub srcs_exclude: Option<BTreeSet<String>>,
+
+    /// Whether or not to set the
+    /// [disable_pipelining](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-disable_pipelining)
+    /// attribute of the crate's library target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_pipelining: Option<bool>,
+
     /// Additional dependencies to pass to a build script's
     /// [deps](https://bazelbuild.github.io/rules_rust/cargo.html#cargo_build_script-deps) attribute.
     pub build_script_deps: Option<BTreeSet<String>>,
@@ -229,6 +308,74 @@ pub struct CrateAnnotations {
     /// The `patches` attribute of a Bazel repository rule. See
     /// [http_archive.patches](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patches)
     pub patches: Option<BTreeSet<String>>,
+
+    /// Annotations which only apply to specific platforms, keyed by platform triple or
+    /// `cfg(...)` expression. Values are rendered into `select` statements. Only the following
+    /// fields are supported: `deps`, `proc_macro_deps`, `data`, `compile_data`, `rustc_env`,
+    /// `rustc_env_files`, `rustc_flags`, `build_script_deps`, `build_script_proc_macro_deps`,
+    /// `build_script_data`, `build_script_tools`, `build_script_env` and `build_script_rustc_env`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform_annotations: Option<BTreeMap<String, CrateAnnotations>>,
+
+    /// The label of an existing Bazel target (Eg. a first-party fork or a prebuilt library)
+    /// which replaces the crate. No repository or BUILD file is generated for the crate and
+    /// all dependents use this label instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_target: Option<String>,
+
+    /// Whether or not to skip the [Config::default_annotations] for the crate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_default_annotations: Option<bool>,
+}
+
+impl CrateAnnotations {
+    /// The names of fields which are set but unsupported in [CrateAnnotations::platform_annotations]
+    pub fn unsupported_platform_fields(&self) -> Vec<&'static str> {
+        let fields = [
+            ("gen_build_script", self.gen_build_script.is_some()),
+            ("gen_binaries", self.gen_binaries.is_some()),
+            ("crate_features", self.crate_features.is_some()),
+            (
+                "crate_features_remove",
+                self.crate_features_remove.is_some(),
+            ),
+            (
+                "crate_features_override",
+                self.crate_features_override.is_some(),
+            ),
+            ("data_glob", self.data_glob.is_some()),
+            ("compile_data_glob", self.compile_data_glob.is_some()),
+            ("tags", self.tags.is_some()),
+            ("visibility", self.visibility.is_some()),
+            ("crate_root", self.crate_root.is_some()),
+            ("srcs_exclude", self.srcs_exclude.is_some()),
+            ("disable_pipelining", self.disable_pipelining.is_some()),
+            (
+                "build_script_data_glob",
+                self.build_script_data_glob.is_some(),
+            ),
+            (
+                "additive_build_file_content",
+                self.additive_build_file_content.is_some(),
+            ),
+            ("shallow_since", self.shallow_since.is_some()),
+            ("patch_args", self.patch_args.is_some()),
+            ("patch_tool", self.patch_tool.is_some()),
+            ("patches", self.patches.is_some()),
+            ("platform_annotations", self.platform_annotations.is_some()),
+            ("override_target", self.override_target.is_some()),
+            (
+                "skip_default_annotations",
+                self.skip_default_annotations.is_some(),
+            ),
+        ];
+
+        fields
+            .iter()
+            .filter(|(_, is_set)| *is_set)
+            .map(|(field, _)| *field)
+            .collect()
+    }
 }
 
 macro_rules! joined_extra_member {
@@ -250,44 +397,50 @@ macro_rules! joined_extra_member {
     };
 }
 
+/// Combines two [CrateAnnotations]. Collections are joined while single values (Eg.
+/// `gen_build_script`) and map entries from `rhs` take precedence over those in `self`.
 impl Add for CrateAnnotations {
     type Output = CrateAnnotations;
 
     fn add(self, rhs: Self) -> Self::Output {
-        let shallow_since = if self.shallow_since.is_some() {
-            self.shallow_since
-        } else if rhs.shallow_since.is_some() {
-            rhs.shallow_since
-        } else {
-            None
-        };
-
-        let patch_tool = if self.patch_tool.is_some() {
-            self.patch_tool
-        } else if rhs.patch_tool.is_some() {
-            rhs.patch_tool
-        } else {
-            None
-        };
-
-        let gen_build_script = if self.gen_build_script.is_some() {
-            self.gen_build_script
-        } else if rhs.gen_build_script.is_some() {
-            rhs.gen_build_script
-        } else {
-            None
-        };
+        let shallow_since = rhs.shallow_since.or(self.shallow_since);
+        let patch_tool = rhs.patch_tool.or(self.patch_tool);
+        let gen_build_script = rhs.gen_build_script.or(self.gen_build_script);
+        let gen_binaries = rhs.gen_binaries.or(self.gen_binaries);
+        let crate_features_override = rhs.crate_features_override.or(self.crate_features_override);
+        let override_target = rhs.override_target.or(self.override_target);
+        let crate_root = rhs.crate_root.or(self.crate_root);
+        let disable_pipelining = rhs.disable_pipelining.or(self.disable_pipelining);
+        let skip_default_annotations = rhs
+            .skip_default_annotations
+            .or(self.skip_default_annotations);
 
         let concat_string = |lhs: &mut String, rhs: String| {
             *lhs = format!("{}{}", lhs, rhs);
         };
 
+        // Annotations for the same platform are combined
+        let extend_platforms =
+            |lhs: &mut BTreeMap<String, CrateAnnotations>,
+             rhs: BTreeMap<String, CrateAnnotations>| {
+                for (platform, annotation) in rhs {
+                    let combined = match lhs.remove(&platform) {
+                        Some(existing) => existing + annotation,
+                        None => annotation,
+                    };
+                    lhs.insert(platform, combined);
+                }
+            };
+
         #[rustfmt::skip]
         let output = CrateAnnotations {
             gen_build_script,
+            gen_binaries,
             deps: joined_extra_member!(self.deps, rhs.deps, BTreeSet::new, BTreeSet::extend),
             proc_macro_deps: joined_extra_member!(self.proc_macro_deps, rhs.proc_macro_deps, BTreeSet::new, BTreeSet::extend),
             crate_features: joined_extra_member!(self.crate_features, rhs.crate_features, BTreeSet::new, BTreeSet::extend),
+            crate_features_remove: joined_extra_member!(self.crate_features_remove, rhs.crate_features_remove, BTreeSet::new, BTreeSet::extend),
+            crate_features_override,
             data: joined_extra_member!(self.data, rhs.data, BTreeSet::new, BTreeSet::extend),
             data_glob: joined_extra_member!(self.data_glob, rhs.data_glob, BTreeSet::new, BTreeSet::extend),
             compile_data: joined_extra_member!(self.compile_data, rhs.compile_data, BTreeSet::new, BTreeSet::extend),
@@ -295,6 +448,11 @@ impl Add for CrateAnnotations {
             rustc_env: joined_extra_member!(self.rustc_env, rhs.rustc_env, BTreeMap::new, BTreeMap::extend),
             rustc_env_files: joined_extra_member!(self.rustc_env_files, rhs.rustc_env_files, BTreeSet::new, BTreeSet::extend),
             rustc_flags: joined_extra_member!(self.rustc_flags, rhs.rustc_flags, Vec::new, Vec::extend),
+            tags: joined_extra_member!(self.tags, rhs.tags, BTreeSet::new, BTreeSet::extend),
+            visibility: joined_extra_member!(self.visibility, rhs.visibility, BTreeSet::new, BTreeSet::extend),
+            crate_root,
+            srcs_exclude: joined_extra_member!(self.srcs_exclude, rhs.srcs_exclude, BTreeSet::new, BTreeSet::extend),
+            disable_pipelining,
             build_script_deps: joined_extra_member!(self.build_script_deps, rhs.build_script_deps, BTreeSet::new, BTreeSet::extend),
             build_script_proc_macro_deps: joined_extra_member!(self.build_script_proc_macro_deps, rhs.build_script_proc_macro_deps, BTreeSet::new, BTreeSet::extend),
             build_script_data: joined_extra_member!(self.build_script_data, rhs.build_script_data, BTreeSet::new, BTreeSet::extend),
@@ -307,6 +465,9 @@ impl Add for CrateAnnotations {
             patch_args: joined_extra_member!(self.patch_args, rhs.patch_args, Vec::new, Vec::extend),
             patch_tool,
             patches: joined_extra_member!(self.patches, rhs.patches, BTreeSet::new, BTreeSet::extend),
+            platform_annotations: joined_extra_member!(self.platform_annotations, rhs.platform_annotations, BTreeMap::new, extend_platforms),
+            override_target,
+            skip_default_annotations,
         };
 
         output
@@ -319,6 +480,25 @@ impl Sum for CrateAnnotations {
     }
 }
 
+/// The kinds of patterns the name of a [CrateId] can contain when used as the key of an
+/// annotation. Variants are ordered from least to most specific.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum NamePattern {
+    /// `*` matches all crates
+    Wildcard,
+
+    /// A regular expression wrapped in slashes (Eg. `/^windows[-_]/`). The expression is
+    /// not implicitly anchored.
+    Regex,
+
+    /// A glob where `*` matches any sequence of characters and `?` matches any single
+    /// character (Eg. `*-sys`)
+    Glob,
+
+    /// An exact crate name
+    Exact,
+}
+
 /// A unique identifier for Crates
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct CrateId {
@@ -335,11 +515,68 @@ impl CrateId {
         Self { name, version }
     }
 
+    /// The kind of pattern the name of the [CrateId] represents
+    pub fn name_pattern(&self) -> NamePattern {
+        if self.name == "*" {
+            NamePattern::Wildcard
+        } else if self.name.len() > 1 && self.name.starts_with('/') && self.name.ends_with('/') {
+            NamePattern::Regex
+        } else if self.name.contains(&['*', '?'][..]) {
+            NamePattern::Glob
+        } else {
+            NamePattern::Exact
+        }
+    }
+
+    /// Compile the name of the [CrateId] into a regular expression if it's a regex or glob
+    pub fn name_regex(&self) -> Option<Result<Regex, regex::Error>> {
+        match self.name_pattern() {
+            NamePattern::Regex => Some(Regex::new(&self.name[1..self.name.len() - 1])),
+            NamePattern::Glob => {
+                let pattern = self
+                    .name
+                    .split('*')
+                    .map(|part| {
+                        part.split('?')
+                            .map(regex::escape)
+                            .collect::<Vec<String>>()
+                            .join(".")
+                    })
+                    .collect::<Vec<String>>()
+                    .join(".*");
+                Some(Regex::new(&format!("^{}$", pattern)))
+            }
+            NamePattern::Wildcard | NamePattern::Exact => None,
+        }
+    }
+
+    /// The precedence of the [CrateId] when used as the key of an annotation. Annotations
+    /// with a higher precedence are applied after (and take precedence over) those with a
+    /// lower one. Name patterns are ranked first (see [NamePattern]), then versions where
+    /// `*` ranks below other semver requirements which rank below exact versions. Any
+    /// remaining ties are broken by the order of the keys.
+    pub fn precedence(&self) -> (NamePattern, u8, &CrateId) {
+        let version = if self.version == "*" {
+            0
+        } else if Version::parse(&self.version).is_ok() {
+            2
+        } else {
+            1
+        };
+
+        (self.name_pattern(), version, self)
+    }
+
     /// Compares a [CrateId] against a [cargo_metadata::Package].
     pub fn matches(&self, package: &Package) -> bool {
         // If the package name does not match, it's obviously
         // not the right package
-        if self.name != "*" && self.name != package.name {
+        let name_matches = match self.name_regex() {
+            Some(Ok(regex)) => regex.is_match(&package.name),
+            Some(Err(_)) => false,
+            None => self.name == "*" || self.name == package.name,
+        };
+        if !name_matches {
             return false;
         }
 
@@ -419,6 +656,20 @@ impl std::fmt::Display for CrateId {
     }
 }
 
+/// A policy describing which licenses third party crates are permitted to use
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LicensePolicy {
+    /// SPDX license identifiers which are permitted. If empty, all licenses not
+    /// in `deny` are permitted.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub allow: BTreeSet<String>,
+
+    /// SPDX license identifiers which are never permitted.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub deny: BTreeSet<String>,
+}
+
 /// Workspace specific settings to control how targets are generated
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
@@ -426,10 +677,19 @@ pub struct Config {
     /// Whether or not to generate Cargo build scripts by default
     pub generate_build_scripts: bool,
 
+    /// Which binaries to generate targets for by default
+    #[serde(default, skip_serializing_if = "GenBinaries::is_default")]
+    pub generate_binaries: GenBinaries,
+
     /// Additional settings to apply to generated crates
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub annotations: BTreeMap<CrateId, CrateAnnotations>,
 
+    /// Settings to apply to all third party crates beneath those in `annotations`. Crates
+    /// may opt out using [CrateAnnotations::skip_default_annotations].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_annotations: Option<CrateAnnotations>,
+
     /// Settings used to determine various render info
     pub rendering: RenderConfig,
 
@@ -439,12 +699,168 @@ pub struct Config {
     /// A set of platform triples to use in generated select statements
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub supported_platform_triples: BTreeSet<String>,
+
+    /// An optional policy which the licenses of all third party crates must satisfy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_policy: Option<LicensePolicy>,
+
+    /// Keys of `annotations` which are only defined by configs or annotation directories shared
+    /// between workspaces. Unlike other annotations, these are not required to match a crate.
+    /// See [Config::try_from_layers].
+    #[serde(skip)]
+    pub shared_annotations: BTreeSet<CrateId>,
 }
 
 impl Config {
+    /// Load a config from a `json` file or, if its extension is `.toml`, a TOML file.
     pub fn try_from_path<T: AsRef<Path>>(path: T) -> Result<Self> {
-        let data = fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&data)?)
+        Ok(serde_json::from_value(read_config_file(path.as_ref())?)?)
+    }
+
+    /// Load a config from a list of layers (Eg. a repository config and a local overlay) where
+    /// each layer is applied over the ones before it, which are in turn applied over any
+    /// `base_layers` (Eg. an organisation wide base). Annotations and default annotations of
+    /// all layers are combined using [CrateAnnotations]' [Add] implementation while all other
+    /// settings are merged so the last layer to set a value wins. An explicit `null` replaces
+    /// the value of a setting from an earlier layer. Annotations may also be collected from
+    /// the `json` or TOML files in `annotation_dirs`, each of which contains a map of
+    /// annotations like the `annotations` field of a config. These are applied beneath the
+    /// annotations of all layers. Annotations only defined by base layers or annotation
+    /// directories are tracked in [Config::shared_annotations].
+    pub fn try_from_layers<T: AsRef<Path>, U: AsRef<Path>>(
+        base_layers: &[T],
+        layers: &[T],
+        annotation_dirs: &[U],
+    ) -> Result<Self> {
+        let mut annotations = BTreeMap::new();
+        for dir in annotation_dirs {
+            for path in annotation_files(dir.as_ref())? {
+                let file_annotations = serde_json::from_value(read_config_file(&path)?)
+                    .with_context(|| format!("Failed to parse annotations: {}", path.display()))?;
+                merge_annotations(&mut annotations, file_annotations);
+            }
+        }
+
+        let mut workspace_annotations = BTreeSet::new();
+        let mut default_annotations: Option<CrateAnnotations> = None;
+        let mut merged = serde_json::Value::Object(serde_json::Map::new());
+        let all_layers = base_layers
+            .iter()
+            .map(|layer| (layer, true))
+            .chain(layers.iter().map(|layer| (layer, false)));
+        for (layer, is_base) in all_layers {
+            let path = layer.as_ref();
+            let mut value = read_config_file(path)?;
+
+            let layer_annotations = value
+                .as_object_mut()
+                .and_then(|obj| obj.remove("annotations"));
+            if let Some(layer_annotations) = layer_annotations {
+                let layer_annotations: BTreeMap<CrateId, CrateAnnotations> =
+                    serde_json::from_value(layer_annotations).with_context(|| {
+                        format!("Failed to parse annotations of config: {}", path.display())
+                    })?;
+                if !is_base {
+                    workspace_annotations.extend(layer_annotations.keys().cloned());
+                }
+                merge_annotations(&mut annotations, layer_annotations);
+            }
+
+            let layer_defaults = value
+                .as_object_mut()
+                .and_then(|obj| obj.remove("default_annotations"))
+                .filter(|defaults| !defaults.is_null());
+            if let Some(layer_defaults) = layer_defaults {
+                let layer_defaults: CrateAnnotations = serde_json::from_value(layer_defaults)
+                    .with_context(|| {
+                        format!(
+                            "Failed to parse default annotations of config: {}",
+                            path.display()
+                        )
+                    })?;
+                default_annotations = Some(match default_annotations {
+                    Some(existing) => existing + layer_defaults,
+                    None => layer_defaults,
+                });
+            }
+
+            merge_json(&mut merged, value);
+        }
+
+        let mut config: Config =
+            serde_json::from_value(merged).context("Failed to parse the merged config")?;
+        config.shared_annotations = annotations
+            .keys()
+            .filter(|id| !workspace_annotations.contains(id))
+            .cloned()
+            .collect();
+        config.annotations = annotations;
+        config.default_annotations = default_annotations;
+        Ok(config)
+    }
+}
+
+/// Read a `json` file or, if its extension is `.toml`, a TOML file into a json value
+fn read_config_file(path: &Path) -> Result<serde_json::Value> {
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config: {}", path.display()))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&data)
+            .with_context(|| format!("Failed to parse TOML config: {}", path.display())),
+        _ => serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse config: {}", path.display())),
+    }
+}
+
+/// Locate all annotation files in a directory sorted by name for a stable merge order
+fn annotation_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("Failed to read annotations directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_file()
+            && matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("json") | Some("toml")
+            )
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Add annotations from `rhs` to `lhs`, combining those which share a [CrateId]
+fn merge_annotations(
+    lhs: &mut BTreeMap<CrateId, CrateAnnotations>,
+    rhs: BTreeMap<CrateId, CrateAnnotations>,
+) {
+    for (id, annotation) in rhs {
+        let combined = match lhs.remove(&id) {
+            Some(existing) => existing + annotation,
+            None => annotation,
+        };
+        lhs.insert(id, combined);
+    }
+}
+
+/// Merge two json values where objects are merged recursively and all other values in
+/// `rhs` replace those in `lhs`.
+fn merge_json(lhs: &mut serde_json::Value, rhs: serde_json::Value) {
+    match (lhs, rhs) {
+        (serde_json::Value::Object(lhs), serde_json::Value::Object(rhs)) => {
+            for (key, value) in rhs {
+                match lhs.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        lhs.insert(key, value);
+                    }
+                }
+            }
+        }
+        (lhs, rhs) => *lhs = rhs,
     }
 }
 
@@ -480,6 +896,356 @@ mod test {
         assert!(!id.matches(&package));
     }
 
+    #[test]
+    fn test_crate_id_pattern_matches() {
+        let matches = |pattern: &str, name: &str| {
+            let mut package = mock_cargo_metadata_package();
+            package.name = name.to_owned();
+            CrateId::new(pattern.to_owned(), "*".to_owned()).matches(&package)
+        };
+
+        assert!(matches("*", "openssl-sys"));
+        assert!(matches("*-sys", "openssl-sys"));
+        assert!(matches("openssl-*", "openssl-sys"));
+        assert!(matches("openssl?sys", "openssl-sys"));
+        assert!(!matches("*-sys-*", "openssl-sys"));
+        assert!(!matches("windows-*", "openssl-sys"));
+        assert!(matches("/ssl/", "openssl-sys"));
+        assert!(matches("/^(openssl|libz)-sys$/", "openssl-sys"));
+        assert!(!matches("/^windows[-_]/", "openssl-sys"));
+        assert!(!matches("/(unclosed/", "openssl-sys"));
+
+        // Regex metacharacters in globs are literal
+        assert!(matches("?.*", "a.b"));
+        assert!(!matches("a.*", "ab"));
+    }
+
+    #[test]
+    fn test_crate_id_precedence() {
+        let mut ids = [
+            CrateId::new("openssl-sys".to_owned(), "0.9.72".to_owned()),
+            CrateId::new("openssl-sys".to_owned(), "*".to_owned()),
+            CrateId::new("openssl-sys".to_owned(), "^0.9".to_owned()),
+            CrateId::new("*-sys".to_owned(), "0.9.72".to_owned()),
+            CrateId::new("/-sys$/".to_owned(), "*".to_owned()),
+            CrateId::new("*".to_owned(), "*".to_owned()),
+        ];
+        ids.sort_by(|a, b| a.precedence().cmp(&b.precedence()));
+
+        assert_eq!(
+            ids.iter().map(ToString::to_string).collect::<Vec<String>>(),
+            vec![
+                "* *",
+                "/-sys$/ *",
+                "*-sys 0.9.72",
+                "openssl-sys *",
+                "openssl-sys ^0.9",
+                "openssl-sys 0.9.72",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crate_annotations_add() {
+        let lhs = CrateAnnotations {
+            gen_build_script: Some(true),
+            deps: Some(BTreeSet::from(["//:a".to_owned()])),
+            rustc_env: Some(BTreeMap::from([
+                ("A".to_owned(), "lhs".to_owned()),
+                ("B".to_owned(), "lhs".to_owned()),
+            ])),
+            ..CrateAnnotations::default()
+        };
+        let rhs = CrateAnnotations {
+            gen_build_script: Some(false),
+            deps: Some(BTreeSet::from(["//:b".to_owned()])),
+            rustc_env: Some(BTreeMap::from([("B".to_owned(), "rhs".to_owned())])),
+            ..CrateAnnotations::default()
+        };
+
+        let sum = lhs + rhs;
+        assert_eq!(sum.gen_build_script, Some(false));
+        assert_eq!(
+            sum.deps,
+            Some(BTreeSet::from(["//:a".to_owned(), "//:b".to_owned()]))
+        );
+        assert_eq!(
+            sum.rustc_env,
+            Some(BTreeMap::from([
+                ("A".to_owned(), "lhs".to_owned()),
+                ("B".to_owned(), "rhs".to_owned()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_gen_binaries_serde() {
+        let all: GenBinaries = serde_json::from_str("true").unwrap();
+        assert_eq!(all, GenBinaries::All(true));
+        assert!(all.includes("any"));
+
+        let none: GenBinaries = serde_json::from_str("false").unwrap();
+        assert!(!none.includes("any"));
+
+        let some: GenBinaries = serde_json::from_str("[\"rustfmt\"]").unwrap();
+        assert!(some.includes("rustfmt"));
+        assert!(!some.includes("cargo-fmt"));
+        assert_eq!(serde_json::to_string(&some).unwrap(), "[\"rustfmt\"]");
+
+        // The default is omitted so the config digest of existing lockfiles is unchanged
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "generate_build_scripts": true,
+            "rendering": {"repository_name": "mock"},
+            "cargo_config": null,
+        }))
+        .unwrap();
+        assert_eq!(config.generate_binaries, GenBinaries::All(true));
+        assert!(!serde_json::to_string(&config)
+            .unwrap()
+            .contains("generate_binaries"));
+    }
+
+    #[test]
+    fn test_toml_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            textwrap::dedent(
+                r#"
+                generate_build_scripts = true
+                supported_platform_triples = ["x86_64-unknown-linux-gnu"]
+
+                [rendering]
+                repository_name = "crate_index"
+
+                [annotations."openssl-sys *"]
+                gen_build_script = false
+                build_script_data_glob = ["include/**"]
+                rustc_env = { OPENSSL_STATIC = "1" }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let config = Config::try_from_path(&path).unwrap();
+        assert!(config.generate_build_scripts);
+        assert_eq!(config.rendering.repository_name, "crate_index");
+        assert_eq!(
+            config.rendering.build_file_template,
+            default_build_file_template()
+        );
+
+        let openssl = &config.annotations[&CrateId::new("openssl-sys".to_owned(), "*".to_owned())];
+        assert_eq!(openssl.gen_build_script, Some(false));
+        assert_eq!(
+            openssl.build_script_data_glob,
+            Some(BTreeSet::from(["include/**".to_owned()]))
+        );
+        assert_eq!(
+            openssl.rustc_env,
+            Some(BTreeMap::from([(
+                "OPENSSL_STATIC".to_owned(),
+                "1".to_owned()
+            )]))
+        );
+    }
+
+    #[test]
+    fn test_config_layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, value: serde_json::Value| {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, value.to_string()).unwrap();
+            path
+        };
+
+        let base = write(
+            "base.json",
+            serde_json::json!({
+                "generate_build_scripts": true,
+                "rendering": {
+                    "repository_name": "base",
+                    "platforms_template": "@platforms//:{triple}",
+                },
+                "cargo_config": null,
+                "supported_platform_triples": ["x86_64-unknown-linux-gnu"],
+                "annotations": {
+                    "openssl-sys *": {"deps": ["//:base"], "gen_build_script": true},
+                },
+                "default_annotations": {"rustc_flags": ["--cap-lints=allow"]},
+            }),
+        );
+        let repo = write(
+            "repo.json",
+            serde_json::json!({
+                "rendering": {"repository_name": "repo"},
+                "annotations": {
+                    "openssl-sys *": {"deps": ["//:repo"], "gen_build_script": false},
+                },
+            }),
+        );
+        let local = write(
+            "local.json",
+            serde_json::json!({
+                "generate_build_scripts": false,
+                "default_annotations": {"rustc_flags": ["-Cdebuginfo=0"]},
+            }),
+        );
+        write(
+            "annotations/sys.json",
+            serde_json::json!({
+                "openssl-sys *": {"deps": ["//:dir"], "gen_build_script": true},
+                "libz-sys *": {"gen_build_script": true},
+            }),
+        );
+        write("annotations/README.txt", serde_json::json!("ignored"));
+
+        let config =
+            Config::try_from_layers(&[base], &[repo, local], &[dir.path().join("annotations")])
+                .unwrap();
+
+        // Scalars are taken from the last layer to set them
+        assert!(!config.generate_build_scripts);
+        assert_eq!(config.rendering.repository_name, "repo");
+        assert_eq!(config.rendering.platforms_template, "@platforms//:{triple}");
+        assert_eq!(
+            config.supported_platform_triples,
+            BTreeSet::from(["x86_64-unknown-linux-gnu".to_owned()])
+        );
+
+        // Annotations are combined with those of the directory applied first
+        let openssl = &config.annotations[&CrateId::new("openssl-sys".to_owned(), "*".to_owned())];
+        assert_eq!(
+            openssl.deps,
+            Some(BTreeSet::from([
+                "//:base".to_owned(),
+                "//:dir".to_owned(),
+                "//:repo".to_owned(),
+            ]))
+        );
+        assert_eq!(openssl.gen_build_script, Some(false));
+        assert!(config
+            .annotations
+            .contains_key(&CrateId::new("libz-sys".to_owned(), "*".to_owned())));
+
+        // Default annotations are combined the same way
+        assert_eq!(
+            config.default_annotations.unwrap().rustc_flags,
+            Some(vec![
+                "--cap-lints=allow".to_owned(),
+                "-Cdebuginfo=0".to_owned()
+            ])
+        );
+
+        // Annotations which are not defined by the workspace's own layers are shared
+        assert_eq!(
+            config.shared_annotations,
+            BTreeSet::from([CrateId::new("libz-sys".to_owned(), "*".to_owned())])
+        );
+    }
+
+    #[test]
+    fn test_config_layer_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, value: serde_json::Value| {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, value.to_string()).unwrap();
+            path
+        };
+
+        // The same crate is annotated by several files of an annotations directory which
+        // are applied in the order of their names.
+        write(
+            "annotations/a.json",
+            serde_json::json!({
+                "openssl-sys *": {
+                    "deps": ["//:a"],
+                    "gen_build_script": false,
+                    "rustc_env": {"OPENSSL_STATIC": "0", "OPENSSL_NO_VENDOR": "1"},
+                },
+                "zlib *": {"gen_build_script": true},
+            }),
+        );
+        write(
+            "annotations/b.json",
+            serde_json::json!({
+                "openssl-sys *": {
+                    "deps": ["//:b"],
+                    "gen_build_script": true,
+                    "rustc_env": {"OPENSSL_STATIC": "1"},
+                },
+            }),
+        );
+        let base = write(
+            "base.json",
+            serde_json::json!({
+                "generate_build_scripts": true,
+                "rendering": {"repository_name": "base", "vendor_mode": "remote"},
+                "cargo_config": {"net": {"offline": true}},
+                "license_policy": {"deny": ["GPL-3.0"]},
+                "default_annotations": {"rustc_flags": ["--cap-lints=allow"]},
+                "annotations": {
+                    "openssl-sys *": {"rustc_env": {"OPENSSL_NO_VENDOR": "0"}},
+                },
+            }),
+        );
+        let repo = write(
+            "repo.json",
+            serde_json::json!({
+                "rendering": {"repository_name": "repo", "vendor_mode": null},
+                "cargo_config": null,
+                "license_policy": null,
+                "default_annotations": null,
+                "annotations": {
+                    "openssl-sys *": {"gen_build_script": null},
+                },
+            }),
+        );
+
+        let config =
+            Config::try_from_layers(&[base], &[repo], &[dir.path().join("annotations")]).unwrap();
+
+        // Later files and layers take precedence for single values and entries of dictionaries
+        let openssl = &config.annotations[&CrateId::new("openssl-sys".to_owned(), "*".to_owned())];
+        assert_eq!(
+            openssl.deps,
+            Some(BTreeSet::from(["//:a".to_owned(), "//:b".to_owned()]))
+        );
+        assert_eq!(
+            openssl.rustc_env,
+            Some(BTreeMap::from([
+                ("OPENSSL_NO_VENDOR".to_owned(), "0".to_owned()),
+                ("OPENSSL_STATIC".to_owned(), "1".to_owned()),
+            ]))
+        );
+
+        // A `null` annotation field leaves the value of earlier layers unchanged
+        assert_eq!(openssl.gen_build_script, Some(true));
+
+        // While a `null` setting replaces the value of earlier layers
+        assert_eq!(config.rendering.repository_name, "repo");
+        assert!(config.rendering.vendor_mode.is_none());
+        assert_eq!(config.cargo_config, None);
+        assert!(config.license_policy.is_none());
+
+        // Except for default annotations which are combined like annotations
+        assert_eq!(
+            config.default_annotations.unwrap().rustc_flags,
+            Some(vec!["--cap-lints=allow".to_owned()])
+        );
+
+        // Settings which are not overridden are kept
+        assert!(config.generate_build_scripts);
+
+        assert_eq!(
+            config.shared_annotations,
+            BTreeSet::from([CrateId::new("zlib".to_owned(), "*".to_owned())])
+        );
+    }
+
     #[test]
     fn test_crate_id_semver_matches() {
         let mut package = mock_cargo_metadata_package();
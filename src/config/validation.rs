@@ -0,0 +1,431 @@
+//! Validation of a [Config] which reports all problems at once
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use cargo_metadata::Metadata as CargoMetadata;
+use cfg_expr::targets::get_builtin_target_by_triple;
+use cfg_expr::Expression;
+use serde::Serialize;
+
+use crate::config::{Config, CrateAnnotations, RenderConfig};
+use crate::utils::starlark::Label;
+
+/// A single problem found in a [Config]
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct ValidationError {
+    /// The path to the offending field. Eg. `annotations["openssl 0.10.36"].deps`
+    pub location: String,
+
+    /// A description of the problem
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(location: String, message: String) -> Self {
+        Self { location, message }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Check a [Config] for problems. If `metadata` is provided, annotations are also checked
+/// for whether or not they apply to any package in the spliced workspace.
+pub fn validate_config(config: &Config, metadata: Option<&CargoMetadata>) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    for (crate_id, annotation) in config.annotations.iter() {
+        let location = format!("annotations[\"{}\"]", crate_id);
+
+        if let Some(Err(err)) = crate_id.name_regex() {
+            errors.push(ValidationError::new(
+                location.clone(),
+                format!("Invalid crate name pattern '{}': {}", crate_id.name, err),
+            ));
+        }
+
+        if let Some(metadata) = metadata {
+            if !metadata.packages.iter().any(|pkg| crate_id.matches(pkg)) {
+                errors.push(ValidationError::new(
+                    location.clone(),
+                    "The annotation does not match any package in the workspace".to_owned(),
+                ));
+            }
+        }
+
+        errors.extend(validate_annotation(&location, annotation));
+    }
+
+    if let Some(annotation) = &config.default_annotations {
+        errors.extend(validate_annotation("default_annotations", annotation));
+        if annotation.skip_default_annotations.is_some() {
+            errors.push(ValidationError::new(
+                "default_annotations.skip_default_annotations".to_owned(),
+                "The field is not supported in default annotations".to_owned(),
+            ));
+        }
+    }
+
+    for triple in config.supported_platform_triples.iter() {
+        if get_builtin_target_by_triple(triple).is_none() {
+            errors.push(ValidationError::new(
+                "supported_platform_triples".to_owned(),
+                format!("Unknown platform triple: {}", triple),
+            ));
+        }
+    }
+
+    errors.extend(validate_render_config(&config.rendering));
+
+    errors.sort();
+    errors
+}
+
+/// Check the labels of a [CrateAnnotations] and the platforms and fields of its platform annotations
+fn validate_annotation(location: &str, annotation: &CrateAnnotations) -> Vec<ValidationError> {
+    let mut errors = validate_annotation_labels(location, annotation);
+    errors.extend(validate_visibility(location, annotation));
+
+    for (platform, platform_annotation) in annotation.platform_annotations.iter().flatten() {
+        let location = format!("{}.platform_annotations[\"{}\"]", location, platform);
+        errors.extend(validate_platform(&location, platform));
+        errors.extend(
+            platform_annotation
+                .unsupported_platform_fields()
+                .into_iter()
+                .map(|field| {
+                    ValidationError::new(
+                        format!("{}.{}", location, field),
+                        "The field is not supported in platform annotations".to_owned(),
+                    )
+                }),
+        );
+        errors.extend(validate_annotation_labels(&location, platform_annotation));
+    }
+
+    errors
+}
+
+/// Ensure a platform is either a known platform triple or a valid `cfg(...)` expression
+fn validate_platform(location: &str, platform: &str) -> Option<ValidationError> {
+    let message = if platform.starts_with("cfg(") {
+        Expression::parse(platform)
+            .err()
+            .map(|err| format!("Invalid cfg expression: {}", err))
+    } else if get_builtin_target_by_triple(platform).is_none() {
+        Some(format!("Unknown platform triple: {}", platform))
+    } else {
+        None
+    };
+
+    message.map(|message| ValidationError::new(location.to_owned(), message))
+}
+
+/// Ensure all fields of a [CrateAnnotations] which expect Bazel labels contain valid labels
+fn validate_annotation_labels(
+    location: &str,
+    annotation: &CrateAnnotations,
+) -> Vec<ValidationError> {
+    let fields: [(&str, &Option<BTreeSet<String>>); 11] = [
+        ("deps", &annotation.deps),
+        ("proc_macro_deps", &annotation.proc_macro_deps),
+        ("data", &annotation.data),
+        ("compile_data", &annotation.compile_data),
+        ("rustc_env_files", &annotation.rustc_env_files),
+        ("build_script_deps", &annotation.build_script_deps),
+        (
+            "build_script_proc_macro_deps",
+            &annotation.build_script_proc_macro_deps,
+        ),
+        ("build_script_data", &annotation.build_script_data),
+        ("build_script_tools", &annotation.build_script_tools),
+        ("patches", &annotation.patches),
+        ("visibility", &annotation.visibility),
+    ];
+
+    let override_target = annotation
+        .override_target
+        .as_ref()
+        .map(|label| ("override_target", label));
+
+    fields
+        .iter()
+        .filter_map(|(field, labels)| labels.as_ref().map(|labels| (*field, labels)))
+        .flat_map(|(field, labels)| labels.iter().map(move |label| (field, label)))
+        .chain(override_target)
+        .filter(|(_, label)| Label::from_str(label).is_err())
+        .map(|(field, label)| {
+            ValidationError::new(
+                format!("{}.{}", location, field),
+                format!("Malformed Bazel label: '{}'", label),
+            )
+        })
+        .collect()
+}
+
+/// Ensure the `visibility` labels of a [CrateAnnotations] name their repository. They're rendered
+/// into BUILD files within each crate's own repository where labels such as `//:__subpackages__`
+/// would refer to that repository instead of the workspace.
+fn validate_visibility(location: &str, annotation: &CrateAnnotations) -> Vec<ValidationError> {
+    annotation
+        .visibility
+        .iter()
+        .flatten()
+        .filter(|label| !label.starts_with('@') && !label.starts_with("//visibility:"))
+        .filter(|label| Label::from_str(label).is_ok())
+        .map(|label| {
+            ValidationError::new(
+                format!("{}.visibility", location),
+                format!(
+                    "Visibility labels must be repository-qualified (eg. '@//pkg:__pkg__'): '{}'",
+                    label
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Ensure all templates in a [RenderConfig] contain the placeholders required to produce
+/// unique values and, where applicable, render into valid Bazel labels.
+fn validate_render_config(config: &RenderConfig) -> Vec<ValidationError> {
+    let templates: [(&str, &String, &[&str], bool); 5] = [
+        (
+            "build_file_template",
+            &config.build_file_template,
+            &["{name}", "{version}"],
+            true,
+        ),
+        (
+            "crate_label_template",
+            &config.crate_label_template,
+            &["{name}", "{version}", "{target}"],
+            true,
+        ),
+        (
+            "crates_module_template",
+            &config.crates_module_template,
+            &["{file}"],
+            true,
+        ),
+        (
+            "crate_repository_template",
+            &config.crate_repository_template,
+            &["{name}", "{version}"],
+            false,
+        ),
+        (
+            "platforms_template",
+            &config.platforms_template,
+            &["{triple}"],
+            true,
+        ),
+    ];
+
+    let mut errors = Vec::new();
+    for (field, template, placeholders, is_label) in templates {
+        let location = format!("rendering.{}", field);
+
+        let missing: Vec<&str> = placeholders
+            .iter()
+            .filter(|placeholder| !template.contains(*placeholder))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            errors.push(ValidationError::new(
+                location.clone(),
+                format!(
+                    "The template '{}' is missing required placeholders: {}",
+                    template,
+                    missing.join(", ")
+                ),
+            ));
+        }
+
+        // Render the template with sample values to ensure it produces a valid label
+        if is_label {
+            let rendered = template
+                .replace("{repository}", &config.repository_name)
+                .replace("{name}", "sample")
+                .replace("{version}", "0.1.0")
+                .replace("{target}", "sample")
+                .replace("{file}", "defs.bzl")
+                .replace("{triple}", "x86_64-unknown-linux-gnu");
+            if Label::from_str(&rendered).is_err() {
+                errors.push(ValidationError::new(
+                    location,
+                    format!(
+                        "The template '{}' does not render a valid Bazel label. Eg. '{}'",
+                        template, rendered
+                    ),
+                ));
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use crate::config::CrateId;
+    use crate::test;
+
+    #[test]
+    fn valid_config() {
+        // The templates of a default `RenderConfig` are empty so the serde defaults are used
+        let mut config = Config {
+            rendering: serde_json::from_value(serde_json::json!({
+                "repository_name": "crate_index"
+            }))
+            .unwrap(),
+            ..Config::default()
+        };
+        config
+            .supported_platform_triples
+            .insert("x86_64-unknown-linux-gnu".to_owned());
+        config.annotations.insert(
+            CrateId::new("openssl-sys".to_owned(), "*".to_owned()),
+            CrateAnnotations {
+                deps: Some(BTreeSet::from(["@openssl//:ssl".to_owned()])),
+                build_script_tools: Some(BTreeSet::from(["//tools:perl".to_owned()])),
+                ..CrateAnnotations::default()
+            },
+        );
+
+        let errors = validate_config(&config, Some(&test::metadata::build_scripts()));
+        assert_eq!(errors, Vec::new());
+    }
+
+    #[test]
+    fn reports_all_problems() {
+        let mut config = Config {
+            rendering: serde_json::from_value(serde_json::json!({
+                "repository_name": "crate_index",
+                "build_file_template": "//:BUILD.{name}.bazel",
+                "platforms_template": "@rules_rust//rust/platform:x86_64",
+            }))
+            .unwrap(),
+            ..Config::default()
+        };
+        config
+            .supported_platform_triples
+            .insert("x86_64-unknown-made-up".to_owned());
+        config.annotations.insert(
+            CrateId::new("mock-crate".to_owned(), "0.1.0".to_owned()),
+            CrateAnnotations::default(),
+        );
+        config.annotations.insert(
+            CrateId::new("/(openssl/".to_owned(), "*".to_owned()),
+            CrateAnnotations::default(),
+        );
+        config.annotations.insert(
+            CrateId::new("openssl".to_owned(), "0.10.36".to_owned()),
+            CrateAnnotations {
+                data: Some(BTreeSet::from(["not a label".to_owned()])),
+                build_script_tools: Some(BTreeSet::from(["//foo:bar:baz".to_owned()])),
+                visibility: Some(BTreeSet::from([
+                    "//visibility:private".to_owned(),
+                    "@//foo:__pkg__".to_owned(),
+                    "//:__subpackages__".to_owned(),
+                ])),
+                override_target: Some("@openssl//:ssl:crypto".to_owned()),
+                ..CrateAnnotations::default()
+            },
+        );
+        config.default_annotations = Some(CrateAnnotations {
+            compile_data: Some(BTreeSet::from(["not a label".to_owned()])),
+            skip_default_annotations: Some(true),
+            ..CrateAnnotations::default()
+        });
+
+        let errors = validate_config(&config, Some(&test::metadata::build_scripts()));
+        let locations: Vec<&str> = errors.iter().map(|e| e.location.as_str()).collect();
+        assert_eq!(
+            locations,
+            vec![
+                "annotations[\"/(openssl/ *\"]",
+                "annotations[\"/(openssl/ *\"]",
+                "annotations[\"mock-crate 0.1.0\"]",
+                "annotations[\"openssl 0.10.36\"].build_script_tools",
+                "annotations[\"openssl 0.10.36\"].data",
+                "annotations[\"openssl 0.10.36\"].override_target",
+                "annotations[\"openssl 0.10.36\"].visibility",
+                "default_annotations.compile_data",
+                "default_annotations.skip_default_annotations",
+                "rendering.build_file_template",
+                "rendering.platforms_template",
+                "supported_platform_triples",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_platform_annotations() {
+        let mut config = Config::default();
+        config.annotations.insert(
+            CrateId::new("openssl-sys".to_owned(), "*".to_owned()),
+            CrateAnnotations {
+                platform_annotations: Some(BTreeMap::from([
+                    (
+                        "cfg(target_os = \"linux\")".to_owned(),
+                        CrateAnnotations {
+                            build_script_env: Some(BTreeMap::from([(
+                                "OPENSSL_STATIC".to_owned(),
+                                "1".to_owned(),
+                            )])),
+                            ..CrateAnnotations::default()
+                        },
+                    ),
+                    ("cfg(target_os = ".to_owned(), CrateAnnotations::default()),
+                    (
+                        "x86_64-apple-darwin".to_owned(),
+                        CrateAnnotations {
+                            gen_build_script: Some(false),
+                            deps: Some(BTreeSet::from(["not a label".to_owned()])),
+                            ..CrateAnnotations::default()
+                        },
+                    ),
+                ])),
+                ..CrateAnnotations::default()
+            },
+        );
+
+        let errors = validate_config(&config, None);
+        let locations: Vec<&str> = errors
+            .iter()
+            .map(|e| e.location.as_str())
+            .filter(|location| location.starts_with("annotations"))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "annotations[\"openssl-sys *\"].platform_annotations[\"cfg(target_os = \"]",
+                "annotations[\"openssl-sys *\"].platform_annotations[\"x86_64-apple-darwin\"].deps",
+                "annotations[\"openssl-sys *\"].platform_annotations[\"x86_64-apple-darwin\"].gen_build_script",
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_annotations_require_metadata() {
+        let mut config = Config::default();
+        config.annotations.insert(
+            CrateId::new("mock-crate".to_owned(), "0.1.0".to_owned()),
+            CrateAnnotations::default(),
+        );
+
+        let errors = validate_config(&config, None);
+        assert!(errors
+            .iter()
+            .all(|e| !e.location.starts_with("annotations")));
+    }
+}
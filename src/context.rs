@@ -1,7 +1,10 @@
 //! Convert annotated metadata into a renderable context
 
 pub mod crate_context;
+pub mod duplicates;
+pub mod graph;
 mod platforms;
+pub mod tree;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
@@ -13,7 +16,7 @@ use serde::{Deserialize, Serialize};
 use crate::config::CrateId;
 use crate::context::crate_context::{CrateContext, CrateDependency, Rule};
 use crate::context::platforms::resolve_cfg_platforms;
-use crate::lockfile::Digest;
+use crate::lockfile::{Digest, DigestInputs};
 use crate::metadata::Annotations;
 use crate::utils::starlark::{Select, SelectList};
 
@@ -26,6 +29,10 @@ pub struct Context {
     /// The collective checksum of all inputs to the context
     pub checksum: Option<Digest>,
 
+    /// Digests of each category of input used to compute `checksum`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_inputs: Option<DigestInputs>,
+
     /// The collection of all crates that make up the dependency graph
     pub crates: BTreeMap<CrateId, CrateContext>,
 
@@ -60,6 +67,7 @@ impl Context {
                     &annotations.lockfile.crates,
                     &annotations.pairred_extras,
                     annotations.config.generate_build_scripts,
+                    &annotations.config.generate_binaries,
                 );
                 let id = CrateId::new(context.name.clone(), context.version.clone());
                 (id, context)
@@ -111,6 +119,7 @@ impl Context {
 
         Ok(Self {
             checksum: None,
+            checksum_inputs: None,
             crates,
             binary_crates,
             workspace_members,
@@ -393,6 +402,30 @@ impl Context {
             .collect()
     }
 
+    /// Find all crates with a library, proc-macro or build script target which depends
+    /// on the crate with the given [CrateId].
+    pub fn crate_dependents(&self, crate_id: &CrateId) -> BTreeSet<&CrateId> {
+        let depends_on = |deps: &SelectList<CrateDependency>| {
+            deps.configurations().into_iter().any(|conf| {
+                deps.get_iter(conf)
+                    .expect("Iterating over known keys should never panic")
+                    .any(|dep| &dep.id == crate_id)
+            })
+        };
+
+        self.crates
+            .iter()
+            .filter(|(id, _)| *id != crate_id)
+            .filter(|(_, ctx)| {
+                depends_on(&ctx.common_attrs.deps)
+                    || depends_on(&ctx.common_attrs.proc_macro_deps)
+                    || matches!(&ctx.build_script_attrs, Some(attrs)
+                        if depends_on(&attrs.deps) || depends_on(&attrs.proc_macro_deps))
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
     /// Produce a list of binary dependencies with optional aliases which prevent duplicate
     /// targets from being generated.
     pub fn flat_binary_deps(&self) -> BTreeMap<CrateId, Option<String>> {
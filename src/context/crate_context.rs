@@ -2,13 +2,15 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
-use cargo_metadata::{Node, Package, PackageId};
+use cargo_metadata::{DependencyKind, Node, Package, PackageId};
 use serde::{Deserialize, Serialize};
 
-use crate::config::CrateId;
+use crate::config::{CrateAnnotations, CrateId, GenBinaries};
 use crate::metadata::{CrateAnnotation, Dependency, PairredExtras, SourceAnnotation};
 use crate::utils::sanitize_module_name;
-use crate::utils::starlark::{Glob, SelectList, SelectMap, SelectStringDict, SelectStringList};
+use crate::utils::starlark::{
+    Glob, SelectList, SelectMap, SelectStringDict, SelectStringList, SelectStringOrderedList,
+};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
 pub struct CrateDependency {
@@ -37,6 +39,25 @@ pub struct TargetAttributes {
     pub srcs: Glob,
 }
 
+/// An optional dependency which is no longer enabled after adjusting a crate's features
+#[derive(Debug, PartialEq, Eq)]
+struct DisabledDependency {
+    /// The package name of the dependency
+    name: String,
+
+    /// The sanitized name the dependency was renamed to, if any
+    alias: Option<String>,
+
+    /// The kind of dependency
+    kind: DependencyKind,
+}
+
+impl DisabledDependency {
+    fn matches(&self, dep: &CrateDependency) -> bool {
+        dep.id.name == self.name && dep.alias == self.alias
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
 pub enum Rule {
     /// `cargo_build_script`
@@ -75,8 +96,11 @@ pub struct CommonAttributes {
     #[serde(skip_serializing_if = "SelectList::should_skip_serializing")]
     pub deps: SelectList<CrateDependency>,
 
-    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
-    pub extra_deps: BTreeSet<String>,
+    #[serde(
+        skip_serializing_if = "SelectStringList::should_skip_serializing",
+        deserialize_with = "SelectList::deserialize_list_or_select"
+    )]
+    pub extra_deps: SelectStringList,
 
     #[serde(skip_serializing_if = "SelectList::should_skip_serializing")]
     pub deps_dev: SelectList<CrateDependency>,
@@ -89,8 +113,11 @@ pub struct CommonAttributes {
     #[serde(skip_serializing_if = "SelectList::should_skip_serializing")]
     pub proc_macro_deps: SelectList<CrateDependency>,
 
-    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
-    pub extra_proc_macro_deps: BTreeSet<String>,
+    #[serde(
+        skip_serializing_if = "SelectStringList::should_skip_serializing",
+        deserialize_with = "SelectList::deserialize_list_or_select"
+    )]
+    pub extra_proc_macro_deps: SelectStringList,
 
     #[serde(skip_serializing_if = "SelectList::should_skip_serializing")]
     pub proc_macro_deps_dev: SelectList<CrateDependency>,
@@ -101,13 +128,19 @@ pub struct CommonAttributes {
     #[serde(skip_serializing_if = "SelectStringList::should_skip_serializing")]
     pub rustc_env_files: SelectStringList,
 
-    #[serde(skip_serializing_if = "SelectStringList::should_skip_serializing")]
-    pub rustc_flags: SelectStringList,
+    #[serde(skip_serializing_if = "SelectStringOrderedList::should_skip_serializing")]
+    pub rustc_flags: SelectStringOrderedList,
 
     pub version: String,
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
+
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub visibility: BTreeSet<String>,
+
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub disable_pipelining: bool,
 }
 
 impl Default for CommonAttributes {
@@ -132,6 +165,8 @@ impl Default for CommonAttributes {
             rustc_flags: Default::default(),
             version: Default::default(),
             tags: Default::default(),
+            visibility: Default::default(),
+            disable_pipelining: Default::default(),
         }
     }
 }
@@ -153,14 +188,20 @@ pub struct BuildScriptAttributes {
     #[serde(skip_serializing_if = "SelectList::should_skip_serializing")]
     pub deps: SelectList<CrateDependency>,
 
-    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
-    pub extra_deps: BTreeSet<String>,
+    #[serde(
+        skip_serializing_if = "SelectStringList::should_skip_serializing",
+        deserialize_with = "SelectList::deserialize_list_or_select"
+    )]
+    pub extra_deps: SelectStringList,
 
     #[serde(skip_serializing_if = "SelectStringDict::should_skip_serializing")]
     pub build_script_env: SelectStringDict,
 
-    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
-    pub extra_proc_macro_deps: BTreeSet<String>,
+    #[serde(
+        skip_serializing_if = "SelectStringList::should_skip_serializing",
+        deserialize_with = "SelectList::deserialize_list_or_select"
+    )]
+    pub extra_proc_macro_deps: SelectStringList,
 
     #[serde(skip_serializing_if = "SelectList::should_skip_serializing")]
     pub proc_macro_deps: SelectList<CrateDependency>,
@@ -168,8 +209,8 @@ pub struct BuildScriptAttributes {
     #[serde(skip_serializing_if = "SelectStringDict::should_skip_serializing")]
     pub rustc_env: SelectStringDict,
 
-    #[serde(skip_serializing_if = "SelectStringList::should_skip_serializing")]
-    pub rustc_flags: SelectStringList,
+    #[serde(skip_serializing_if = "SelectStringOrderedList::should_skip_serializing")]
+    pub rustc_flags: SelectStringOrderedList,
 
     #[serde(skip_serializing_if = "SelectStringList::should_skip_serializing")]
     pub rustc_env_files: SelectStringList,
@@ -237,6 +278,11 @@ pub struct CrateContext {
     /// Additional text to add to the generated BUILD file.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub additive_build_file_content: Option<String>,
+
+    /// The label of an existing Bazel target which replaces the crate. Replaced crates
+    /// have no repository or BUILD file and dependents use this label instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_target: Option<String>,
 }
 
 impl CrateContext {
@@ -246,6 +292,7 @@ impl CrateContext {
         source_annotations: &BTreeMap<PackageId, SourceAnnotation>,
         extras: &BTreeMap<CrateId, PairredExtras>,
         include_build_scripts: bool,
+        gen_binaries: &GenBinaries,
     ) -> Self {
         let package: &Package = &packages[&annotation.node.id];
         let current_crate_id = CrateId::new(package.name.clone(), package.version.to_string());
@@ -275,9 +322,19 @@ impl CrateContext {
             .clone()
             .map(new_crate_dep);
 
+        // Apply any features removed or overridden by annotations
+        let resolved_features: BTreeSet<String> =
+            annotation.node.features.iter().cloned().collect();
+        let crate_features = match extras.get(&current_crate_id) {
+            Some(extra) => Self::adjust_features(package, &resolved_features, &extra.crate_extra),
+            None => resolved_features.clone(),
+        };
+        let disabled_deps =
+            Self::disabled_optional_deps(package, &resolved_features, &crate_features);
+
         // Gather all "common" attributes
         let mut common_attrs = CommonAttributes {
-            crate_features: annotation.node.features.iter().cloned().collect(),
+            crate_features,
             deps,
             deps_dev,
             edition: package.edition.clone(),
@@ -286,12 +343,29 @@ impl CrateContext {
             version: package.version.to_string(),
             ..Default::default()
         };
+        for dep in disabled_deps.iter() {
+            if dep.kind == DependencyKind::Normal {
+                common_attrs
+                    .deps
+                    .retain(|crate_dep| !dep.matches(crate_dep));
+                common_attrs
+                    .proc_macro_deps
+                    .retain(|crate_dep| !dep.matches(crate_dep));
+            }
+        }
 
         let include_build_scripts =
             Self::crate_includes_build_script(package, extras, include_build_scripts);
 
+        let gen_binaries = Self::crate_generated_binaries(package, extras, gen_binaries);
+
         // Iterate over each target and produce a Bazel target for all supported "kinds"
-        let targets = Self::collect_targets(&annotation.node, packages, include_build_scripts);
+        let targets = Self::collect_targets(
+            &annotation.node,
+            packages,
+            include_build_scripts,
+            &gen_binaries,
+        );
 
         // Parse the library crate name from the set of included targets
         let library_target_name = {
@@ -330,12 +404,18 @@ impl CrateContext {
                 None,
             );
 
-            let build_deps = annotation.deps.build_deps.clone().map(new_crate_dep);
-            let build_proc_macro_deps = annotation
+            let mut build_deps = annotation.deps.build_deps.clone().map(new_crate_dep);
+            let mut build_proc_macro_deps = annotation
                 .deps
                 .build_proc_macro_deps
                 .clone()
                 .map(new_crate_dep);
+            for dep in disabled_deps.iter() {
+                if dep.kind == DependencyKind::Build {
+                    build_deps.retain(|crate_dep| !dep.matches(crate_dep));
+                    build_proc_macro_deps.retain(|crate_dep| !dep.matches(crate_dep));
+                }
+            }
 
             Some(BuildScriptAttributes {
                 deps: build_deps,
@@ -364,6 +444,7 @@ impl CrateContext {
             build_script_attrs,
             license,
             additive_build_file_content: None,
+            override_target: None,
         }
         .with_overrides(extras)
     }
@@ -375,20 +456,11 @@ impl CrateContext {
         if let Some(pairred_override) = extras.get(&id) {
             let crate_extra = &pairred_override.crate_extra;
 
-            // Deps
-            if let Some(extra) = &crate_extra.deps {
-                self.common_attrs.extra_deps = extra.clone();
-            }
-
-            // Proc macro deps
-            if let Some(extra) = &crate_extra.proc_macro_deps {
-                self.common_attrs.extra_proc_macro_deps = extra.clone();
-            }
-
-            // Compile data
-            if let Some(extra) = &crate_extra.compile_data {
-                for data in extra.iter() {
-                    self.common_attrs.compile_data.insert(data.clone(), None);
+            // Attributes which support platform specific values
+            self.apply_selectable_extras(crate_extra, None);
+            if let Some(platform_annotations) = &crate_extra.platform_annotations {
+                for (platform, platform_extra) in platform_annotations {
+                    self.apply_selectable_extras(platform_extra, Some(platform));
                 }
             }
 
@@ -404,72 +476,55 @@ impl CrateContext {
                 }
             }
 
-            // Data
-            if let Some(extra) = &crate_extra.data {
-                for data in extra.iter() {
-                    self.common_attrs.data.insert(data.clone(), None);
-                }
-            }
-
             // Data glob
             if let Some(extra) = &crate_extra.data_glob {
                 self.common_attrs.data_glob.extend(extra.clone());
             }
 
-            // Rustc flags
-            // TODO: SelectList is currently backed by `BTreeSet` which is generally incorrect
-            // for rustc flags. Should SelectList be refactored?
-            if let Some(extra) = &crate_extra.rustc_flags {
-                for data in extra.iter() {
-                    self.common_attrs.rustc_flags.insert(data.clone(), None);
+            // Tags
+            if let Some(extra) = &crate_extra.tags {
+                for tag in extra.iter() {
+                    if !self.common_attrs.tags.contains(tag) {
+                        self.common_attrs.tags.push(tag.clone());
+                    }
                 }
             }
 
-            // Rustc env
-            if let Some(extra) = &crate_extra.rustc_env {
-                self.common_attrs.rustc_env.insert(extra.clone(), None);
+            // Visibility
+            if let Some(extra) = &crate_extra.visibility {
+                self.common_attrs.visibility.extend(extra.clone());
             }
 
-            // Rustc env files
-            if let Some(extra) = &crate_extra.rustc_env_files {
-                for data in extra.iter() {
-                    self.common_attrs.rustc_env_files.insert(data.clone(), None);
-                }
+            // Pipelining
+            if let Some(disable_pipelining) = crate_extra.disable_pipelining {
+                self.common_attrs.disable_pipelining = disable_pipelining;
             }
 
-            // Build script Attributes
-            if let Some(attrs) = &mut self.build_script_attrs {
-                // Deps
-                if let Some(extra) = &crate_extra.build_script_deps {
-                    attrs.extra_deps = extra.clone();
-                }
-
-                // Proc macro deps
-                if let Some(extra) = &crate_extra.build_script_proc_macro_deps {
-                    attrs.extra_proc_macro_deps = extra.clone();
+            // Target attributes
+            for rule in self.targets.iter_mut() {
+                let attrs = match rule {
+                    Rule::BuildScript(attrs)
+                    | Rule::ProcMacro(attrs)
+                    | Rule::Library(attrs)
+                    | Rule::Binary(attrs) => attrs,
+                };
+
+                if let Some(extra) = &crate_extra.srcs_exclude {
+                    attrs.srcs.exclude.extend(extra.iter().cloned());
                 }
 
-                // Data
-                if let Some(extra) = &crate_extra.build_script_data {
-                    for data in extra {
-                        attrs.data.insert(data.clone(), None);
+                if let Rule::Library(attrs) | Rule::ProcMacro(attrs) = rule {
+                    if let Some(crate_root) = &crate_extra.crate_root {
+                        attrs.crate_root = Some(crate_root.clone());
                     }
                 }
+            }
 
-                // Data glob
+            // Build script data glob
+            if let Some(attrs) = &mut self.build_script_attrs {
                 if let Some(extra) = &crate_extra.build_script_data_glob {
                     attrs.data_glob.extend(extra.clone());
                 }
-
-                // Rustc env
-                if let Some(extra) = &crate_extra.build_script_rustc_env {
-                    attrs.rustc_env.insert(extra.clone(), None);
-                }
-
-                // Build script env
-                if let Some(extra) = &crate_extra.build_script_env {
-                    attrs.build_script_env.insert(extra.clone(), None);
-                }
             }
 
             // Extra build contents
@@ -511,11 +566,217 @@ impl CrateContext {
                     }
                 }
             }
+
+            // Replacement targets
+            if let Some(override_target) = &crate_extra.override_target {
+                self.override_target = Some(override_target.clone());
+                self.repository = None;
+            }
         }
 
         self
     }
 
+    /// Insert the values of annotations which are rendered into `select` statements. If a
+    /// `configuration` (a platform triple or `cfg(...)` expression) is provided, the values
+    /// only apply to matching platforms.
+    fn apply_selectable_extras(
+        &mut self,
+        extra: &CrateAnnotations,
+        configuration: Option<&String>,
+    ) {
+        let insert_all = |select: &mut SelectStringList, values: &Option<BTreeSet<String>>| {
+            for value in values.iter().flatten() {
+                select.insert(value.clone(), configuration.cloned());
+            }
+        };
+
+        // Deps
+        insert_all(&mut self.common_attrs.extra_deps, &extra.deps);
+
+        // Proc macro deps
+        insert_all(
+            &mut self.common_attrs.extra_proc_macro_deps,
+            &extra.proc_macro_deps,
+        );
+
+        // Compile data
+        insert_all(&mut self.common_attrs.compile_data, &extra.compile_data);
+
+        // Data
+        insert_all(&mut self.common_attrs.data, &extra.data);
+
+        // Rustc flags
+        if let Some(flags) = &extra.rustc_flags {
+            for flag in flags.iter() {
+                self.common_attrs
+                    .rustc_flags
+                    .insert(flag.clone(), configuration.cloned());
+            }
+        }
+
+        // Rustc env
+        if let Some(env) = &extra.rustc_env {
+            self.common_attrs
+                .rustc_env
+                .insert(env.clone(), configuration.cloned());
+        }
+
+        // Rustc env files
+        insert_all(
+            &mut self.common_attrs.rustc_env_files,
+            &extra.rustc_env_files,
+        );
+
+        // Build script Attributes
+        if let Some(attrs) = &mut self.build_script_attrs {
+            // Deps
+            insert_all(&mut attrs.extra_deps, &extra.build_script_deps);
+
+            // Proc macro deps
+            insert_all(
+                &mut attrs.extra_proc_macro_deps,
+                &extra.build_script_proc_macro_deps,
+            );
+
+            // Data
+            insert_all(&mut attrs.data, &extra.build_script_data);
+
+            // Tools
+            insert_all(&mut attrs.tools, &extra.build_script_tools);
+
+            // Rustc env
+            if let Some(env) = &extra.build_script_rustc_env {
+                attrs.rustc_env.insert(env.clone(), configuration.cloned());
+            }
+
+            // Build script env
+            if let Some(env) = &extra.build_script_env {
+                attrs
+                    .build_script_env
+                    .insert(env.clone(), configuration.cloned());
+            }
+        }
+    }
+
+    /// Remove or override the features Cargo resolved for a package using annotations.
+    fn adjust_features(
+        package: &Package,
+        resolved_features: &BTreeSet<String>,
+        extra: &CrateAnnotations,
+    ) -> BTreeSet<String> {
+        if let Some(features) = &extra.crate_features_override {
+            return Self::enable_features(package, features);
+        }
+
+        match &extra.crate_features_remove {
+            Some(remove) => resolved_features.difference(remove).cloned().collect(),
+            None => resolved_features.clone(),
+        }
+    }
+
+    /// Collect the given features and all features they transitively enable.
+    fn enable_features(package: &Package, features: &BTreeSet<String>) -> BTreeSet<String> {
+        let mut enabled = BTreeSet::new();
+        let mut queue: Vec<String> = features.iter().cloned().collect();
+        while let Some(feature) = queue.pop() {
+            if !enabled.insert(feature.clone()) {
+                continue;
+            }
+
+            for entry in package.features.get(&feature).into_iter().flatten() {
+                match entry.split_once('/') {
+                    // `dep:name` only enables an optional dependency
+                    _ if entry.starts_with("dep:") => {}
+                    // `name/feature` also enables the implicit feature of an optional
+                    // dependency whereas `name?/feature` does not.
+                    Some((dep, _)) => {
+                        if !dep.ends_with('?')
+                            && (package.features.contains_key(dep)
+                                || Self::has_implicit_feature(package, dep))
+                        {
+                            queue.push(dep.to_owned());
+                        }
+                    }
+                    None => queue.push(entry.clone()),
+                }
+            }
+        }
+
+        enabled
+    }
+
+    /// Optional dependencies have an implicit feature of the same name unless they're
+    /// referenced with `dep:` syntax.
+    fn has_implicit_feature(package: &Package, name: &str) -> bool {
+        let explicit = format!("dep:{}", name);
+        package
+            .dependencies
+            .iter()
+            .any(|dep| dep.optional && dep.rename.as_deref().unwrap_or(&dep.name) == name)
+            && !package.features.values().flatten().any(|e| e == &explicit)
+    }
+
+    /// Determine whether or not an optional dependency (identified by the name it's
+    /// referred to as in the package's manifest) is enabled by a set of features.
+    fn is_optional_dep_enabled(package: &Package, features: &BTreeSet<String>, name: &str) -> bool {
+        let explicit = format!("dep:{}", name);
+        let entries = |feature: &String| package.features.get(feature).into_iter().flatten();
+
+        if Self::has_implicit_feature(package, name) && features.contains(name) {
+            return true;
+        }
+
+        features.iter().flat_map(entries).any(|entry| {
+            entry == &explicit || matches!(entry.split_once('/'), Some((dep, _)) if dep == name)
+        })
+    }
+
+    /// Locate optional dependencies which were enabled by the features Cargo resolved but
+    /// are not enabled by the adjusted set of features.
+    fn disabled_optional_deps(
+        package: &Package,
+        resolved_features: &BTreeSet<String>,
+        features: &BTreeSet<String>,
+    ) -> Vec<DisabledDependency> {
+        if resolved_features == features {
+            return Vec::new();
+        }
+
+        let dependency_name = |dep: &cargo_metadata::Dependency| -> String {
+            dep.rename.clone().unwrap_or_else(|| dep.name.clone())
+        };
+
+        let disabled: Vec<DisabledDependency> = package
+            .dependencies
+            .iter()
+            .filter(|dep| dep.optional)
+            .filter(|dep| {
+                let name = dependency_name(dep);
+                Self::is_optional_dep_enabled(package, resolved_features, &name)
+                    && !Self::is_optional_dep_enabled(package, features, &name)
+            })
+            .map(|dep| DisabledDependency {
+                name: dep.name.clone(),
+                alias: dep.rename.as_deref().map(sanitize_module_name),
+                kind: dep.kind,
+            })
+            .collect();
+
+        // Dependencies which are also required (Eg. for other platforms) cannot be removed
+        disabled
+            .into_iter()
+            .filter(|disabled| {
+                !package.dependencies.iter().any(|dep| {
+                    !dep.optional
+                        && dep.kind == disabled.kind
+                        && dep.name == disabled.name
+                        && dep.rename.as_deref().map(sanitize_module_name) == disabled.alias
+                })
+            })
+            .collect()
+    }
+
     /// Determine whether or not a crate __should__ include a build script
     /// (build.rs) if it happens to have one.
     fn crate_includes_build_script(
@@ -535,11 +796,29 @@ impl CrateContext {
             .unwrap_or(default_generate_build_script)
     }
 
+    fn crate_generated_binaries(
+        package: &Package,
+        overrides: &BTreeMap<CrateId, PairredExtras>,
+        default_gen_binaries: &GenBinaries,
+    ) -> GenBinaries {
+        // Locate extra settings for the current package.
+        let settings = overrides
+            .iter()
+            .find(|(_, settings)| settings.package_id == package.id);
+
+        // If the crate has extra settings, which explicitly set `gen_binaries`, always use
+        // this value, otherwise, fallback to the provided default.
+        settings
+            .and_then(|(_, settings)| settings.crate_extra.gen_binaries.clone())
+            .unwrap_or_else(|| default_gen_binaries.clone())
+    }
+
     /// Collect all Bazel targets that should be generated for a particular Package
     fn collect_targets(
         node: &Node,
         packages: &BTreeMap<PackageId, Package>,
         include_build_scripts: bool,
+        gen_binaries: &GenBinaries,
     ) -> Vec<Rule> {
         let package = &packages[&node.id];
 
@@ -597,7 +876,7 @@ impl CrateContext {
                         }
 
                         // Check to see if the dependencies is a library target
-                        if kind == "bin" {
+                        if kind == "bin" && gen_binaries.includes(&target.name) {
                             return Some(Rule::Binary(TargetAttributes {
                                 crate_name: target.name.clone(),
                                 crate_root,
@@ -617,7 +896,6 @@ impl CrateContext {
 mod test {
     use super::*;
 
-    use crate::config::CrateAnnotations;
     use crate::metadata::Annotations;
 
     fn common_annotations() -> Annotations {
@@ -643,6 +921,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             false,
+            &GenBinaries::default(),
         );
 
         assert_eq!(context.name, "common");
@@ -691,6 +970,7 @@ mod test {
             &annotations.lockfile.crates,
             &pairred_extras,
             false,
+            &GenBinaries::default(),
         );
 
         assert_eq!(context.name, "common");
@@ -715,6 +995,292 @@ mod test {
         );
     }
 
+    #[test]
+    fn context_with_selected_binaries() {
+        let annotations = common_annotations();
+
+        let package_id = PackageId {
+            repr: "common 0.1.0 (path+file://{TEMP_DIR}/common)".to_owned(),
+        };
+
+        let binaries = |gen_binaries: Option<GenBinaries>, default: GenBinaries| {
+            let pairred_extras = BTreeMap::from([(
+                CrateId::new("common".to_owned(), "0.1.0".to_owned()),
+                PairredExtras {
+                    package_id: package_id.clone(),
+                    crate_extra: CrateAnnotations {
+                        gen_binaries,
+                        ..CrateAnnotations::default()
+                    },
+                },
+            )]);
+
+            CrateContext::new(
+                &annotations.metadata.crates[&package_id],
+                &annotations.metadata.packages,
+                &annotations.lockfile.crates,
+                &pairred_extras,
+                false,
+                &default,
+            )
+            .targets
+            .into_iter()
+            .filter_map(|rule| match rule {
+                Rule::Binary(attrs) => Some(attrs.crate_name),
+                _ => None,
+            })
+            .collect::<Vec<String>>()
+        };
+
+        let selected = GenBinaries::Some(BTreeSet::from(["common-bin".to_owned()]));
+        let unknown = GenBinaries::Some(BTreeSet::from(["other-bin".to_owned()]));
+
+        // The global default applies when the annotation is unset
+        assert_eq!(binaries(None, GenBinaries::default()), vec!["common-bin"]);
+        assert!(binaries(None, GenBinaries::All(false)).is_empty());
+        assert!(binaries(None, unknown.clone()).is_empty());
+
+        // Annotations take precedence over the global default
+        assert_eq!(
+            binaries(Some(selected), GenBinaries::All(false)),
+            vec!["common-bin"]
+        );
+        assert!(binaries(Some(unknown), GenBinaries::default()).is_empty());
+    }
+
+    #[test]
+    fn context_with_target_attribute_overrides() {
+        let annotations = common_annotations();
+
+        let package_id = PackageId {
+            repr: "common 0.1.0 (path+file://{TEMP_DIR}/common)".to_owned(),
+        };
+
+        let pairred_extras = BTreeMap::from([(
+            CrateId::new("common".to_owned(), "0.1.0".to_owned()),
+            PairredExtras {
+                package_id: package_id.clone(),
+                crate_extra: CrateAnnotations {
+                    tags: Some(BTreeSet::from(["no-remote-exec".to_owned()])),
+                    visibility: Some(BTreeSet::from(["//:__subpackages__".to_owned()])),
+                    crate_root: Some("src/root.rs".to_owned()),
+                    srcs_exclude: Some(BTreeSet::from(["tests/fixtures/**".to_owned()])),
+                    disable_pipelining: Some(true),
+                    ..CrateAnnotations::default()
+                },
+            },
+        )]);
+
+        let context = CrateContext::new(
+            &annotations.metadata.crates[&package_id],
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            false,
+            &GenBinaries::default(),
+        );
+
+        let srcs = Glob {
+            include: vec!["**/*.rs".to_owned()],
+            exclude: vec!["tests/fixtures/**".to_owned()],
+        };
+
+        // Only the library's crate root is replaced
+        assert_eq!(
+            context.targets,
+            vec![
+                Rule::Library(TargetAttributes {
+                    crate_name: "common".to_owned(),
+                    crate_root: Some("src/root.rs".to_owned()),
+                    srcs: srcs.clone(),
+                }),
+                Rule::Binary(TargetAttributes {
+                    crate_name: "common-bin".to_owned(),
+                    crate_root: Some("main.rs".to_owned()),
+                    srcs,
+                }),
+            ]
+        );
+        assert_eq!(context.common_attrs.tags, vec!["no-remote-exec"]);
+        assert_eq!(
+            context.common_attrs.visibility,
+            BTreeSet::from(["//:__subpackages__".to_owned()])
+        );
+        assert!(context.common_attrs.disable_pipelining);
+    }
+
+    #[test]
+    fn context_with_platform_overrides() {
+        let annotations = build_script_annotations();
+
+        let package_id = PackageId {
+            repr: "openssl-sys 0.9.72 (registry+https://github.com/rust-lang/crates.io-index)"
+                .to_owned(),
+        };
+
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let linux = "cfg(target_os = \"linux\")".to_owned();
+        let mut pairred_extras = BTreeMap::new();
+        pairred_extras.insert(
+            CrateId::new("openssl-sys".to_owned(), "0.9.72".to_owned()),
+            PairredExtras {
+                package_id,
+                crate_extra: CrateAnnotations {
+                    deps: Some(BTreeSet::from(["//:common".to_owned()])),
+                    build_script_tools: Some(BTreeSet::from(["//:perl".to_owned()])),
+                    rustc_flags: Some(vec![
+                        "--cfg".to_owned(),
+                        "ossl300".to_owned(),
+                        "-C".to_owned(),
+                        "link-arg=-Wl,foo".to_owned(),
+                        "--cfg".to_owned(),
+                        "ossl111".to_owned(),
+                    ]),
+                    platform_annotations: Some(BTreeMap::from([(
+                        linux.clone(),
+                        CrateAnnotations {
+                            deps: Some(BTreeSet::from(["//:linux".to_owned()])),
+                            rustc_flags: Some(vec!["-Clink-arg=-lrt".to_owned()]),
+                            build_script_env: Some(BTreeMap::from([(
+                                "OPENSSL_STATIC".to_owned(),
+                                "1".to_owned(),
+                            )])),
+                            ..CrateAnnotations::default()
+                        },
+                    )])),
+                    ..CrateAnnotations::default()
+                },
+            },
+        );
+
+        let context = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            true,
+            &GenBinaries::default(),
+        );
+
+        let mut extra_deps = SelectStringList::default();
+        extra_deps.insert("//:common".to_owned(), None);
+        extra_deps.insert("//:linux".to_owned(), Some(linux.clone()));
+        assert_eq!(context.common_attrs.extra_deps, extra_deps);
+
+        // Flags keep their order and duplicates
+        let mut rustc_flags = SelectStringOrderedList::default();
+        for flag in [
+            "--cfg",
+            "ossl300",
+            "-C",
+            "link-arg=-Wl,foo",
+            "--cfg",
+            "ossl111",
+        ] {
+            rustc_flags.insert(flag.to_owned(), None);
+        }
+        rustc_flags.insert("-Clink-arg=-lrt".to_owned(), Some(linux.clone()));
+        assert_eq!(context.common_attrs.rustc_flags, rustc_flags);
+
+        let build_script_attrs = context.build_script_attrs.unwrap();
+        let mut build_script_env = SelectStringDict::default();
+        build_script_env.insert(
+            BTreeMap::from([("OPENSSL_STATIC".to_owned(), "1".to_owned())]),
+            Some(linux),
+        );
+        assert_eq!(build_script_attrs.build_script_env, build_script_env);
+
+        let mut tools = SelectStringList::default();
+        tools.insert("//:perl".to_owned(), None);
+        assert_eq!(build_script_attrs.tools, tools);
+    }
+
+    fn sysinfo_context(crate_extra: CrateAnnotations) -> CrateContext {
+        let annotations = crate_type_annotations();
+
+        let package_id = PackageId {
+            repr: "sysinfo 0.22.5 (registry+https://github.com/rust-lang/crates.io-index)"
+                .to_owned(),
+        };
+
+        let pairred_extras = BTreeMap::from([(
+            CrateId::new("sysinfo".to_owned(), "0.22.5".to_owned()),
+            PairredExtras {
+                package_id: package_id.clone(),
+                crate_extra,
+            },
+        )]);
+
+        CrateContext::new(
+            &annotations.metadata.crates[&package_id],
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            false,
+            &GenBinaries::default(),
+        )
+    }
+
+    fn dep_names(deps: &SelectList<CrateDependency>) -> BTreeSet<String> {
+        deps.get_iter(None)
+            .unwrap()
+            .map(|dep| dep.id.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn context_with_removed_features() {
+        // Removing some features keeps optional dependencies enabled by the others
+        let context = sysinfo_context(CrateAnnotations {
+            crate_features_remove: Some(BTreeSet::from(["default".to_owned()])),
+            ..CrateAnnotations::default()
+        });
+        assert_eq!(
+            context.common_attrs.crate_features,
+            BTreeSet::from(["multithread".to_owned(), "rayon".to_owned()])
+        );
+        assert!(dep_names(&context.common_attrs.deps).contains("rayon"));
+
+        let context = sysinfo_context(CrateAnnotations {
+            crate_features_remove: Some(BTreeSet::from([
+                "default".to_owned(),
+                "multithread".to_owned(),
+                "rayon".to_owned(),
+            ])),
+            ..CrateAnnotations::default()
+        });
+        assert_eq!(context.common_attrs.crate_features, BTreeSet::new());
+        assert_eq!(
+            dep_names(&context.common_attrs.deps),
+            BTreeSet::from(["cfg-if".to_owned(), "once_cell".to_owned()])
+        );
+    }
+
+    #[test]
+    fn context_with_overridden_features() {
+        let context = sysinfo_context(CrateAnnotations {
+            crate_features_override: Some(BTreeSet::from(["apple-app-store".to_owned()])),
+            ..CrateAnnotations::default()
+        });
+        assert_eq!(
+            context.common_attrs.crate_features,
+            BTreeSet::from(["apple-app-store".to_owned(), "apple-sandbox".to_owned()])
+        );
+        assert!(!dep_names(&context.common_attrs.deps).contains("rayon"));
+
+        // Features enable the optional dependencies they name
+        let context = sysinfo_context(CrateAnnotations {
+            crate_features_override: Some(BTreeSet::from(["multithread".to_owned()])),
+            ..CrateAnnotations::default()
+        });
+        assert_eq!(
+            context.common_attrs.crate_features,
+            BTreeSet::from(["multithread".to_owned(), "rayon".to_owned()])
+        );
+        assert!(dep_names(&context.common_attrs.deps).contains("rayon"));
+    }
+
     fn build_script_annotations() -> Annotations {
         Annotations::new(
             crate::test::metadata::build_scripts(),
@@ -750,6 +1316,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             true,
+            &GenBinaries::default(),
         );
 
         assert_eq!(context.name, "openssl-sys");
@@ -791,6 +1358,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             false,
+            &GenBinaries::default(),
         );
 
         assert_eq!(context.name, "openssl-sys");
@@ -822,6 +1390,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             false,
+            &GenBinaries::default(),
         );
 
         assert_eq!(context.name, "sysinfo");
@@ -0,0 +1,315 @@
+//! Detection of crates which appear at multiple versions in a [Context]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use cargo_metadata::{DependencyKind as CargoDependencyKind, Metadata as CargoMetadata};
+use semver::Version;
+use serde::Serialize;
+
+use crate::config::CrateId;
+use crate::context::graph::{DependencyEdge, DependencyKind};
+use crate::context::Context;
+
+/// A crate which depends on one version of a duplicated crate
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Dependent {
+    /// The crate declaring the dependency
+    pub id: CrateId,
+
+    /// The attribute the dependency is declared in
+    pub kind: DependencyKind,
+
+    /// The semver requirement the dependent declares in its manifest, if known
+    pub requirement: Option<String>,
+}
+
+/// One of the versions of a duplicated crate
+#[derive(Debug, Serialize)]
+pub struct DuplicateVersion {
+    /// The version of the crate
+    pub version: String,
+
+    /// All crates which depend on this version
+    pub dependents: Vec<Dependent>,
+}
+
+/// A crate which appears at more than one version
+#[derive(Debug, Serialize)]
+pub struct DuplicateCrate {
+    /// The name of the crate
+    pub name: String,
+
+    /// Each version of the crate, from oldest to newest
+    pub versions: Vec<DuplicateVersion>,
+
+    /// The newest version which satisfies the requirements of every dependent. This is only
+    /// known if the requirements of all dependents are known.
+    pub unifying_version: Option<String>,
+}
+
+impl DuplicateCrate {
+    /// Versions which could be replaced by the `unifying_version`
+    fn redundant_versions(&self) -> impl Iterator<Item = &String> {
+        self.versions
+            .iter()
+            .map(|version| &version.version)
+            .filter(move |version| Some(*version) != self.unifying_version.as_ref())
+    }
+}
+
+/// A report of all crates which appear at more than one version
+#[derive(Debug, Serialize)]
+pub struct DuplicatesReport {
+    pub crates: Vec<DuplicateCrate>,
+}
+
+impl DuplicatesReport {
+    /// Locate all duplicate crates in a [Context]. The semver requirements of dependents are
+    /// read from `metadata` if it's provided.
+    pub fn new(context: &Context, metadata: Option<&CargoMetadata>) -> Self {
+        let mut versions: BTreeMap<&String, BTreeSet<&CrateId>> = BTreeMap::new();
+        for id in context.crates.keys() {
+            versions.entry(&id.name).or_default().insert(id);
+        }
+        versions.retain(|_, ids| ids.len() > 1);
+
+        let edges = context.all_dependency_edges();
+
+        let crates = versions
+            .into_iter()
+            .map(|(name, ids)| {
+                let mut ids: Vec<&CrateId> = ids.into_iter().collect();
+                ids.sort_by_key(|id| Version::parse(&id.version).ok());
+
+                let versions: Vec<DuplicateVersion> = ids
+                    .into_iter()
+                    .map(|id| {
+                        let dependents: BTreeSet<Dependent> = edges
+                            .iter()
+                            .filter(|edge| edge.target == id)
+                            .map(|edge| Dependent {
+                                id: edge.source.clone(),
+                                kind: edge.kind,
+                                requirement: metadata.and_then(|m| find_requirement(m, edge)),
+                            })
+                            .collect();
+
+                        DuplicateVersion {
+                            version: id.version.clone(),
+                            dependents: dependents.into_iter().collect(),
+                        }
+                    })
+                    .collect();
+
+                let unifying_version = match metadata {
+                    Some(_) => find_unifying_version(&versions),
+                    None => None,
+                };
+
+                DuplicateCrate {
+                    name: name.clone(),
+                    versions,
+                    unifying_version,
+                }
+            })
+            .collect();
+
+        Self { crates }
+    }
+}
+
+/// Find the requirement a dependency edge was resolved from in a crate's manifest
+fn find_requirement(metadata: &CargoMetadata, edge: &DependencyEdge) -> Option<String> {
+    let package = metadata.packages.iter().find(|pkg| {
+        pkg.name == edge.source.name && pkg.version.to_string() == edge.source.version
+    })?;
+    let target_version = Version::parse(&edge.target.version).ok()?;
+
+    let kind = match edge.kind {
+        DependencyKind::Normal | DependencyKind::ProcMacro => CargoDependencyKind::Normal,
+        DependencyKind::NormalDev | DependencyKind::ProcMacroDev => {
+            CargoDependencyKind::Development
+        }
+        DependencyKind::Build | DependencyKind::BuildProcMacro => CargoDependencyKind::Build,
+    };
+
+    // Dependencies are identified by name and the name they're renamed to (alias)
+    let candidates: Vec<&cargo_metadata::Dependency> = package
+        .dependencies
+        .iter()
+        .filter(|dep| dep.name == edge.target.name && dep.rename.as_ref() == edge.alias)
+        .collect();
+
+    // Prefer requirements of the same kind which match the resolved version. Note that
+    // requirements never match pre-release versions (Eg. those of git dependencies).
+    candidates
+        .iter()
+        .filter(|dep| dep.req.matches(&target_version))
+        .find(|dep| dep.kind == kind)
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|dep| dep.req.matches(&target_version))
+        })
+        .or_else(|| candidates.iter().find(|dep| dep.kind == kind))
+        .map(|dep| dep.req.to_string())
+}
+
+/// Find the newest version which satisfies every requirement of every dependent
+fn find_unifying_version(versions: &[DuplicateVersion]) -> Option<String> {
+    let requirements: Vec<semver::VersionReq> = versions
+        .iter()
+        .flat_map(|version| version.dependents.iter())
+        .map(|dependent| {
+            dependent
+                .requirement
+                .as_ref()
+                .and_then(|req| semver::VersionReq::parse(req).ok())
+        })
+        .collect::<Option<Vec<semver::VersionReq>>>()?;
+
+    versions
+        .iter()
+        .rev()
+        .find(|candidate| match Version::parse(&candidate.version) {
+            Ok(version) => requirements.iter().all(|req| req.matches(&version)),
+            Err(_) => false,
+        })
+        .map(|version| version.version.clone())
+}
+
+impl fmt::Display for DuplicatesReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.crates.is_empty() {
+            return writeln!(f, "No crates appear at more than one version.");
+        }
+
+        for (index, duplicate) in self.crates.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            writeln!(
+                f,
+                "{} ({} versions)",
+                duplicate.name,
+                duplicate.versions.len()
+            )?;
+            for version in duplicate.versions.iter() {
+                writeln!(f, "  {}", version.version)?;
+                for dependent in version.dependents.iter() {
+                    match &dependent.requirement {
+                        Some(req) => writeln!(
+                            f,
+                            "    {} ({}) requires {}",
+                            dependent.id, dependent.kind, req
+                        )?,
+                        None => writeln!(f, "    {} ({})", dependent.id, dependent.kind)?,
+                    }
+                }
+            }
+
+            if let Some(unifying_version) = &duplicate.unifying_version {
+                writeln!(
+                    f,
+                    "  hint: {} satisfies every requirement. The duplicates can be collapsed with:",
+                    unifying_version
+                )?;
+                for version in duplicate.redundant_versions() {
+                    writeln!(
+                        f,
+                        "    cargo update --package {}@{} --precise {}",
+                        duplicate.name, version, unifying_version
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::config::Config;
+    use crate::metadata::Annotations;
+
+    fn mock_context_aliases() -> Context {
+        let annotations = Annotations::new(
+            crate::test::metadata::alias(),
+            crate::test::lockfile::alias(),
+            Config::default(),
+        )
+        .unwrap();
+
+        Context::new(annotations).unwrap()
+    }
+
+    #[test]
+    fn duplicates_with_requirements() {
+        let context = mock_context_aliases();
+        let metadata = crate::test::metadata::alias();
+        let report = DuplicatesReport::new(&context, Some(&metadata));
+
+        let names: Vec<&str> = report.crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["log", "names"]);
+
+        // `aliases` pins both versions of `log` exactly so they cannot be unified
+        let log = &report.crates[0];
+        let versions: Vec<&str> = log.versions.iter().map(|v| v.version.as_str()).collect();
+        assert_eq!(versions, vec!["0.3.9", "0.4.14"]);
+        assert_eq!(
+            log.versions[0].dependents,
+            vec![Dependent {
+                id: CrateId::new("aliases".to_owned(), "0.1.0".to_owned()),
+                kind: DependencyKind::Normal,
+                requirement: Some("=0.3.9".to_owned()),
+            }]
+        );
+        assert_eq!(
+            log.versions[1].dependents,
+            vec![
+                Dependent {
+                    id: CrateId::new("aliases".to_owned(), "0.1.0".to_owned()),
+                    kind: DependencyKind::Normal,
+                    requirement: Some("=0.4.14".to_owned()),
+                },
+                Dependent {
+                    id: CrateId::new("log".to_owned(), "0.3.9".to_owned()),
+                    kind: DependencyKind::Normal,
+                    requirement: Some("^0.4".to_owned()),
+                },
+            ]
+        );
+        assert_eq!(log.unifying_version, None);
+
+        // A wildcard requirement is satisfied by the newest version of `names`
+        let names = &report.crates[1];
+        assert_eq!(names.unifying_version, Some("0.12.0".to_owned()));
+        assert_eq!(
+            names.redundant_versions().collect::<Vec<&String>>(),
+            vec!["0.11.1-dev"]
+        );
+
+        let text = report.to_string();
+        assert!(text.contains("cargo update --package names@0.11.1-dev --precise 0.12.0"));
+    }
+
+    #[test]
+    fn duplicates_without_requirements() {
+        let context = mock_context_aliases();
+        let report = DuplicatesReport::new(&context, None);
+
+        assert_eq!(report.crates.len(), 2);
+        assert!(report
+            .crates
+            .iter()
+            .flat_map(|c| c.versions.iter())
+            .flat_map(|v| v.dependents.iter())
+            .all(|d| d.requirement.is_none()));
+        assert!(report.crates.iter().all(|c| c.unifying_version.is_none()));
+    }
+}
@@ -0,0 +1,694 @@
+//! Utilities for walking the dependency graph described by a [Context]
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::config::CrateId;
+use crate::context::crate_context::CrateDependency;
+use crate::context::Context;
+use crate::utils::starlark::{Select, SelectList};
+
+/// The attribute through which one crate depends on another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencyKind {
+    /// A dependency from `deps`
+    Normal,
+
+    /// A dependency from `deps_dev`
+    NormalDev,
+
+    /// A dependency from `proc_macro_deps`
+    ProcMacro,
+
+    /// A dependency from `proc_macro_deps_dev`
+    ProcMacroDev,
+
+    /// A dependency from the build script's `deps`
+    Build,
+
+    /// A dependency from the build script's `proc_macro_deps`
+    BuildProcMacro,
+}
+
+impl fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::Normal => "normal",
+            Self::NormalDev => "normal-dev",
+            Self::ProcMacro => "proc-macro",
+            Self::ProcMacroDev => "proc-macro-dev",
+            Self::Build => "build",
+            Self::BuildProcMacro => "build-proc-macro",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// A single dependency of one crate on another
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DependencyEdge<'a> {
+    /// The crate declaring the dependency
+    pub source: &'a CrateId,
+
+    /// The crate being depended on
+    pub target: &'a CrateId,
+
+    /// The attribute the dependency is declared in
+    pub kind: DependencyKind,
+
+    /// The `cfg` expression or platform triple the dependency is conditional on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<&'a String>,
+
+    /// The name the dependency is renamed to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<&'a String>,
+}
+
+impl fmt::Display for DependencyEdge<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(condition) = self.condition {
+            write!(f, ", {}", condition)?;
+        }
+        if let Some(alias) = self.alias {
+            write!(f, ", as {}", alias)?;
+        }
+        Ok(())
+    }
+}
+
+/// Produce an edge for every dependency in a [SelectList]
+fn select_edges<'a>(
+    source: &'a CrateId,
+    deps: &'a SelectList<CrateDependency>,
+    kind: DependencyKind,
+) -> impl Iterator<Item = DependencyEdge<'a>> {
+    deps.configurations().into_iter().flat_map(move |conf| {
+        deps.get_iter(conf)
+            .expect("Iterating over known keys should never panic")
+            // Crates depend on their own build scripts. These are not edges between crates.
+            .filter(move |dep| &dep.id != source)
+            .map(move |dep| DependencyEdge {
+                source,
+                target: &dep.id,
+                kind,
+                condition: conf,
+                alias: dep.alias.as_ref(),
+            })
+    })
+}
+
+/// A crate in a [DependencyGraph]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyNode<'a> {
+    /// The crate's identifier
+    pub id: &'a CrateId,
+
+    /// The name of the crate
+    pub name: &'a String,
+
+    /// The version of the crate
+    pub version: &'a String,
+
+    /// Whether or not the crate is a workspace member
+    pub workspace_member: bool,
+
+    /// Whether or not the crate provides binaries (See [Context::binary_crates])
+    pub binary: bool,
+}
+
+/// A crate in the inverted dependency tree produced by [Context::dependents_tree]. Each
+/// crate's dependents are only listed the first time it appears in the tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependentNode<'a> {
+    /// The crate's identifier
+    #[serde(rename = "crate")]
+    pub crate_id: &'a CrateId,
+
+    /// The dependency of this crate on its parent in the tree. Unset for the root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge: Option<DependencyEdge<'a>>,
+
+    /// Whether or not the crate is a workspace member. The dependents of workspace members
+    /// are not listed.
+    pub workspace_member: bool,
+
+    /// Whether or not the crate's dependents are listed elsewhere in the tree
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub repeated: bool,
+
+    /// The crates which depend on this crate
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependents: Vec<DependentNode<'a>>,
+}
+
+impl DependentNode<'_> {
+    // A helper for recursively writing each level of the tree
+    fn fmt_dependents(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+        for (index, node) in self.dependents.iter().enumerate() {
+            let (branch, indent) = match index + 1 == self.dependents.len() {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+
+            write!(f, "{}{}{}", prefix, branch, node.crate_id)?;
+            if let Some(edge) = &node.edge {
+                write!(f, " [{}]", edge)?;
+            }
+            if node.workspace_member {
+                write!(f, " (workspace member)")?;
+            }
+            if node.repeated {
+                write!(f, " (*)")?;
+            }
+            writeln!(f)?;
+
+            node.fmt_dependents(f, &format!("{}{}", prefix, indent))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for DependentNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.crate_id)?;
+        self.fmt_dependents(f, "")
+    }
+}
+
+/// A stable node and edge list of every crate in a [Context]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyGraph<'a> {
+    /// All crates in the graph, sorted by [CrateId]
+    pub nodes: Vec<DependencyNode<'a>>,
+
+    /// All dependencies between crates, sorted by source and target
+    pub edges: Vec<DependencyEdge<'a>>,
+}
+
+impl DependencyGraph<'_> {
+    /// Render the graph in the Graphviz DOT language
+    pub fn to_dot(&self) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+
+        let mut dot = String::from("digraph dependencies {\n");
+        dot.push_str("    node [shape=ellipse];\n");
+
+        for node in self.nodes.iter() {
+            let attrs = match (node.workspace_member, node.binary) {
+                (true, _) => ", shape=box, style=\"bold,filled\", fillcolor=lightblue",
+                (false, true) => ", shape=box, style=filled, fillcolor=lightgoldenrod",
+                (false, false) => "",
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\\n{}\"{}];\n",
+                escape(&node.id.to_string()),
+                escape(node.name),
+                escape(node.version),
+                attrs
+            ));
+        }
+
+        for edge in self.edges.iter() {
+            let mut label = vec![edge.kind.to_string()];
+            if let Some(condition) = edge.condition {
+                label.push(escape(condition));
+            }
+            if let Some(alias) = edge.alias {
+                label.push(format!("as {}", escape(alias)));
+            }
+
+            let style = match edge.kind {
+                DependencyKind::Normal | DependencyKind::ProcMacro => "solid",
+                DependencyKind::NormalDev | DependencyKind::ProcMacroDev => "dashed",
+                DependencyKind::Build | DependencyKind::BuildProcMacro => "dotted",
+            };
+
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\", style={}];\n",
+                escape(&edge.source.to_string()),
+                escape(&edge.target.to_string()),
+                label.join("\\n"),
+                style
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Context {
+    /// Collect all direct dependencies of the given crate
+    pub fn dependency_edges<'a>(&'a self, crate_id: &'a CrateId) -> Vec<DependencyEdge<'a>> {
+        let ctx = match self.crates.get(crate_id) {
+            Some(ctx) => ctx,
+            None => return Vec::new(),
+        };
+
+        let attrs = &ctx.common_attrs;
+        let mut edges: Vec<DependencyEdge> =
+            select_edges(crate_id, &attrs.deps, DependencyKind::Normal)
+                .chain(select_edges(
+                    crate_id,
+                    &attrs.deps_dev,
+                    DependencyKind::NormalDev,
+                ))
+                .chain(select_edges(
+                    crate_id,
+                    &attrs.proc_macro_deps,
+                    DependencyKind::ProcMacro,
+                ))
+                .chain(select_edges(
+                    crate_id,
+                    &attrs.proc_macro_deps_dev,
+                    DependencyKind::ProcMacroDev,
+                ))
+                .collect();
+
+        if let Some(attrs) = &ctx.build_script_attrs {
+            edges.extend(select_edges(crate_id, &attrs.deps, DependencyKind::Build));
+            edges.extend(select_edges(
+                crate_id,
+                &attrs.proc_macro_deps,
+                DependencyKind::BuildProcMacro,
+            ));
+        }
+
+        edges.sort();
+        edges.dedup();
+        edges
+    }
+
+    /// Collect every dependency edge in the graph
+    pub fn all_dependency_edges(&self) -> Vec<DependencyEdge<'_>> {
+        self.crates
+            .keys()
+            .flat_map(|id| self.dependency_edges(id))
+            .collect()
+    }
+
+    /// Collect the direct dependencies of the given crate which are enabled when building
+    /// for the given platform triple. Conditional dependencies are resolved using
+    /// [Context::conditions].
+    pub fn platform_dependency_edges<'a>(
+        &'a self,
+        crate_id: &'a CrateId,
+        platform_triple: &str,
+    ) -> Vec<DependencyEdge<'a>> {
+        self.dependency_edges(crate_id)
+            .into_iter()
+            .filter(|edge| match edge.condition {
+                Some(condition) => self
+                    .conditions
+                    .get(condition)
+                    .map(|triples| triples.contains(platform_triple))
+                    .unwrap_or(false),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Produce a node and edge list of the entire dependency graph
+    pub fn dependency_graph(&self) -> DependencyGraph<'_> {
+        let nodes = self
+            .crates
+            .iter()
+            .map(|(id, ctx)| DependencyNode {
+                id,
+                name: &ctx.name,
+                version: &ctx.version,
+                workspace_member: self.workspace_members.contains_key(id),
+                binary: self.binary_crates.contains(id),
+            })
+            .collect();
+
+        DependencyGraph {
+            nodes,
+            edges: self.all_dependency_edges(),
+        }
+    }
+
+    /// Produce the inverted dependency tree of the given crate, similar to `cargo tree -i`.
+    /// Each crate's dependents are only expanded once so the size of the tree is bounded
+    /// by the number of edges in the graph. Later occurrences are marked as repeated.
+    pub fn dependents_tree<'a>(&'a self, crate_id: &'a CrateId) -> DependentNode<'a> {
+        // Index all edges by the crate they point to
+        let mut dependents: BTreeMap<&CrateId, Vec<DependencyEdge>> = BTreeMap::new();
+        for edge in self.all_dependency_edges() {
+            dependents.entry(edge.target).or_default().push(edge);
+        }
+
+        let mut expanded = BTreeSet::from([crate_id]);
+        let mut root = DependentNode {
+            crate_id,
+            edge: None,
+            workspace_member: self.workspace_members.contains_key(crate_id),
+            repeated: false,
+            dependents: Vec::new(),
+        };
+        if !root.workspace_member {
+            root.dependents = self.expand_dependents(crate_id, &dependents, &mut expanded);
+        }
+
+        root
+    }
+
+    // A helper for recursively expanding the dependents of crates which have not been seen
+    fn expand_dependents<'a>(
+        &self,
+        crate_id: &'a CrateId,
+        dependents: &BTreeMap<&'a CrateId, Vec<DependencyEdge<'a>>>,
+        expanded: &mut BTreeSet<&'a CrateId>,
+    ) -> Vec<DependentNode<'a>> {
+        dependents
+            .get(crate_id)
+            .into_iter()
+            .flatten()
+            .map(|edge| {
+                let workspace_member = self.workspace_members.contains_key(edge.source);
+                let mut node = DependentNode {
+                    crate_id: edge.source,
+                    edge: Some(edge.clone()),
+                    workspace_member,
+                    repeated: false,
+                    dependents: Vec::new(),
+                };
+
+                if !workspace_member {
+                    // Crates already in the tree, including those introducing cycles through
+                    // dev dependencies, are marked instead of expanded again
+                    if expanded.insert(edge.source) {
+                        node.dependents = self.expand_dependents(edge.source, dependents, expanded);
+                    } else {
+                        node.repeated = true;
+                    }
+                }
+
+                node
+            })
+            .collect()
+    }
+
+    /// Find the shortest path from each workspace member which depends on the given crate.
+    /// Each path is ordered starting with the edge leaving the workspace member. Crates are
+    /// visited at most once so this is linear in the size of the graph.
+    pub fn shortest_dependency_paths<'a>(
+        &'a self,
+        crate_id: &'a CrateId,
+    ) -> Vec<Vec<DependencyEdge<'a>>> {
+        // Index all edges by the crate they point to
+        let mut dependents: BTreeMap<&CrateId, Vec<DependencyEdge>> = BTreeMap::new();
+        for edge in self.all_dependency_edges() {
+            dependents.entry(edge.target).or_default().push(edge);
+        }
+
+        // Walk dependents breadth first, recording the edge each crate was first reached by
+        let mut reached_by: BTreeMap<&CrateId, Option<DependencyEdge>> =
+            BTreeMap::from([(crate_id, None)]);
+        let mut queue = VecDeque::from([crate_id]);
+        let mut members = Vec::new();
+        while let Some(current) = queue.pop_front() {
+            for edge in dependents.get(current).into_iter().flatten() {
+                if reached_by.contains_key(edge.source) {
+                    continue;
+                }
+                reached_by.insert(edge.source, Some(edge.clone()));
+
+                // Paths end at the first workspace member they reach
+                if self.workspace_members.contains_key(edge.source) {
+                    members.push(edge.source);
+                } else {
+                    queue.push_back(edge.source);
+                }
+            }
+        }
+
+        members.sort();
+        members
+            .into_iter()
+            .map(|member| {
+                let mut path = Vec::new();
+                let mut current = member;
+                while let Some(Some(edge)) = reached_by.get(current) {
+                    path.push(edge.clone());
+                    current = edge.target;
+                }
+                path
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::config::Config;
+    use crate::context::crate_context::CrateContext;
+    use crate::metadata::Annotations;
+
+    fn mock_context_build_scripts() -> Context {
+        let annotations = Annotations::new(
+            crate::test::metadata::build_scripts(),
+            crate::test::lockfile::build_scripts(),
+            Config {
+                generate_build_scripts: true,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+
+        Context::new(annotations).unwrap()
+    }
+
+    #[test]
+    fn dependency_edges_kinds_and_conditions() {
+        let context = mock_context_build_scripts();
+        let openssl_sys = CrateId::new("openssl-sys".to_owned(), "0.9.72".to_owned());
+        let edges = context.dependency_edges(&openssl_sys);
+
+        let libc = CrateId::new("libc".to_owned(), "0.2.112".to_owned());
+        assert!(edges.contains(&DependencyEdge {
+            source: &openssl_sys,
+            target: &libc,
+            kind: DependencyKind::Normal,
+            condition: None,
+            alias: None,
+        }));
+
+        let vcpkg = CrateId::new("vcpkg".to_owned(), "0.2.15".to_owned());
+        let condition = "cfg(target_env = \"msvc\")".to_owned();
+        assert!(edges.contains(&DependencyEdge {
+            source: &openssl_sys,
+            target: &vcpkg,
+            kind: DependencyKind::Build,
+            condition: Some(&condition),
+            alias: None,
+        }));
+
+        // The dependency on the crate's own build script is not an edge
+        assert!(edges.iter().all(|edge| edge.target != &openssl_sys));
+    }
+
+    #[test]
+    fn platform_dependency_edges() {
+        let mut context = mock_context_build_scripts();
+        context.conditions = BTreeMap::from([(
+            "cfg(target_env = \"msvc\")".to_owned(),
+            BTreeSet::from(["x86_64-pc-windows-msvc".to_owned()]),
+        )]);
+
+        let openssl_sys = CrateId::new("openssl-sys".to_owned(), "0.9.72".to_owned());
+        let vcpkg = CrateId::new("vcpkg".to_owned(), "0.2.15".to_owned());
+        let libc = CrateId::new("libc".to_owned(), "0.2.112".to_owned());
+
+        let windows = context.platform_dependency_edges(&openssl_sys, "x86_64-pc-windows-msvc");
+        assert!(windows.iter().any(|edge| edge.target == &vcpkg));
+        assert!(windows.iter().any(|edge| edge.target == &libc));
+
+        let linux = context.platform_dependency_edges(&openssl_sys, "aarch64-unknown-linux-gnu");
+        assert!(linux.iter().all(|edge| edge.target != &vcpkg));
+        assert!(linux.iter().any(|edge| edge.target == &libc));
+    }
+
+    #[test]
+    fn shortest_dependency_paths_to_workspace_members() {
+        let context = mock_context_build_scripts();
+        let libc = CrateId::new("libc".to_owned(), "0.2.112".to_owned());
+        let paths = context.shortest_dependency_paths(&libc);
+
+        let rendered: Vec<Vec<String>> = paths
+            .iter()
+            .map(|path| path.iter().map(|edge| edge.target.to_string()).collect())
+            .collect();
+
+        // Only the shortest path from each workspace member is reported
+        assert_eq!(
+            rendered,
+            vec![vec![
+                "openssl 0.10.36".to_owned(),
+                "libc 0.2.112".to_owned()
+            ]]
+        );
+
+        // Every path starts at a workspace member
+        assert!(paths
+            .iter()
+            .all(|path| context.workspace_members.contains_key(path[0].source)));
+    }
+
+    #[test]
+    fn dependency_graph_json() {
+        let context = mock_context_build_scripts();
+        let graph = serde_json::to_value(context.dependency_graph()).unwrap();
+
+        let nodes = graph["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), context.crates.len());
+        assert!(nodes.contains(&serde_json::json!({
+            "id": "build-scripts 0.1.0",
+            "name": "build-scripts",
+            "version": "0.1.0",
+            "workspace_member": true,
+            "binary": false,
+        })));
+
+        let edges = graph["edges"].as_array().unwrap();
+        assert!(edges.contains(&serde_json::json!({
+            "source": "openssl-sys 0.9.72",
+            "target": "vcpkg 0.2.15",
+            "kind": "build",
+            "condition": "cfg(target_env = \"msvc\")",
+        })));
+        assert!(edges.contains(&serde_json::json!({
+            "source": "openssl 0.10.36",
+            "target": "openssl-sys 0.9.72",
+            "kind": "normal",
+            "alias": "ffi",
+        })));
+    }
+
+    #[test]
+    fn dependency_graph_dot() {
+        let context = mock_context_build_scripts();
+        let dot = context.dependency_graph().to_dot();
+
+        assert!(dot.starts_with("digraph dependencies {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(
+            "    \"build-scripts 0.1.0\" [label=\"build-scripts\\n0.1.0\", shape=box, style=\"bold,filled\", fillcolor=lightblue];\n"
+        ));
+        assert!(dot.contains(
+            "    \"openssl-sys 0.9.72\" -> \"vcpkg 0.2.15\" [label=\"build\\ncfg(target_env = \\\"msvc\\\")\", style=dotted];\n"
+        ));
+        assert!(dot.contains(
+            "    \"openssl 0.10.36\" -> \"openssl-sys 0.9.72\" [label=\"normal\\nas ffi\", style=solid];\n"
+        ));
+    }
+
+    #[test]
+    fn dependents_tree_lists_crates_once() {
+        let context = mock_context_build_scripts();
+        let libc = CrateId::new("libc".to_owned(), "0.2.112".to_owned());
+        let tree = context.dependents_tree(&libc);
+
+        assert_eq!(
+            tree.to_string(),
+            concat!(
+                "libc 0.2.112\n",
+                "├── openssl 0.10.36 [normal]\n",
+                "│   └── build-scripts 0.1.0 [normal] (workspace member)\n",
+                "└── openssl-sys 0.9.72 [normal]\n",
+                "    ├── openssl 0.10.36 [normal, as ffi] (*)\n",
+                "    └── openssl 0.10.36 [build, as ffi] (*)\n",
+            )
+        );
+
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json["crate"], "libc 0.2.112");
+        assert_eq!(json["dependents"][1]["crate"], "openssl-sys 0.9.72");
+        assert_eq!(json["dependents"][1]["edge"]["kind"], "normal");
+    }
+
+    #[test]
+    fn dependents_tree_marks_repeated_crates() {
+        // A chain of diamonds where every layer doubles the number of paths to the bottom
+        let layers = 32;
+        let id = |layer: usize, index: usize| {
+            CrateId::new(format!("crate-{}-{}", layer, index), "0.1.0".to_owned())
+        };
+
+        let mut context = Context::default();
+        let member = CrateId::new("member".to_owned(), "0.1.0".to_owned());
+        let mut crate_context = |crate_id: &CrateId, deps: Vec<CrateId>| {
+            let mut ctx = CrateContext {
+                name: crate_id.name.clone(),
+                version: crate_id.version.clone(),
+                ..CrateContext::default()
+            };
+            for dep in deps {
+                ctx.common_attrs.deps.insert(
+                    CrateDependency {
+                        target: dep.name.clone(),
+                        id: dep,
+                        alias: None,
+                    },
+                    None,
+                );
+            }
+            context.crates.insert(crate_id.clone(), ctx);
+        };
+
+        crate_context(&member, vec![id(0, 0), id(0, 1)]);
+        for layer in 0..layers {
+            for index in 0..2 {
+                crate_context(&id(layer, index), vec![id(layer + 1, 0), id(layer + 1, 1)]);
+            }
+        }
+        crate_context(&id(layers, 0), Vec::new());
+        crate_context(&id(layers, 1), Vec::new());
+        context
+            .workspace_members
+            .insert(member.clone(), "member".to_owned());
+
+        let bottom = id(layers, 0);
+        let tree = context.dependents_tree(&bottom).to_string();
+
+        // Each crate is expanded once and then only marked
+        assert_eq!(tree.lines().count(), 4 * layers + 1);
+        assert_eq!(tree.matches(" (*)\n").count(), 2 * (layers - 1));
+        assert_eq!(
+            tree.matches("member 0.1.0 [normal] (workspace member)\n")
+                .count(),
+            2
+        );
+
+        let paths = context.shortest_dependency_paths(&bottom);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), layers + 1);
+        assert_eq!(paths[0][0].source, &member);
+    }
+
+    #[test]
+    fn dependents_tree_of_workspace_member() {
+        let context = mock_context_build_scripts();
+        let member = context.workspace_members.keys().next().unwrap().clone();
+
+        let tree = context.dependents_tree(&member);
+        assert!(tree.workspace_member);
+        assert!(tree.dependents.is_empty());
+    }
+
+    #[test]
+    fn shortest_dependency_paths_of_workspace_member() {
+        let context = mock_context_build_scripts();
+        let member = context.workspace_members.keys().next().unwrap().clone();
+
+        assert!(context.shortest_dependency_paths(&member).is_empty());
+    }
+}
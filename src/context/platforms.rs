@@ -13,29 +13,40 @@ pub fn resolve_cfg_platforms(
     crates: Vec<&CrateContext>,
     supported_platform_triples: &BTreeSet<String>,
 ) -> Result<BTreeMap<String, BTreeSet<String>>> {
-    // Collect all unique configurations from all dependencies into a single set
+    // Collect all unique configurations from all selectable attributes into a single set
     let configurations: BTreeSet<String> = crates
         .iter()
         .flat_map(|ctx| {
             let attr = &ctx.common_attrs;
-            attr.deps
-                .configurations()
-                .into_iter()
-                .chain(attr.deps_dev.configurations().into_iter())
-                .chain(attr.proc_macro_deps.configurations().into_iter())
-                .chain(attr.proc_macro_deps_dev.configurations().into_iter())
-                // Chain the build dependencies if some are defined
-                .chain(if let Some(attr) = &ctx.build_script_attrs {
-                    attr.deps
-                        .configurations()
-                        .into_iter()
-                        .chain(attr.proc_macro_deps.configurations().into_iter())
-                        .collect::<BTreeSet<Option<&String>>>()
-                        .into_iter()
-                } else {
-                    BTreeSet::new().into_iter()
-                })
-                .flatten()
+            let mut configurations = BTreeSet::new();
+            configurations.extend(attr.deps.configurations());
+            configurations.extend(attr.deps_dev.configurations());
+            configurations.extend(attr.extra_deps.configurations());
+            configurations.extend(attr.proc_macro_deps.configurations());
+            configurations.extend(attr.proc_macro_deps_dev.configurations());
+            configurations.extend(attr.extra_proc_macro_deps.configurations());
+            configurations.extend(attr.compile_data.configurations());
+            configurations.extend(attr.data.configurations());
+            configurations.extend(attr.rustc_env.configurations());
+            configurations.extend(attr.rustc_env_files.configurations());
+            configurations.extend(attr.rustc_flags.configurations());
+
+            // Include the build script attributes if some are defined
+            if let Some(attr) = &ctx.build_script_attrs {
+                configurations.extend(attr.deps.configurations());
+                configurations.extend(attr.extra_deps.configurations());
+                configurations.extend(attr.proc_macro_deps.configurations());
+                configurations.extend(attr.extra_proc_macro_deps.configurations());
+                configurations.extend(attr.compile_data.configurations());
+                configurations.extend(attr.data.configurations());
+                configurations.extend(attr.build_script_env.configurations());
+                configurations.extend(attr.rustc_env.configurations());
+                configurations.extend(attr.rustc_env_files.configurations());
+                configurations.extend(attr.rustc_flags.configurations());
+                configurations.extend(attr.tools.configurations());
+            }
+
+            configurations.into_iter().flatten()
         })
         .cloned()
         .collect();
@@ -107,7 +118,7 @@ mod test {
     use crate::config::CrateId;
     use crate::context::crate_context::CrateDependency;
     use crate::context::CommonAttributes;
-    use crate::utils::starlark::SelectList;
+    use crate::utils::starlark::{SelectList, SelectStringOrderedList};
 
     use super::*;
 
@@ -235,6 +246,41 @@ mod test {
         );
     }
 
+    #[test]
+    fn resolve_annotation_configurations() {
+        let configuration = r#"cfg(target_os = "macos")"#.to_owned();
+        let mut rustc_flags = SelectStringOrderedList::default();
+        rustc_flags.insert(
+            "-Clink-arg=-framework".to_owned(),
+            Some(configuration.clone()),
+        );
+
+        let context = CrateContext {
+            name: "mock_crate_a".to_owned(),
+            version: "0.1.0".to_owned(),
+            common_attrs: CommonAttributes {
+                rustc_flags,
+                ..CommonAttributes::default()
+            },
+            ..CrateContext::default()
+        };
+
+        let configurations =
+            resolve_cfg_platforms(vec![&context], &supported_platform_triples()).unwrap();
+
+        assert_eq!(
+            configurations,
+            BTreeMap::from([(
+                configuration,
+                BTreeSet::from([
+                    "aarch64-apple-darwin".to_owned(),
+                    "i686-apple-darwin".to_owned(),
+                    "x86_64-apple-darwin".to_owned(),
+                ])
+            )])
+        );
+    }
+
     #[test]
     fn resolve_unsupported_targeted() {
         let configuration = r#"cfg(target = "x86_64-unknown-unknown")"#.to_owned();
@@ -0,0 +1,144 @@
+//! Rendering of a workspace member's dependency tree for a single platform
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::config::CrateId;
+use crate::context::graph::{DependencyEdge, DependencyKind};
+use crate::context::Context;
+
+/// The dependency tree of a crate as it would be built for a specific platform triple
+pub struct DependencyTree<'a> {
+    context: &'a Context,
+    root: &'a CrateId,
+    platform_triple: &'a str,
+}
+
+impl<'a> DependencyTree<'a> {
+    pub fn new(context: &'a Context, root: &'a CrateId, platform_triple: &'a str) -> Self {
+        Self {
+            context,
+            root,
+            platform_triple,
+        }
+    }
+
+    // A helper for recursively writing each level of the tree
+    fn fmt_dependencies(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        crate_id: &'a CrateId,
+        prefix: &str,
+        visited: &mut BTreeSet<&'a CrateId>,
+    ) -> fmt::Result {
+        let edges: Vec<DependencyEdge> = self
+            .context
+            .platform_dependency_edges(crate_id, self.platform_triple)
+            .into_iter()
+            // Dev dependencies are only built for the root of the tree
+            .filter(|edge| {
+                crate_id == self.root
+                    || !matches!(
+                        edge.kind,
+                        DependencyKind::NormalDev | DependencyKind::ProcMacroDev
+                    )
+            })
+            .collect();
+
+        for (index, edge) in edges.iter().enumerate() {
+            let (branch, indent) = match index + 1 == edges.len() {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+
+            write!(f, "{}{}{}", prefix, branch, edge.target)?;
+            let mut labels = Vec::new();
+            if edge.kind != DependencyKind::Normal {
+                labels.push(edge.kind.to_string());
+            }
+            if let Some(alias) = edge.alias {
+                labels.push(format!("as {}", alias));
+            }
+            if !labels.is_empty() {
+                write!(f, " ({})", labels.join(", "))?;
+            }
+
+            // Crates which have already been expanded are marked instead of repeated
+            if !visited.insert(edge.target) {
+                writeln!(f, " (*)")?;
+                continue;
+            }
+            writeln!(f)?;
+
+            self.fmt_dependencies(f, edge.target, &format!("{}{}", prefix, indent), visited)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for DependencyTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.root)?;
+        let mut visited = BTreeSet::from([self.root]);
+        self.fmt_dependencies(f, self.root, "", &mut visited)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use crate::config::Config;
+    use crate::metadata::Annotations;
+
+    fn mock_context_build_scripts() -> Context {
+        let annotations = Annotations::new(
+            crate::test::metadata::build_scripts(),
+            crate::test::lockfile::build_scripts(),
+            Config {
+                generate_build_scripts: true,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+
+        let mut context = Context::new(annotations).unwrap();
+        context.conditions = BTreeMap::from([(
+            "cfg(target_env = \"msvc\")".to_owned(),
+            BTreeSet::from(["x86_64-pc-windows-msvc".to_owned()]),
+        )]);
+
+        context
+    }
+
+    #[test]
+    fn render_platform_specific_tree() {
+        let context = mock_context_build_scripts();
+        let root = CrateId::new("build-scripts".to_owned(), "0.1.0".to_owned());
+
+        let linux = DependencyTree::new(&context, &root, "x86_64-unknown-linux-gnu").to_string();
+        assert!(linux.starts_with("build-scripts 0.1.0\n└── openssl 0.10.36\n"));
+        assert!(linux.contains("openssl-sys 0.9.72 (as ffi)\n"));
+        assert!(linux.contains("openssl-sys 0.9.72 (build, as ffi) (*)\n"));
+        assert!(linux.contains("cc 1.0.72 (build)\n"));
+        assert!(!linux.contains("vcpkg"));
+
+        let windows = DependencyTree::new(&context, &root, "x86_64-pc-windows-msvc").to_string();
+        assert!(windows.contains("vcpkg 0.2.15 (build)\n"));
+    }
+
+    #[test]
+    fn render_repeated_crates_once() {
+        let context = mock_context_build_scripts();
+        let root = CrateId::new("build-scripts".to_owned(), "0.1.0".to_owned());
+
+        let tree = DependencyTree::new(&context, &root, "x86_64-unknown-linux-gnu").to_string();
+
+        // `libc` is depended on by both `openssl` and `openssl-sys`
+        assert_eq!(tree.matches("libc 0.2.112\n").count(), 1);
+        assert_eq!(tree.matches("libc 0.2.112 (*)\n").count(), 1);
+    }
+}
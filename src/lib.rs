@@ -1,10 +1,16 @@
 pub mod cli;
 
+mod audit;
 mod config;
 mod context;
+mod licenses;
 mod lockfile;
 mod metadata;
+mod mirror;
+mod raze;
 mod rendering;
+mod sbom;
+mod schema;
 mod splicing;
 mod utils;
 
@@ -0,0 +1,557 @@
+//! Utilities for reporting on and enforcing the licenses of third party crates
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::config::{CrateId, LicensePolicy};
+use crate::context::Context;
+
+/// A parsed [SPDX license expression](https://spdx.github.io/spdx-spec/SPDX-license-expressions/).
+/// Cargo's deprecated `/` separator is treated as `OR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseExpr {
+    /// A single license identifier with an optional exception (`WITH`)
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+
+    /// Both expressions must be satisfied
+    And(Box<LicenseExpr>, Box<LicenseExpr>),
+
+    /// Either expression must be satisfied
+    Or(Box<LicenseExpr>, Box<LicenseExpr>),
+}
+
+impl LicenseExpr {
+    /// All license identifiers referenced by the expression
+    pub fn licenses(&self) -> BTreeSet<&str> {
+        match self {
+            Self::License { id, .. } => BTreeSet::from([id.as_str()]),
+            Self::And(lhs, rhs) | Self::Or(lhs, rhs) => {
+                lhs.licenses().into_iter().chain(rhs.licenses()).collect()
+            }
+        }
+    }
+
+    /// Determine whether or not the expression can be satisfied by the given policy.
+    pub fn is_satisfied_by(&self, policy: &LicensePolicy) -> bool {
+        match self {
+            Self::License { id, .. } => {
+                let matches =
+                    |set: &BTreeSet<String>| set.iter().any(|l| l.eq_ignore_ascii_case(id));
+                !matches(&policy.deny) && (policy.allow.is_empty() || matches(&policy.allow))
+            }
+            Self::And(lhs, rhs) => lhs.is_satisfied_by(policy) && rhs.is_satisfied_by(policy),
+            Self::Or(lhs, rhs) => lhs.is_satisfied_by(policy) || rhs.is_satisfied_by(policy),
+        }
+    }
+}
+
+impl FromStr for LicenseExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Split the expression into tokens, treating parentheses and `/` as their own tokens
+        let tokens: Vec<String> = s
+            .replace('(', " ( ")
+            .replace(')', " ) ")
+            .replace('/', " / ")
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect();
+
+        let mut parser = ExprParser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected token '{}' in license expression '{}'", token, s)
+        }
+
+        Ok(expr)
+    }
+}
+
+impl fmt::Display for LicenseExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::License { id, exception } => match exception {
+                Some(exception) => write!(f, "{} WITH {}", id, exception),
+                None => write!(f, "{}", id),
+            },
+            Self::And(lhs, rhs) => {
+                // `AND` binds tighter than `OR` so nested `OR` expressions need parentheses
+                let term = |f: &mut fmt::Formatter<'_>, expr: &LicenseExpr| match expr {
+                    Self::Or(..) => write!(f, "({})", expr),
+                    _ => write!(f, "{}", expr),
+                };
+                term(f, lhs)?;
+                write!(f, " AND ")?;
+                term(f, rhs)
+            }
+            Self::Or(lhs, rhs) => write!(f, "{} OR {}", lhs, rhs),
+        }
+    }
+}
+
+/// A recursive descent parser for license expressions. `AND` binds tighter than `OR`.
+struct ExprParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+}
+
+impl ExprParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.pos).map(String::as_str);
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<LicenseExpr> {
+        let mut expr = self.parse_and()?;
+        while matches!(self.peek(), Some(op) if op == "/" || op.eq_ignore_ascii_case("OR")) {
+            self.next();
+            expr = LicenseExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<LicenseExpr> {
+        let mut expr = self.parse_term()?;
+        while matches!(self.peek(), Some(op) if op.eq_ignore_ascii_case("AND")) {
+            self.next();
+            expr = LicenseExpr::And(Box::new(expr), Box::new(self.parse_term()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_term(&mut self) -> Result<LicenseExpr> {
+        match self.next() {
+            Some("(") => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => bail!("Unclosed parenthesis in license expression"),
+                }
+            }
+            Some(token) if is_operator(token) => {
+                bail!("Expected a license but found '{}'", token)
+            }
+            Some(id) => {
+                let id = id.to_owned();
+                let exception = match self.peek() {
+                    Some(op) if op.eq_ignore_ascii_case("WITH") => {
+                        self.next();
+                        match self.next() {
+                            Some(exception) if !is_operator(exception) => {
+                                Some(exception.to_owned())
+                            }
+                            _ => bail!("Expected an exception after 'WITH' for '{}'", id),
+                        }
+                    }
+                    _ => None,
+                };
+                Ok(LicenseExpr::License { id, exception })
+            }
+            None => bail!("Unexpected end of license expression"),
+        }
+    }
+}
+
+fn is_operator(token: &str) -> bool {
+    ["(", ")", "/", "AND", "OR", "WITH"]
+        .iter()
+        .any(|op| op.eq_ignore_ascii_case(token))
+}
+
+/// The license information of a single third party crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrateLicense {
+    /// The name of the crate
+    pub name: String,
+
+    /// The version of the crate
+    pub version: String,
+
+    /// The crate's license expression, if one was declared
+    pub license: Option<String>,
+}
+
+/// An aggregation of the licenses used by all third party crates in a [Context]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LicenseReport {
+    /// Every third party crate and its license
+    pub crates: Vec<CrateLicense>,
+
+    /// A mapping of license identifiers to the crates which reference them
+    pub licenses: BTreeMap<String, BTreeSet<CrateId>>,
+
+    /// Crates which have no license expression
+    pub unlicensed: BTreeSet<CrateId>,
+
+    /// Crates whose license expression could not be parsed
+    pub unparsed: BTreeSet<CrateId>,
+}
+
+impl LicenseReport {
+    pub fn new(context: &Context) -> Self {
+        let mut crates = Vec::new();
+        let mut licenses: BTreeMap<String, BTreeSet<CrateId>> = BTreeMap::new();
+        let mut unlicensed = BTreeSet::new();
+        let mut unparsed = BTreeSet::new();
+
+        // Workspace members are first party code and not included in the report
+        for (id, ctx) in context
+            .crates
+            .iter()
+            .filter(|(id, _)| !context.workspace_members.contains_key(id))
+        {
+            crates.push(CrateLicense {
+                name: ctx.name.clone(),
+                version: ctx.version.clone(),
+                license: ctx.license.clone(),
+            });
+
+            match &ctx.license {
+                Some(license) => match LicenseExpr::from_str(license) {
+                    Ok(expr) => {
+                        for license in expr.licenses() {
+                            licenses
+                                .entry(license.to_owned())
+                                .or_default()
+                                .insert(id.clone());
+                        }
+                    }
+                    Err(_) => {
+                        unparsed.insert(id.clone());
+                    }
+                },
+                None => {
+                    unlicensed.insert(id.clone());
+                }
+            }
+        }
+
+        Self {
+            crates,
+            licenses,
+            unlicensed,
+            unparsed,
+        }
+    }
+
+    /// Render the report as CSV with one row per crate
+    pub fn to_csv(&self) -> String {
+        let escape = |field: &str| match field.contains([',', '"', '\n']) {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field.to_owned(),
+        };
+
+        let mut csv = String::from("name,version,license\n");
+        for krate in self.crates.iter() {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                escape(&krate.name),
+                escape(&krate.version),
+                escape(krate.license.as_deref().unwrap_or_default())
+            ));
+        }
+        csv
+    }
+
+    /// Render the report as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let escape = |field: &str| field.replace('|', "\\|");
+
+        let mut md = String::from("# Third party licenses\n\n## Summary\n\n");
+        md.push_str("| License | Crates |\n| --- | --- |\n");
+        for (license, crates) in self.licenses.iter() {
+            md.push_str(&format!("| {} | {} |\n", escape(license), crates.len()));
+        }
+        if !self.unlicensed.is_empty() {
+            md.push_str(&format!("| _None_ | {} |\n", self.unlicensed.len()));
+        }
+        if !self.unparsed.is_empty() {
+            md.push_str(&format!("| _Unparsed_ | {} |\n", self.unparsed.len()));
+        }
+
+        md.push_str("\n## Crates\n\n| Name | Version | License |\n| --- | --- | --- |\n");
+        for krate in self.crates.iter() {
+            md.push_str(&format!(
+                "| {} | {} | {} |\n",
+                escape(&krate.name),
+                escape(&krate.version),
+                krate
+                    .license
+                    .as_deref()
+                    .map(escape)
+                    .unwrap_or_else(|| "_None_".to_owned())
+            ));
+        }
+        md
+    }
+}
+
+/// Ensure the licenses of all third party crates satisfy the given policy. When the policy
+/// has no allowed licenses, only crates which reference a denied license are violations.
+pub fn enforce_license_policy(policy: &LicensePolicy, context: &Context) -> Result<()> {
+    let deny_only = policy.allow.is_empty();
+    let is_denied = |id: &str| policy.deny.iter().any(|l| l.eq_ignore_ascii_case(id));
+
+    let mut violations = Vec::new();
+    for (id, ctx) in context
+        .crates
+        .iter()
+        .filter(|(id, _)| !context.workspace_members.contains_key(id))
+    {
+        match &ctx.license {
+            Some(license) => match LicenseExpr::from_str(license) {
+                Ok(expr) => {
+                    if !expr.is_satisfied_by(policy) {
+                        violations.push(format!("{}: '{}'", id, license));
+                    }
+                }
+                // Without an allow list, an expression is only checked for denied licenses
+                Err(_) if deny_only => {
+                    if license
+                        .split(|c: char| c.is_whitespace() || "()/".contains(c))
+                        .any(is_denied)
+                    {
+                        violations.push(format!("{}: '{}'", id, license));
+                    }
+                }
+                Err(e) => violations.push(format!("{}: {}", id, e)),
+            },
+            // Crates with only a `license-file` cannot reference a denied license
+            None if deny_only => {}
+            None => violations.push(format!("{}: no license expression", id)),
+        }
+    }
+
+    if !violations.is_empty() {
+        bail!(
+            "The following crates do not satisfy the license policy:\n  {}",
+            violations.join("\n  ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::context::crate_context::CrateContext;
+
+    fn license(id: &str) -> LicenseExpr {
+        LicenseExpr::License {
+            id: id.to_owned(),
+            exception: None,
+        }
+    }
+
+    fn mock_context() -> Context {
+        let mut context = Context::default();
+        for (name, license) in [
+            ("dual", Some("MIT OR Apache-2.0")),
+            ("legacy", Some("MIT/Apache-2.0")),
+            ("copyleft", Some("GPL-3.0-only")),
+            ("mystery", None),
+            ("broken", Some("MIT OR")),
+        ] {
+            let id = CrateId::new(name.to_owned(), "1.0.0".to_owned());
+            let ctx = CrateContext {
+                name: name.to_owned(),
+                version: "1.0.0".to_owned(),
+                license: license.map(str::to_owned),
+                ..CrateContext::default()
+            };
+            context.crates.insert(id, ctx);
+        }
+
+        // Workspace members are never checked
+        let member = CrateId::new("member".to_owned(), "0.1.0".to_owned());
+        let ctx = CrateContext {
+            name: "member".to_owned(),
+            version: "0.1.0".to_owned(),
+            ..CrateContext::default()
+        };
+        context.crates.insert(member.clone(), ctx);
+        context
+            .workspace_members
+            .insert(member, "member".to_owned());
+
+        context
+    }
+
+    #[test]
+    fn parse_expressions() {
+        assert_eq!(LicenseExpr::from_str("MIT").unwrap(), license("MIT"));
+        assert_eq!(
+            LicenseExpr::from_str("MIT/Apache-2.0").unwrap(),
+            LicenseExpr::Or(Box::new(license("MIT")), Box::new(license("Apache-2.0")))
+        );
+        assert_eq!(
+            LicenseExpr::from_str("(MIT OR Apache-2.0) AND BSD-3-Clause").unwrap(),
+            LicenseExpr::And(
+                Box::new(LicenseExpr::Or(
+                    Box::new(license("MIT")),
+                    Box::new(license("Apache-2.0"))
+                )),
+                Box::new(license("BSD-3-Clause"))
+            )
+        );
+        assert_eq!(
+            LicenseExpr::from_str("Apache-2.0 WITH LLVM-exception OR MIT").unwrap(),
+            LicenseExpr::Or(
+                Box::new(LicenseExpr::License {
+                    id: "Apache-2.0".to_owned(),
+                    exception: Some("LLVM-exception".to_owned()),
+                }),
+                Box::new(license("MIT"))
+            )
+        );
+    }
+
+    #[test]
+    fn display_expressions() {
+        let normalize = |s: &str| LicenseExpr::from_str(s).unwrap().to_string();
+
+        assert_eq!(normalize("MIT/Apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(
+            normalize("(MIT OR Apache-2.0) AND Unicode-DFS-2016"),
+            "(MIT OR Apache-2.0) AND Unicode-DFS-2016"
+        );
+        assert_eq!(
+            normalize("Apache-2.0 WITH LLVM-exception or MIT and BSD-3-Clause"),
+            "Apache-2.0 WITH LLVM-exception OR MIT AND BSD-3-Clause"
+        );
+    }
+
+    #[test]
+    fn parse_invalid_expressions() {
+        assert!(LicenseExpr::from_str("").is_err());
+        assert!(LicenseExpr::from_str("MIT OR").is_err());
+        assert!(LicenseExpr::from_str("(MIT").is_err());
+        assert!(LicenseExpr::from_str("MIT Apache-2.0").is_err());
+    }
+
+    #[test]
+    fn policy_satisfaction() {
+        let expr = LicenseExpr::from_str("MIT OR GPL-3.0-only").unwrap();
+
+        let allow_mit = LicensePolicy {
+            allow: BTreeSet::from(["mit".to_owned()]),
+            ..LicensePolicy::default()
+        };
+        assert!(expr.is_satisfied_by(&allow_mit));
+
+        let deny_both = LicensePolicy {
+            deny: BTreeSet::from(["MIT".to_owned(), "GPL-3.0-only".to_owned()]),
+            ..LicensePolicy::default()
+        };
+        assert!(!expr.is_satisfied_by(&deny_both));
+
+        let expr = LicenseExpr::from_str("MIT AND GPL-3.0-only").unwrap();
+        assert!(!expr.is_satisfied_by(&allow_mit));
+    }
+
+    #[test]
+    fn report() {
+        let report = LicenseReport::new(&mock_context());
+
+        assert_eq!(report.crates.len(), 5);
+        assert_eq!(
+            report.licenses.keys().collect::<Vec<_>>(),
+            vec!["Apache-2.0", "GPL-3.0-only", "MIT"]
+        );
+        assert_eq!(report.licenses["MIT"].len(), 2);
+        assert_eq!(
+            report.unlicensed,
+            BTreeSet::from([CrateId::new("mystery".to_owned(), "1.0.0".to_owned())])
+        );
+        assert_eq!(
+            report.unparsed,
+            BTreeSet::from([CrateId::new("broken".to_owned(), "1.0.0".to_owned())])
+        );
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("name,version,license\n"));
+        assert!(csv.contains("copyleft,1.0.0,GPL-3.0-only\n"));
+        assert!(csv.contains("mystery,1.0.0,\n"));
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| MIT | 2 |\n"));
+        assert!(markdown.contains("| _None_ | 1 |\n"));
+        assert!(markdown.contains("| _Unparsed_ | 1 |\n"));
+        assert!(markdown.contains("| legacy | 1.0.0 | MIT/Apache-2.0 |\n"));
+    }
+
+    #[test]
+    fn enforce_policy() {
+        let context = mock_context();
+
+        let policy = LicensePolicy {
+            allow: BTreeSet::from(["MIT".to_owned()]),
+            ..LicensePolicy::default()
+        };
+        let err = enforce_license_policy(&policy, &context)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("copyleft 1.0.0: 'GPL-3.0-only'"));
+        assert!(err.contains("mystery 1.0.0: no license expression"));
+        assert!(err.contains("broken 1.0.0: Unexpected end of license expression"));
+        assert!(!err.contains("dual"));
+        assert!(!err.contains("legacy"));
+        assert!(!err.contains("member"));
+    }
+
+    #[test]
+    fn enforce_deny_only_policy() {
+        let mut context = mock_context();
+
+        let policy = LicensePolicy {
+            deny: BTreeSet::from(["GPL-3.0-only".to_owned()]),
+            ..LicensePolicy::default()
+        };
+        let err = enforce_license_policy(&policy, &context)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("copyleft 1.0.0: 'GPL-3.0-only'"));
+
+        // Crates without a parsable expression only fail if they name a denied license
+        assert!(!err.contains("mystery"));
+        assert!(!err.contains("broken"));
+
+        context
+            .crates
+            .retain(|id, _| id.name != "copyleft" && id.name != "broken");
+        context.crates.insert(
+            CrateId::new("broken".to_owned(), "1.0.0".to_owned()),
+            CrateContext {
+                name: "broken".to_owned(),
+                version: "1.0.0".to_owned(),
+                license: Some("MIT OR (GPL-3.0-only".to_owned()),
+                ..CrateContext::default()
+            },
+        );
+        let err = enforce_license_policy(&policy, &context)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("broken 1.0.0: 'MIT OR (GPL-3.0-only'"));
+
+        context.crates.retain(|id, _| id.name != "broken");
+        assert!(enforce_license_policy(&policy, &context).is_ok());
+    }
+}
@@ -1,5 +1,7 @@
 //! Utility module for interracting with different kinds of lock files
 
+pub mod diff;
+
 use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::fs;
@@ -80,16 +82,26 @@ pub fn lock_context(
 ) -> Result<Context> {
     // Ensure there is no existing checksum which could impact the lockfile results
     context.checksum = None;
+    context.checksum_inputs = None;
 
     let checksum = Digest::new(&context, config, splicing_manifest, cargo_bin, rustc_bin)
         .context("Failed to generate context digest")?;
+    let checksum_inputs =
+        DigestInputs::new(&context, config, splicing_manifest, cargo_bin, rustc_bin)
+            .context("Failed to generate input digests")?;
 
     Ok(Context {
         checksum: Some(checksum),
+        checksum_inputs: Some(checksum_inputs),
         ..context
     })
 }
 
+/// Serialize a lockfile to the content [write_lockfile] would write to disk
+pub fn lockfile_content(lockfile: &Context) -> Result<String> {
+    Ok(serde_json::to_string_pretty(lockfile)? + "\n")
+}
+
 /// Write a [crate::planning::PlannedContext] to disk
 pub fn write_lockfile(lockfile: Context, path: &Path, dry_run: bool) -> Result<()> {
     let content = serde_json::to_string_pretty(&lockfile)?;
@@ -125,27 +137,26 @@ impl Digest {
         let cargo_bazel_version = env!("CARGO_PKG_VERSION");
 
         // Ensure the checksum of a digest is not present before computing one
-        Ok(match context.checksum {
-            Some(_) => Self::compute(
-                &Context {
-                    checksum: None,
-                    ..context.clone()
-                },
-                config,
-                &splicing_metadata,
-                cargo_bazel_version,
-                &cargo_version,
-                &rustc_version,
-            ),
-            None => Self::compute(
-                context,
-                config,
-                &splicing_metadata,
-                cargo_bazel_version,
-                &cargo_version,
-                &rustc_version,
-            ),
-        })
+        Ok(
+            match context.checksum.is_some() || context.checksum_inputs.is_some() {
+                true => Self::compute(
+                    &without_checksums(context),
+                    config,
+                    &splicing_metadata,
+                    cargo_bazel_version,
+                    &cargo_version,
+                    &rustc_version,
+                ),
+                false => Self::compute(
+                    context,
+                    config,
+                    &splicing_metadata,
+                    cargo_bazel_version,
+                    &cargo_version,
+                    &rustc_version,
+                ),
+            },
+        )
     }
 
     fn compute(
@@ -160,6 +171,7 @@ impl Digest {
         // always None. This then allows us to have this method not return a
         // Result.
         debug_assert!(context.checksum.is_none());
+        debug_assert!(context.checksum_inputs.is_none());
 
         let mut hasher = Sha256::new();
 
@@ -184,6 +196,13 @@ impl Digest {
         Self(hasher.finalize().encode_hex::<String>())
     }
 
+    /// Compute the digest of a single serializable input
+    fn of<T: Serialize>(value: &T) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(serde_json::to_string(value).unwrap().as_bytes());
+        Self(hasher.finalize().encode_hex::<String>())
+    }
+
     fn bin_version(binary: &Path) -> Result<String> {
         let safe_vars = [OsStr::new("HOMEDRIVE"), OsStr::new("PATHEXT")];
         let env = std::env::vars_os().filter(|(var, _)| safe_vars.contains(&var.as_os_str()));
@@ -203,6 +222,101 @@ impl Digest {
     }
 }
 
+/// A copy of a [Context] with no checksum information
+fn without_checksums(context: &Context) -> Context {
+    Context {
+        checksum: None,
+        checksum_inputs: None,
+        ..context.clone()
+    }
+}
+
+/// The individual inputs which contribute to a lockfile's [Digest]. Storing these alongside the
+/// digest allows the cause of a digest mismatch to be identified.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct DigestInputs {
+    /// The version of `cargo-bazel` which generated the lockfile
+    pub cargo_bazel_version: String,
+
+    /// The digest of the lockfile's rendering context
+    pub context: Digest,
+
+    /// The digest of the `cargo-bazel` config
+    pub config: Digest,
+
+    /// The digest of the splicing inputs
+    pub splicing_manifest: Digest,
+
+    /// The output of `cargo --version`
+    pub cargo_version: String,
+
+    /// The output of `rustc --version`
+    pub rustc_version: String,
+}
+
+impl DigestInputs {
+    pub fn new(
+        context: &Context,
+        config: &Config,
+        splicing_manifest: &SplicingManifest,
+        cargo_bin: &Path,
+        rustc_bin: &Path,
+    ) -> Result<Self> {
+        let splicing_metadata = SplicingMetadata::try_from((*splicing_manifest).clone())?;
+
+        Ok(Self::compute(
+            &without_checksums(context),
+            config,
+            &splicing_metadata,
+            env!("CARGO_PKG_VERSION"),
+            &Digest::bin_version(cargo_bin)?,
+            &Digest::bin_version(rustc_bin)?,
+        ))
+    }
+
+    fn compute(
+        context: &Context,
+        config: &Config,
+        splicing_metadata: &SplicingMetadata,
+        cargo_bazel_version: &str,
+        cargo_version: &str,
+        rustc_version: &str,
+    ) -> Self {
+        Self {
+            cargo_bazel_version: cargo_bazel_version.to_owned(),
+            context: Digest::of(context),
+            config: Digest::of(config),
+            splicing_manifest: Digest::of(splicing_metadata),
+            cargo_version: cargo_version.trim().to_owned(),
+            rustc_version: rustc_version.trim().to_owned(),
+        }
+    }
+
+    /// The names of all inputs which differ between two sets of inputs
+    pub fn changes(&self, other: &Self) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.cargo_bazel_version != other.cargo_bazel_version {
+            changes.push("cargo_bazel_version");
+        }
+        if self.context != other.context {
+            changes.push("context");
+        }
+        if self.config != other.config {
+            changes.push("config");
+        }
+        if self.splicing_manifest != other.splicing_manifest {
+            changes.push("splicing_manifest");
+        }
+        if self.cargo_version != other.cargo_version {
+            changes.push("cargo_version");
+        }
+        if self.rustc_version != other.rustc_version {
+            changes.push("rustc_version");
+        }
+        changes
+    }
+}
+
 impl PartialEq<str> for Digest {
     fn eq(&self, other: &str) -> bool {
         self.0 == other
@@ -219,6 +333,7 @@ impl PartialEq<String> for Digest {
 mod test {
     use crate::config::{CrateAnnotations, CrateId};
     use crate::splicing::cargo_config::{AdditionalRegistry, CargoConfig, Registry};
+    use crate::utils::starlark::SelectStringList;
 
     use super::*;
 
@@ -369,6 +484,145 @@ mod test {
         );
     }
 
+    #[test]
+    fn digest_inputs_changes() {
+        let context = Context::default();
+        let config = Config::default();
+        let splicing_metadata = SplicingMetadata::default();
+
+        let inputs = DigestInputs::compute(
+            &context,
+            &config,
+            &splicing_metadata,
+            "0.1.0",
+            "cargo 1.57.0 (b2e52d7ca 2021-10-21)\n",
+            "rustc 1.57.0 (f1edd0429 2021-11-29)\n",
+        );
+        assert_eq!(inputs.rustc_version, "rustc 1.57.0 (f1edd0429 2021-11-29)");
+        assert!(inputs.changes(&inputs).is_empty());
+
+        let updated = DigestInputs::compute(
+            &context,
+            &Config {
+                generate_build_scripts: true,
+                ..Config::default()
+            },
+            &splicing_metadata,
+            "0.1.0",
+            "cargo 1.57.0 (b2e52d7ca 2021-10-21)\n",
+            "rustc 1.58.0 (02072b482 2022-01-11)\n",
+        );
+        assert_eq!(inputs.changes(&updated), vec!["config", "rustc_version"]);
+    }
+
+    #[test]
+    fn digest_ignores_checksum_inputs() {
+        let context = Context::default();
+        let config = Config::default();
+        let splicing_metadata = SplicingMetadata::default();
+
+        let inputs = DigestInputs::compute(
+            &context,
+            &config,
+            &splicing_metadata,
+            "0.1.0",
+            "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
+            "rustc 1.57.0 (f1edd0429 2021-11-29)",
+        );
+        let locked = Context {
+            checksum_inputs: Some(inputs),
+            ..Context::default()
+        };
+
+        assert_eq!(
+            Digest::compute(
+                &without_checksums(&locked),
+                &config,
+                &splicing_metadata,
+                "0.1.0",
+                "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
+                "rustc 1.57.0 (f1edd0429 2021-11-29)",
+            ),
+            Digest::compute(
+                &context,
+                &config,
+                &splicing_metadata,
+                "0.1.0",
+                "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
+                "rustc 1.57.0 (f1edd0429 2021-11-29)",
+            )
+        );
+    }
+
+    #[test]
+    fn read_lockfile_with_flat_extra_deps() {
+        // Lockfiles written before `extra_deps` were selectable store them as plain lists
+        let temp_dir = tempfile::tempdir().unwrap();
+        let lockfile = temp_dir.as_ref().join("lockfile");
+        fs::write(
+            &lockfile,
+            serde_json::json!({
+                "checksum": "a2e8c7c9e08c1ff81ec9b1665c2944ed5da8e0eaf04e9f4d846a9317d2ac332f",
+                "crates": {
+                    "libz-sys 1.1.3": {
+                        "name": "libz-sys",
+                        "version": "1.1.3",
+                        "repository": null,
+                        "targets": [],
+                        "common_attrs": {
+                            "extra_deps": ["@zlib"],
+                            "extra_proc_macro_deps": ["@zlib_macros"],
+                            "edition": "2015",
+                            "version": "1.1.3"
+                        },
+                        "build_script_attrs": {
+                            "extra_deps": ["@zlib_build"]
+                        }
+                    }
+                },
+                "binary_crates": [],
+                "workspace_members": {},
+                "conditions": {}
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let context = Context::try_from_path(&lockfile).unwrap();
+        let crate_context =
+            &context.crates[&CrateId::new("libz-sys".to_owned(), "1.1.3".to_owned())];
+
+        let mut extra_deps = SelectStringList::default();
+        extra_deps.insert("@zlib".to_owned(), None);
+        assert_eq!(crate_context.common_attrs.extra_deps, extra_deps);
+
+        let mut extra_proc_macro_deps = SelectStringList::default();
+        extra_proc_macro_deps.insert("@zlib_macros".to_owned(), None);
+        assert_eq!(
+            crate_context.common_attrs.extra_proc_macro_deps,
+            extra_proc_macro_deps
+        );
+
+        let mut build_script_extra_deps = SelectStringList::default();
+        build_script_extra_deps.insert("@zlib_build".to_owned(), None);
+        assert_eq!(
+            crate_context
+                .build_script_attrs
+                .as_ref()
+                .unwrap()
+                .extra_deps,
+            build_script_extra_deps
+        );
+
+        // The current format is written back
+        let content = lockfile_content(&context).unwrap();
+        assert!(content.contains("\"extra_deps\": {\n"));
+        assert_eq!(
+            serde_json::from_str::<Context>(&content).unwrap().crates,
+            context.crates
+        );
+    }
+
     #[test]
     fn detect_bazel_lockfile() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -0,0 +1,547 @@
+//! Compare two lockfiles to produce a summary of how pinned crates changed
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::config::{Commitish, CrateId};
+use crate::context::crate_context::{CrateContext, CrateDependency};
+use crate::context::Context;
+use crate::metadata::SourceAnnotation;
+use crate::utils::starlark::{Select, SelectList};
+
+/// The key used to represent unconditional entries of a select
+const COMMON_CONFIGURATION: &str = "common";
+
+/// A pair of values representing something before and after a change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// The entries added to and removed from a collection.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct SetChange {
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub added: BTreeSet<String>,
+
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub removed: BTreeSet<String>,
+}
+
+impl SetChange {
+    fn new(old: &BTreeSet<String>, new: &BTreeSet<String>) -> Self {
+        Self {
+            added: new.difference(old).cloned().collect(),
+            removed: old.difference(new).cloned().collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Changes to where and how a crate's source is acquired.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceChange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<Change<Option<String>>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<Change<Option<String>>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitish: Option<Change<Option<String>>>,
+}
+
+impl SourceChange {
+    fn new(old: &Option<SourceAnnotation>, new: &Option<SourceAnnotation>) -> Self {
+        let (old_url, old_sha256, old_commitish) = source_fields(old);
+        let (new_url, new_sha256, new_commitish) = source_fields(new);
+
+        let change = |old: Option<String>, new: Option<String>| -> Option<Change<Option<String>>> {
+            if old == new {
+                None
+            } else {
+                Some(Change { old, new })
+            }
+        };
+
+        Self {
+            url: change(old_url, new_url),
+            sha256: change(old_sha256, new_sha256),
+            commitish: change(old_commitish, new_commitish),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.url.is_none() && self.sha256.is_none() && self.commitish.is_none()
+    }
+}
+
+/// All changes between two versions of the same crate.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct CrateChange {
+    /// Changes to the crate's `crate_features`
+    #[serde(skip_serializing_if = "SetChange::is_empty")]
+    pub crate_features: SetChange,
+
+    /// Changes to each dependency attribute, keyed by attribute name then configuration
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub deps: BTreeMap<String, BTreeMap<String, SetChange>>,
+
+    /// Changes to the crate's source annotation
+    #[serde(skip_serializing_if = "SourceChange::is_empty")]
+    pub source: SourceChange,
+}
+
+impl CrateChange {
+    fn new(old: &CrateContext, new: &CrateContext) -> Self {
+        let old_deps = collect_deps(old);
+        let new_deps = collect_deps(new);
+
+        let attributes: BTreeSet<&String> = old_deps.keys().chain(new_deps.keys()).collect();
+        let deps = attributes
+            .into_iter()
+            .filter_map(|attr| {
+                let empty = BTreeMap::new();
+                let old_selects = old_deps.get(attr).unwrap_or(&empty);
+                let new_selects = new_deps.get(attr).unwrap_or(&empty);
+
+                let configurations: BTreeSet<&String> =
+                    old_selects.keys().chain(new_selects.keys()).collect();
+                let changes: BTreeMap<String, SetChange> = configurations
+                    .into_iter()
+                    .filter_map(|conf| {
+                        let empty = BTreeSet::new();
+                        let change = SetChange::new(
+                            old_selects.get(conf).unwrap_or(&empty),
+                            new_selects.get(conf).unwrap_or(&empty),
+                        );
+                        if change.is_empty() {
+                            None
+                        } else {
+                            Some((conf.clone(), change))
+                        }
+                    })
+                    .collect();
+
+                if changes.is_empty() {
+                    None
+                } else {
+                    Some((attr.clone(), changes))
+                }
+            })
+            .collect();
+
+        Self {
+            crate_features: SetChange::new(
+                &old.common_attrs.crate_features,
+                &new.common_attrs.crate_features,
+            ),
+            deps,
+            source: SourceChange::new(&old.repository, &new.repository),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.crate_features.is_empty() && self.deps.is_empty() && self.source.is_empty()
+    }
+}
+
+/// A crate which was pinned to a different version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionChange {
+    /// The name of the crate
+    pub name: String,
+
+    /// The old and new versions of the crate
+    pub version: Change<String>,
+
+    /// Other changes to the crate between versions
+    #[serde(skip_serializing_if = "CrateChange::is_empty")]
+    pub changes: CrateChange,
+}
+
+/// A summary of the differences between two [Context] lockfiles.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct LockfileDiff {
+    /// Crates which only exist in the new lockfile
+    pub added: BTreeSet<CrateId>,
+
+    /// Crates which only exist in the old lockfile
+    pub removed: BTreeSet<CrateId>,
+
+    /// Crates whose version changed
+    pub upgraded: Vec<VersionChange>,
+
+    /// Crates with the same version in both lockfiles but different attributes
+    pub changed: BTreeMap<CrateId, CrateChange>,
+}
+
+impl LockfileDiff {
+    pub fn new(old: &Context, new: &Context) -> Self {
+        let old_ids: BTreeSet<&CrateId> = old.crates.keys().collect();
+        let new_ids: BTreeSet<&CrateId> = new.crates.keys().collect();
+
+        // Crates pinned at the same version in both lockfiles
+        let changed = old_ids
+            .intersection(&new_ids)
+            .filter_map(|id| {
+                let change = CrateChange::new(&old.crates[*id], &new.crates[*id]);
+                if change.is_empty() {
+                    None
+                } else {
+                    Some(((*id).clone(), change))
+                }
+            })
+            .collect();
+
+        // Pair up removed and added versions of the same crate to track upgrades. When
+        // multiple versions of a crate change, versions are paired in ascending order.
+        let mut added: BTreeSet<CrateId> = new_ids.difference(&old_ids).cloned().cloned().collect();
+        let mut removed: BTreeSet<CrateId> =
+            old_ids.difference(&new_ids).cloned().cloned().collect();
+        let names: BTreeSet<String> = removed.iter().map(|id| id.name.clone()).collect();
+
+        let mut upgraded = Vec::new();
+        for name in names {
+            let mut old_versions = versions_of(&removed, &name);
+            let mut new_versions = versions_of(&added, &name);
+            sort_versions(&mut old_versions);
+            sort_versions(&mut new_versions);
+
+            for (old_id, new_id) in old_versions.into_iter().zip(new_versions) {
+                upgraded.push(VersionChange {
+                    name: name.clone(),
+                    version: Change {
+                        old: old_id.version.clone(),
+                        new: new_id.version.clone(),
+                    },
+                    changes: CrateChange::new(&old.crates[&old_id], &new.crates[&new_id]),
+                });
+                removed.remove(&old_id);
+                added.remove(&new_id);
+            }
+        }
+
+        Self {
+            added,
+            removed,
+            upgraded,
+            changed,
+        }
+    }
+
+    /// Determine whether or not there are any differences
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.upgraded.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for LockfileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences found");
+        }
+
+        if !self.added.is_empty() {
+            writeln!(f, "Added:")?;
+            for id in self.added.iter() {
+                writeln!(f, "  + {}", id)?;
+            }
+        }
+
+        if !self.removed.is_empty() {
+            writeln!(f, "Removed:")?;
+            for id in self.removed.iter() {
+                writeln!(f, "  - {}", id)?;
+            }
+        }
+
+        if !self.upgraded.is_empty() {
+            writeln!(f, "Upgraded:")?;
+            for change in self.upgraded.iter() {
+                writeln!(
+                    f,
+                    "  ~ {} {} -> {}",
+                    change.name, change.version.old, change.version.new
+                )?;
+                write_crate_change(f, &change.changes)?;
+            }
+        }
+
+        if !self.changed.is_empty() {
+            writeln!(f, "Changed:")?;
+            for (id, change) in self.changed.iter() {
+                writeln!(f, "  ~ {}", id)?;
+                write_crate_change(f, change)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_crate_change(f: &mut fmt::Formatter<'_>, change: &CrateChange) -> fmt::Result {
+    if !change.crate_features.is_empty() {
+        writeln!(f, "      crate_features:")?;
+        write_set_change(f, &change.crate_features)?;
+    }
+
+    for (attr, selects) in change.deps.iter() {
+        for (conf, set_change) in selects.iter() {
+            writeln!(f, "      {} ({}):", attr, conf)?;
+            write_set_change(f, set_change)?;
+        }
+    }
+
+    let source_fields = [
+        ("url", &change.source.url),
+        ("sha256", &change.source.sha256),
+        ("commitish", &change.source.commitish),
+    ];
+    for (field, value) in source_fields {
+        if let Some(value) = value {
+            writeln!(
+                f,
+                "      {}: {} -> {}",
+                field,
+                value.old.as_deref().unwrap_or("None"),
+                value.new.as_deref().unwrap_or("None")
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_set_change(f: &mut fmt::Formatter<'_>, change: &SetChange) -> fmt::Result {
+    for entry in change.removed.iter() {
+        writeln!(f, "        - {}", entry)?;
+    }
+    for entry in change.added.iter() {
+        writeln!(f, "        + {}", entry)?;
+    }
+    Ok(())
+}
+
+fn versions_of(ids: &BTreeSet<CrateId>, name: &str) -> Vec<CrateId> {
+    ids.iter().filter(|id| id.name == name).cloned().collect()
+}
+
+/// Sort crate ids by semver, falling back to a lexical comparison for unparsable versions
+fn sort_versions(ids: &mut [CrateId]) {
+    ids.sort_by(|lhs, rhs| {
+        match (
+            semver::Version::parse(&lhs.version),
+            semver::Version::parse(&rhs.version),
+        ) {
+            (Ok(l), Ok(r)) => l.cmp(&r),
+            _ => lhs.version.cmp(&rhs.version),
+        }
+    });
+}
+
+/// Collect all dependencies of a crate as strings, keyed by attribute name and configuration.
+fn collect_deps(ctx: &CrateContext) -> BTreeMap<String, BTreeMap<String, BTreeSet<String>>> {
+    let self_id = CrateId::new(ctx.name.clone(), ctx.version.clone());
+
+    let mut attrs = vec![
+        ("deps", &ctx.common_attrs.deps),
+        ("deps_dev", &ctx.common_attrs.deps_dev),
+        ("proc_macro_deps", &ctx.common_attrs.proc_macro_deps),
+        ("proc_macro_deps_dev", &ctx.common_attrs.proc_macro_deps_dev),
+    ];
+    if let Some(build_attrs) = &ctx.build_script_attrs {
+        attrs.push(("build_script_deps", &build_attrs.deps));
+        attrs.push(("build_script_proc_macro_deps", &build_attrs.proc_macro_deps));
+    }
+
+    attrs
+        .into_iter()
+        .map(|(attr, deps)| (attr.to_owned(), collect_select_list(deps, &self_id)))
+        .filter(|(_, selects)| !selects.is_empty())
+        .collect()
+}
+
+fn collect_select_list(
+    deps: &SelectList<CrateDependency>,
+    self_id: &CrateId,
+) -> BTreeMap<String, BTreeSet<String>> {
+    deps.configurations()
+        .into_iter()
+        .map(|conf| {
+            let entries: BTreeSet<String> = deps
+                .get_iter(conf)
+                .expect("Iterating over known keys should never panic")
+                // Build scripts are tracked as a dependency of their own crate
+                .filter(|dep| &dep.id != self_id)
+                .map(|dep| match &dep.alias {
+                    Some(alias) => format!("{} (as {})", dep.id, alias),
+                    None => dep.id.to_string(),
+                })
+                .collect();
+
+            let conf = conf
+                .cloned()
+                .unwrap_or_else(|| COMMON_CONFIGURATION.to_owned());
+
+            (conf, entries)
+        })
+        .filter(|(_, entries)| !entries.is_empty())
+        .collect()
+}
+
+/// Extract the url, sha256, and git commitish of a source annotation
+fn source_fields(
+    source: &Option<SourceAnnotation>,
+) -> (Option<String>, Option<String>, Option<String>) {
+    match source {
+        Some(SourceAnnotation::Http { url, sha256, .. }) => {
+            (Some(url.clone()), sha256.clone(), None)
+        }
+        Some(SourceAnnotation::Git {
+            remote, commitish, ..
+        }) => {
+            let commitish = match commitish {
+                Commitish::Tag(v) => format!("tag={}", v),
+                Commitish::Branch(v) => format!("branch={}", v),
+                Commitish::Rev(v) => format!("rev={}", v),
+            };
+            (Some(remote.clone()), None, Some(commitish))
+        }
+        None => (None, None, None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mock_crate(name: &str, version: &str, sha256: &str) -> (CrateId, CrateContext) {
+        let id = CrateId::new(name.to_owned(), version.to_owned());
+        let context = CrateContext {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            repository: Some(SourceAnnotation::Http {
+                url: format!(
+                    "https://crates.io/api/v1/crates/{}/{}/download",
+                    name, version
+                ),
+                sha256: Some(sha256.to_owned()),
+                patch_args: None,
+                patch_tool: None,
+                patches: None,
+            }),
+            ..CrateContext::default()
+        };
+        (id, context)
+    }
+
+    #[test]
+    fn identical_lockfiles() {
+        let mut context = Context::default();
+        let (id, ctx) = mock_crate("mock_crate", "0.1.0", "abc");
+        context.crates.insert(id, ctx);
+
+        let diff = LockfileDiff::new(&context, &context);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No differences found\n");
+    }
+
+    #[test]
+    fn added_removed_and_upgraded() {
+        let mut old = Context::default();
+        let mut new = Context::default();
+
+        let (id, ctx) = mock_crate("removed", "1.0.0", "abc");
+        old.crates.insert(id, ctx);
+        let (id, ctx) = mock_crate("added", "1.0.0", "abc");
+        new.crates.insert(id, ctx);
+        let (id, ctx) = mock_crate("upgraded", "0.9.0", "abc");
+        old.crates.insert(id, ctx);
+        let (id, ctx) = mock_crate("upgraded", "0.10.0", "def");
+        new.crates.insert(id, ctx);
+
+        let diff = LockfileDiff::new(&old, &new);
+
+        assert_eq!(
+            diff.added,
+            BTreeSet::from([CrateId::new("added".to_owned(), "1.0.0".to_owned())])
+        );
+        assert_eq!(
+            diff.removed,
+            BTreeSet::from([CrateId::new("removed".to_owned(), "1.0.0".to_owned())])
+        );
+        assert_eq!(diff.upgraded.len(), 1);
+
+        let upgrade = &diff.upgraded[0];
+        assert_eq!(upgrade.name, "upgraded");
+        assert_eq!(
+            upgrade.version,
+            Change {
+                old: "0.9.0".to_owned(),
+                new: "0.10.0".to_owned(),
+            }
+        );
+        assert_eq!(
+            upgrade.changes.source.sha256,
+            Some(Change {
+                old: Some("abc".to_owned()),
+                new: Some("def".to_owned()),
+            })
+        );
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn changed_features_and_deps() {
+        let mut old = Context::default();
+        let mut new = Context::default();
+
+        let (dep_id, dep_ctx) = mock_crate("dep", "1.0.0", "abc");
+        old.crates.insert(dep_id.clone(), dep_ctx.clone());
+        new.crates.insert(dep_id.clone(), dep_ctx);
+
+        let (id, old_ctx) = mock_crate("mock_crate", "0.1.0", "abc");
+        let mut new_ctx = old_ctx.clone();
+        new_ctx.common_attrs.crate_features.insert("std".to_owned());
+        new_ctx.common_attrs.deps.insert(
+            CrateDependency {
+                id: dep_id,
+                target: "dep".to_owned(),
+                alias: None,
+            },
+            Some("cfg(unix)".to_owned()),
+        );
+        old.crates.insert(id.clone(), old_ctx);
+        new.crates.insert(id.clone(), new_ctx);
+
+        let diff = LockfileDiff::new(&old, &new);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.upgraded.is_empty());
+
+        let change = &diff.changed[&id];
+        assert_eq!(
+            change.crate_features.added,
+            BTreeSet::from(["std".to_owned()])
+        );
+        assert_eq!(
+            change.deps["deps"]["cfg(unix)"].added,
+            BTreeSet::from(["dep 1.0.0".to_owned()])
+        );
+
+        let text = diff.to_string();
+        assert!(text.contains("deps (cfg(unix)):"));
+        assert!(text.contains("+ dep 1.0.0"));
+    }
+}
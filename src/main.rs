@@ -11,5 +11,18 @@ fn main() -> cli::Result<()> {
         cli::Options::Splice(opt) => cli::splice(opt),
         cli::Options::Query(opt) => cli::query(opt),
         cli::Options::Vendor(opt) => cli::vendor(opt),
+        cli::Options::Diff(opt) => cli::diff(opt),
+        cli::Options::Why(opt) => cli::why(opt),
+        cli::Options::Tree(opt) => cli::tree(opt),
+        cli::Options::Graph(opt) => cli::graph(opt),
+        cli::Options::Licenses(opt) => cli::licenses(opt),
+        cli::Options::Audit(opt) => cli::audit(opt),
+        cli::Options::Validate(opt) => cli::validate(opt),
+        cli::Options::MigrateRaze(opt) => cli::migrate_raze(opt),
+        cli::Options::Dupes(opt) => cli::dupes(opt),
+        cli::Options::Sbom(opt) => cli::sbom(opt),
+        cli::Options::Mirror(opt) => cli::mirror(opt),
+        cli::Options::PopulateCache(opt) => cli::populate_cache(opt),
+        cli::Options::Schema(opt) => cli::schema(opt),
     }
 }
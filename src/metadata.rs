@@ -74,6 +74,48 @@ impl MetadataGenerator for Generator {
     }
 }
 
+/// A request to update a single crate in an existing Cargo lockfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoUpdateRequest {
+    /// The package spec of the crate to update (`cargo update --package`). Eg. `name`
+    /// or `name@version` where `version` is the currently locked version of the crate.
+    pub package: String,
+
+    /// An exact version to update the crate to (`cargo update --precise`)
+    pub precise: Option<String>,
+}
+
+impl CargoUpdateRequest {
+    /// Create update requests for the given package specs. An exact version may only be
+    /// requested when updating a single package.
+    pub fn new_list(packages: &[String], precise: &Option<String>) -> Result<Vec<Self>> {
+        if precise.is_some() && packages.len() != 1 {
+            bail!(
+                "An exact version can only be requested when updating a single crate. Got: {:?}",
+                packages
+            );
+        }
+
+        packages
+            .iter()
+            .map(|package| {
+                let name = package.split('@').next().unwrap_or_default();
+                if name.is_empty() || package.ends_with('@') {
+                    bail!(
+                        "Invalid update request '{}'. Expected `name` or `name@version`",
+                        package
+                    );
+                }
+
+                Ok(Self {
+                    package: package.clone(),
+                    precise: precise.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
 pub struct LockGenerator {
     /// The path to a `cargo` binary
     cargo_bin: PathBuf,
@@ -90,14 +132,22 @@ impl LockGenerator {
         }
     }
 
+    /// Generate a lockfile for the given manifest. When an existing lockfile is provided it's
+    /// reused as is unless `updates` are requested, in which case only the requested crates
+    /// (and any dependencies they require) are updated.
     pub fn generate(
         &self,
         manifest_path: &Path,
         existing_lock: &Option<PathBuf>,
+        updates: &[CargoUpdateRequest],
     ) -> Result<cargo_lock::Lockfile> {
         let manifest_dir = manifest_path.parent().unwrap();
         let generated_lockfile_path = manifest_dir.join("Cargo.lock");
 
+        if !updates.is_empty() && existing_lock.is_none() {
+            bail!("Updating specific crates requires an existing Cargo lockfile")
+        }
+
         let output = if let Some(lock) = existing_lock {
             if !lock.exists() {
                 bail!(
@@ -112,6 +162,37 @@ impl LockGenerator {
             }
             fs::copy(&lock, &generated_lockfile_path)?;
 
+            // Seed the update with the existing lockfile so unrelated crates remain pinned.
+            for update in updates {
+                let mut command = Command::new(&self.cargo_bin);
+                command
+                    .current_dir(manifest_dir)
+                    .arg("update")
+                    .arg("--manifest-path")
+                    .arg(manifest_path)
+                    .arg("--package")
+                    .arg(&update.package)
+                    .env("RUSTC", &self.rustc_bin);
+                if let Some(version) = &update.precise {
+                    command.arg("--precise").arg(version);
+                }
+
+                let output = command.output().context(format!(
+                    "Error running cargo to update '{}' in '{}'",
+                    update.package,
+                    manifest_path.display()
+                ))?;
+
+                if !output.status.success() {
+                    eprintln!("{}", String::from_utf8_lossy(&output.stdout));
+                    eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+                    bail!(format!(
+                        "Failed to update '{}': {}",
+                        update.package, output.status
+                    ))
+                }
+            }
+
             // Ensure the Cargo cache is up to date to simulate the behavior
             // of having just generated a new one
             Command::new(&self.cargo_bin)
@@ -239,3 +320,119 @@ pub fn load_metadata(
 
     Ok((metadata, lockfile))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cargo_update_requests() {
+        assert_eq!(
+            CargoUpdateRequest::new_list(&["serde".to_owned(), "libc@0.2.112".to_owned()], &None)
+                .unwrap(),
+            vec![
+                CargoUpdateRequest {
+                    package: "serde".to_owned(),
+                    precise: None,
+                },
+                CargoUpdateRequest {
+                    package: "libc@0.2.112".to_owned(),
+                    precise: None,
+                },
+            ]
+        );
+        assert_eq!(
+            CargoUpdateRequest::new_list(&["libc@0.2.112".to_owned()], &Some("0.2.113".to_owned()))
+                .unwrap(),
+            vec![CargoUpdateRequest {
+                package: "libc@0.2.112".to_owned(),
+                precise: Some("0.2.113".to_owned()),
+            }]
+        );
+        assert!(CargoUpdateRequest::new_list(&["@0.2.112".to_owned()], &None).is_err());
+        assert!(CargoUpdateRequest::new_list(&["libc@".to_owned()], &None).is_err());
+        assert!(CargoUpdateRequest::new_list(
+            &["libc".to_owned(), "serde".to_owned()],
+            &Some("0.2.113".to_owned())
+        )
+        .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn update_existing_lockfile() {
+        use std::collections::BTreeSet;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        let manifest_path = workspace.join("Cargo.toml");
+        fs::write(&manifest_path, "").unwrap();
+
+        let existing_lock = dir.path().join("Cargo.lock");
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("test_data/metadata/build_scripts/Cargo.lock"),
+            &existing_lock,
+        )
+        .unwrap();
+
+        // A stand-in for `cargo` which records its arguments and simulates `cargo update`
+        // by bumping the locked version of `libc`.
+        let log = dir.path().join("cargo.log");
+        let cargo = dir.path().join("cargo");
+        fs::write(
+            &cargo,
+            format!(
+                r#"#!/bin/sh
+echo "$@" >> "{log}"
+if [ "$1" = "update" ]; then
+    sed -i.bak 's/^version = "0.2.112"$/version = "0.2.113"/' Cargo.lock
+fi
+"#,
+                log = log.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&cargo, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let updates =
+            CargoUpdateRequest::new_list(&["libc@0.2.112".to_owned()], &Some("0.2.113".to_owned()))
+                .unwrap();
+        let lockfile = LockGenerator::new(cargo, PathBuf::from("rustc"))
+            .generate(&manifest_path, &Some(existing_lock.clone()), &updates)
+            .unwrap();
+
+        let commands = fs::read_to_string(&log).unwrap();
+        assert_eq!(
+            commands.lines().collect::<Vec<&str>>(),
+            vec![
+                format!(
+                    "update --manifest-path {} --package libc@0.2.112 --precise 0.2.113",
+                    manifest_path.display()
+                ),
+                format!("fetch --locked --manifest-path {}", manifest_path.display()),
+            ]
+        );
+
+        // Only the requested crate was updated
+        let versions = |lockfile: &cargo_lock::Lockfile| -> BTreeSet<String> {
+            lockfile
+                .packages
+                .iter()
+                .map(|pkg| format!("{} {}", pkg.name, pkg.version))
+                .collect()
+        };
+        let original = versions(&cargo_lock::Lockfile::load(&existing_lock).unwrap());
+        let updated = versions(&lockfile);
+        assert_eq!(
+            updated.difference(&original).collect::<Vec<&String>>(),
+            vec!["libc 0.2.113"]
+        );
+        assert_eq!(
+            original.difference(&updated).collect::<Vec<&String>>(),
+            vec!["libc 0.2.112"]
+        );
+    }
+}
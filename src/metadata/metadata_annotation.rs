@@ -9,7 +9,7 @@ use cargo_metadata::{Node, Package, PackageId};
 use hex::ToHex;
 use serde::{Deserialize, Serialize};
 
-use crate::config::{Commitish, Config, CrateAnnotations, CrateId};
+use crate::config::{Commitish, Config, CrateAnnotations, CrateId, NamePattern};
 use crate::metadata::dependency::DependencySet;
 use crate::splicing::{SourceInfo, WorkspaceMetadata};
 
@@ -378,10 +378,17 @@ impl Annotations {
             .packages
             .iter()
             .filter_map(|(pkg_id, pkg)| {
-                let extras: Vec<CrateAnnotations> = config
+                let mut matching: Vec<(&CrateId, &CrateAnnotations)> = config
                     .annotations
                     .iter()
                     .filter(|(id, _)| id.matches(pkg))
+                    .collect();
+
+                // Annotations are applied from the least to the most specific key
+                matching.sort_by(|(lhs, _), (rhs, _)| lhs.precedence().cmp(&rhs.precedence()));
+
+                let extras: Vec<CrateAnnotations> = matching
+                    .into_iter()
                     .map(|(id, extra)| {
                         // Mark that an annotation has been consumed
                         unused_extra_annotations.remove(id);
@@ -392,25 +399,65 @@ impl Annotations {
                     .cloned()
                     .collect();
 
-                if !extras.is_empty() {
-                    Some((
+                let crate_extra = if extras.is_empty() {
+                    None
+                } else {
+                    Some(extras.into_iter().sum::<CrateAnnotations>())
+                };
+
+                // Default annotations apply to all third party crates beneath any
+                // crate specific annotations unless the crate opts out of them.
+                let default_extra = config.default_annotations.as_ref().filter(|_| {
+                    lockfile_annotation.crates.contains_key(pkg_id)
+                        && crate_extra
+                            .as_ref()
+                            .and_then(|extra| extra.skip_default_annotations)
+                            != Some(true)
+                });
+
+                let crate_extra = match (default_extra, crate_extra) {
+                    (Some(default_extra), Some(crate_extra)) => {
+                        Some(default_extra.clone() + crate_extra)
+                    }
+                    (Some(default_extra), None) => Some(default_extra.clone()),
+                    (None, crate_extra) => crate_extra,
+                };
+
+                crate_extra.map(|crate_extra| {
+                    (
                         CrateId::new(pkg.name.clone(), pkg.version.to_string()),
                         PairredExtras {
                             package_id: pkg_id.clone(),
-                            crate_extra: extras.into_iter().sum(),
+                            crate_extra,
                         },
-                    ))
-                } else {
-                    None
-                }
+                    )
+                })
             })
             .collect();
 
-        // Alert on any unused annotations
-        if !unused_extra_annotations.is_empty() {
+        // Alert on any unused annotations. Only annotations for a specific crate from this
+        // workspace's configs are errors. Patterns are expected to match nothing as dependencies
+        // change and shared annotations are not specific to any one workspace.
+        let (unused_exact, unused_patterns): (Vec<&CrateId>, Vec<&CrateId>) =
+            unused_extra_annotations.keys().partition(|id| {
+                id.name_pattern() == NamePattern::Exact && !config.shared_annotations.contains(id)
+            });
+        let format_ids = |ids: Vec<&CrateId>| {
+            ids.iter()
+                .map(|id| format!("'{}'", id))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        if !unused_patterns.is_empty() {
+            eprintln!(
+                "Warning: Annotations did not match any crates: {}",
+                format_ids(unused_patterns)
+            );
+        }
+        if !unused_exact.is_empty() {
             bail!(
-                "Unused annotations were provided. Please remove them: {:?}",
-                unused_extra_annotations.keys()
+                "Unused annotations were provided. Please remove them: {}. Run the `validate` subcommand for a full report of problems with the config.",
+                format_ids(unused_exact)
             );
         }
 
@@ -552,4 +599,81 @@ mod test {
         assert!(result_str.contains("Unused annotations were provided. Please remove them"));
         assert!(result_str.contains("mock-crate"));
     }
+
+    #[test]
+    fn unused_annotation_patterns_are_allowed() {
+        let mut config = Config::default();
+        for name in ["mock-*", "/^mock-crate$/", "*"] {
+            config.annotations.insert(
+                CrateId::new(name.to_owned(), "9.9.9".to_owned()),
+                CrateAnnotations::default(),
+            );
+        }
+
+        assert!(
+            Annotations::new(test::metadata::no_deps(), test::lockfile::no_deps(), config).is_ok()
+        );
+    }
+
+    #[test]
+    fn unused_shared_annotations_are_allowed() {
+        let mut config = Config::default();
+        let id = CrateId::new("mock-crate".to_owned(), "0.1.0".to_owned());
+        config
+            .annotations
+            .insert(id.clone(), CrateAnnotations::default());
+        config.shared_annotations.insert(id);
+
+        assert!(
+            Annotations::new(test::metadata::no_deps(), test::lockfile::no_deps(), config).is_ok()
+        );
+    }
+
+    #[test]
+    fn default_annotations() {
+        let mut config = Config {
+            default_annotations: Some(CrateAnnotations {
+                rustc_flags: Some(vec!["--cap-lints=allow".to_owned()]),
+                ..CrateAnnotations::default()
+            }),
+            ..Config::default()
+        };
+        config.annotations.insert(
+            CrateId::new("log".to_owned(), "0.4.14".to_owned()),
+            CrateAnnotations {
+                rustc_flags: Some(vec!["--cfg=log".to_owned()]),
+                ..CrateAnnotations::default()
+            },
+        );
+        config.annotations.insert(
+            CrateId::new("log".to_owned(), "0.3.9".to_owned()),
+            CrateAnnotations {
+                skip_default_annotations: Some(true),
+                ..CrateAnnotations::default()
+            },
+        );
+
+        let annotations =
+            Annotations::new(test::metadata::alias(), test::lockfile::alias(), config).unwrap();
+        let rustc_flags = |name: &str, version: &str| {
+            annotations
+                .pairred_extras
+                .get(&CrateId::new(name.to_owned(), version.to_owned()))
+                .and_then(|extra| extra.crate_extra.rustc_flags.clone())
+        };
+
+        // Defaults apply beneath crate specific annotations
+        assert_eq!(
+            rustc_flags("log", "0.4.14"),
+            Some(vec!["--cap-lints=allow".to_owned(), "--cfg=log".to_owned()])
+        );
+        assert_eq!(
+            rustc_flags("libc", "0.2.117"),
+            Some(vec!["--cap-lints=allow".to_owned()])
+        );
+
+        // Crates may opt out of defaults and workspace members never receive them
+        assert_eq!(rustc_flags("log", "0.3.9"), None);
+        assert_eq!(rustc_flags("aliases", "0.1.0"), None);
+    }
 }
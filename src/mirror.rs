@@ -0,0 +1,721 @@
+//! Tools for exporting pinned crates into a Cargo
+//! [local registry](https://doc.rust-lang.org/cargo/reference/source-replacement.html#local-registry-sources)
+//! or a Bazel repository cache
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use sha2::{Digest as Sha2Digest, Sha256};
+
+use crate::config::CrateId;
+use crate::context::Context;
+use crate::metadata::SourceAnnotation;
+
+/// The name of the source the local registry is registered as in a Cargo config
+const MIRROR_SOURCE_NAME: &str = "cargo-bazel-mirror";
+
+/// A dependency of a crate in a registry index
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct IndexDependency {
+    name: String,
+    req: String,
+    features: Vec<String>,
+    optional: bool,
+    default_features: bool,
+    target: Option<String>,
+    kind: String,
+    registry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+}
+
+/// A single version of a crate in a registry index. See
+/// [Index format](https://doc.rust-lang.org/cargo/reference/registry-index.html#json-schema)
+#[derive(Debug, Serialize)]
+struct IndexEntry {
+    name: String,
+    vers: String,
+    deps: Vec<IndexDependency>,
+    cksum: String,
+    features: BTreeMap<String, Vec<String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    features2: BTreeMap<String, Vec<String>>,
+    yanked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    v: Option<u32>,
+}
+
+impl IndexEntry {
+    /// Generate an index entry from the contents of a packaged crate's `Cargo.toml` file
+    fn from_manifest(manifest: &str, cksum: &str) -> Result<Self> {
+        let manifest: toml::Value = toml::from_str(manifest)?;
+        let package = manifest
+            .get("package")
+            .context("The manifest has no [package] table")?;
+        let field = |name: &str| -> Result<String> {
+            package
+                .get(name)
+                .and_then(toml::Value::as_str)
+                .map(str::to_owned)
+                .with_context(|| format!("The manifest has no `package.{}` field", name))
+        };
+
+        let mut deps = Vec::new();
+        deps.extend(parse_dependencies(&manifest, None)?);
+        if let Some(targets) = manifest.get("target").and_then(toml::Value::as_table) {
+            for (target, table) in targets {
+                deps.extend(parse_dependencies(table, Some(target))?);
+            }
+        }
+        deps.sort();
+
+        // Features using the `dep:` or `?/` syntax must be in `features2`
+        let mut features = BTreeMap::new();
+        let mut features2 = BTreeMap::new();
+        if let Some(table) = manifest.get("features").and_then(toml::Value::as_table) {
+            for (feature, values) in table {
+                let values: Vec<String> = values
+                    .as_array()
+                    .map(|values| {
+                        values
+                            .iter()
+                            .filter_map(toml::Value::as_str)
+                            .map(str::to_owned)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                match values
+                    .iter()
+                    .any(|v| v.starts_with("dep:") || v.contains("?/"))
+                {
+                    true => features2.insert(feature.clone(), values),
+                    false => features.insert(feature.clone(), values),
+                };
+            }
+        }
+
+        Ok(Self {
+            name: field("name")?,
+            vers: field("version")?,
+            deps,
+            cksum: cksum.to_owned(),
+            v: match features2.is_empty() {
+                true => None,
+                false => Some(2),
+            },
+            features,
+            features2,
+            yanked: false,
+            links: field("links").ok(),
+        })
+    }
+}
+
+/// Parse the dependency tables of a manifest, or of a `[target.'cfg(..)']` table within one
+fn parse_dependencies(table: &toml::Value, target: Option<&str>) -> Result<Vec<IndexDependency>> {
+    let mut deps = Vec::new();
+    for (section, kind) in [
+        ("dependencies", "normal"),
+        ("dev-dependencies", "dev"),
+        ("build-dependencies", "build"),
+    ] {
+        let section = match table.get(section).and_then(toml::Value::as_table) {
+            Some(section) => section,
+            None => continue,
+        };
+
+        for (name, spec) in section {
+            let dep = match spec {
+                toml::Value::String(req) => IndexDependency {
+                    name: name.clone(),
+                    req: req.clone(),
+                    features: Vec::new(),
+                    optional: false,
+                    default_features: true,
+                    target: target.map(str::to_owned),
+                    kind: kind.to_owned(),
+                    registry: None,
+                    package: None,
+                },
+                toml::Value::Table(spec) => {
+                    let flag = |names: &[&str], default: bool| {
+                        names
+                            .iter()
+                            .find_map(|name| spec.get(*name).and_then(toml::Value::as_bool))
+                            .unwrap_or(default)
+                    };
+                    IndexDependency {
+                        name: name.clone(),
+                        req: spec
+                            .get("version")
+                            .and_then(toml::Value::as_str)
+                            .unwrap_or("*")
+                            .to_owned(),
+                        features: spec
+                            .get("features")
+                            .and_then(toml::Value::as_array)
+                            .map(|features| {
+                                features
+                                    .iter()
+                                    .filter_map(toml::Value::as_str)
+                                    .map(str::to_owned)
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                        optional: flag(&["optional"], false),
+                        default_features: flag(&["default-features", "default_features"], true),
+                        target: target.map(str::to_owned),
+                        kind: kind.to_owned(),
+                        registry: spec
+                            .get("registry-index")
+                            .and_then(toml::Value::as_str)
+                            .map(str::to_owned),
+                        package: spec
+                            .get("package")
+                            .and_then(toml::Value::as_str)
+                            .map(str::to_owned),
+                    }
+                }
+                _ => bail!("Unexpected dependency specification for '{}'", name),
+            };
+            deps.push(dep);
+        }
+    }
+
+    Ok(deps)
+}
+
+/// The path of a crate's file within a registry index
+pub fn index_path(name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => PathBuf::from("1").join(&name),
+        2 => PathBuf::from("2").join(&name),
+        3 => PathBuf::from("3").join(&name[..1]).join(&name),
+        _ => PathBuf::from(&name[..2]).join(&name[2..4]).join(&name),
+    }
+}
+
+/// Read the `Cargo.toml` file from a packaged (`.crate`) crate
+fn read_packaged_manifest(crate_file: &Path, id: &CrateId) -> Result<String> {
+    let file = fs::File::open(crate_file)
+        .with_context(|| format!("Failed to open {}", crate_file.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+    let manifest_path = PathBuf::from(format!("{}-{}", id.name, id.version)).join("Cargo.toml");
+    let entries = archive
+        .entries()
+        .with_context(|| format!("Failed to read archive: {}", crate_file.display()))?;
+    for entry in entries {
+        let mut entry =
+            entry.with_context(|| format!("Failed to read archive: {}", crate_file.display()))?;
+        if entry.path()? != manifest_path {
+            continue;
+        }
+
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .context("The packaged Cargo.toml is not valid utf-8")?;
+        return Ok(content);
+    }
+
+    bail!(
+        "No {} was found in {}",
+        manifest_path.display(),
+        crate_file.display()
+    )
+}
+
+/// Recursively locate all `.crate` files in a directory
+fn find_crate_files(dir: &Path, files: &mut BTreeMap<String, BTreeSet<PathBuf>>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_crate_files(&path, files)?;
+        } else if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
+            if file_name.ends_with(".crate") {
+                files
+                    .entry(file_name.to_owned())
+                    .or_default()
+                    .insert(path.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let content =
+        fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    Ok(hex::encode(Sha256::digest(&content)))
+}
+
+/// The outcome of mirroring crates into a local registry or Bazel repository cache
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    /// Crates which were added to the registry or cache
+    pub mirrored: BTreeSet<CrateId>,
+
+    /// Crates with no `.crate` file in the source directory
+    pub missing: BTreeSet<CrateId>,
+
+    /// Crates whose `.crate` files do not match the sha256 in the lockfile
+    pub mismatched: BTreeSet<CrateId>,
+
+    /// Crates which could not be mirrored and the reason why (Eg. git repositories)
+    pub skipped: BTreeMap<CrateId, &'static str>,
+}
+
+impl MirrorReport {
+    /// Returns true if any pinned crate could not be mirrored
+    pub fn is_incomplete(&self) -> bool {
+        !self.missing.is_empty() || !self.mismatched.is_empty()
+    }
+
+    /// The number of crates which could not be mirrored
+    pub fn failures(&self) -> usize {
+        self.missing.len() + self.mismatched.len()
+    }
+}
+
+impl fmt::Display for MirrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Mirrored {} crates", self.mirrored.len())?;
+        for (id, reason) in self.skipped.iter() {
+            writeln!(f, "Skipped ({}): {}", reason, id)?;
+        }
+        for id in self.missing.iter() {
+            writeln!(f, "Missing: {}", id)?;
+        }
+        for id in self.mismatched.iter() {
+            writeln!(f, "Checksum mismatch: {}", id)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `.crate` file matching a crate pinned in a lockfile
+struct PinnedArchive<'a> {
+    id: &'a CrateId,
+    path: PathBuf,
+    sha256: String,
+}
+
+/// Locate the `.crate` files in `crates_dir` of all pinned registry crates in `context`.
+/// Crates which cannot be located are recorded in `report`. If `require_sha256` is set,
+/// crates with no sha256 in the lockfile are skipped.
+fn locate_archives<'a>(
+    context: &'a Context,
+    crates_dir: &Path,
+    require_sha256: bool,
+    report: &mut MirrorReport,
+) -> Result<Vec<PinnedArchive<'a>>> {
+    let mut crate_files = BTreeMap::new();
+    find_crate_files(crates_dir, &mut crate_files)?;
+
+    let mut archives = Vec::new();
+    for (id, ctx) in context.crates.iter() {
+        if context.workspace_members.contains_key(id) {
+            continue;
+        }
+
+        let expected_sha256 = match &ctx.repository {
+            Some(SourceAnnotation::Http { sha256, .. }) => sha256,
+            _ => {
+                report.skipped.insert(id.clone(), "not from a registry");
+                continue;
+            }
+        };
+
+        if require_sha256 && expected_sha256.is_none() {
+            report
+                .skipped
+                .insert(id.clone(), "no sha256 in the lockfile");
+            continue;
+        }
+
+        let file_name = format!("{}-{}.crate", id.name, id.version);
+        let candidates = match crate_files.get(&file_name) {
+            Some(candidates) => candidates,
+            None => {
+                report.missing.insert(id.clone());
+                continue;
+            }
+        };
+
+        // Multiple registries may contain a crate of the same name and version
+        let mut matched = None;
+        for candidate in candidates {
+            let sha256 = sha256_file(candidate)?;
+            if expected_sha256.is_none() || expected_sha256.as_ref() == Some(&sha256) {
+                matched = Some((candidate, sha256));
+                break;
+            }
+        }
+        match matched {
+            Some((path, sha256)) => archives.push(PinnedArchive {
+                id,
+                path: path.clone(),
+                sha256,
+            }),
+            None => {
+                report.mismatched.insert(id.clone());
+            }
+        }
+    }
+
+    Ok(archives)
+}
+
+/// Copy the `.crate` files of all pinned registry crates in `context` from `crates_dir`
+/// into a local registry at `registry_dir` and generate its index.
+pub fn mirror_crates(
+    context: &Context,
+    crates_dir: &Path,
+    registry_dir: &Path,
+) -> Result<MirrorReport> {
+    let mut report = MirrorReport::default();
+    let mut index: BTreeMap<PathBuf, Vec<IndexEntry>> = BTreeMap::new();
+
+    for archive in locate_archives(context, crates_dir, false, &mut report)? {
+        let manifest = read_packaged_manifest(&archive.path, archive.id)?;
+        let entry = IndexEntry::from_manifest(&manifest, &archive.sha256)
+            .with_context(|| format!("Failed to generate an index entry for {}", archive.id))?;
+
+        let file_name = format!("{}-{}.crate", archive.id.name, archive.id.version);
+        fs::create_dir_all(registry_dir)?;
+        fs::copy(&archive.path, registry_dir.join(&file_name))
+            .with_context(|| format!("Failed to copy {}", archive.path.display()))?;
+
+        index
+            .entry(index_path(&archive.id.name))
+            .or_default()
+            .push(entry);
+        report.mirrored.insert(archive.id.clone());
+    }
+
+    for (path, entries) in index {
+        let path = registry_dir.join("index").join(path);
+        fs::create_dir_all(
+            path.parent()
+                .expect("Index files are always in a directory"),
+        )?;
+
+        let content = entries
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<String>, _>>()?
+            .join("\n")
+            + "\n";
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write index file: {}", path.display()))?;
+    }
+
+    Ok(report)
+}
+
+/// The path of an archive relative to the root of a Bazel
+/// [repository cache](https://bazel.build/run/build#repository-cache)
+fn repository_cache_path(sha256: &str) -> PathBuf {
+    PathBuf::from("content_addressable")
+        .join("sha256")
+        .join(sha256)
+        .join("file")
+}
+
+/// Copy the `.crate` files of all pinned registry crates in `context` from `crates_dir`
+/// into the repository cache at `cache_dir` so Bazel can fetch the `http_archive`
+/// repositories for them offline.
+pub fn populate_cache(
+    context: &Context,
+    crates_dir: &Path,
+    cache_dir: &Path,
+) -> Result<MirrorReport> {
+    let mut report = MirrorReport::default();
+
+    for archive in locate_archives(context, crates_dir, true, &mut report)? {
+        let path = cache_dir.join(repository_cache_path(&archive.sha256));
+        fs::create_dir_all(
+            path.parent()
+                .expect("Cache files are always in a directory"),
+        )?;
+        fs::copy(&archive.path, &path)
+            .with_context(|| format!("Failed to copy {}", archive.path.display()))?;
+
+        report.mirrored.insert(archive.id.clone());
+    }
+
+    Ok(report)
+}
+
+/// Render a Cargo config which replaces crates.io with the local registry at `registry_dir`
+pub fn render_cargo_config(registry_dir: &Path) -> String {
+    format!(
+        "[source.crates-io]\nreplace-with = \"{name}\"\n\n[source.{name}]\nlocal-registry = {path:?}\n",
+        name = MIRROR_SOURCE_NAME,
+        path = registry_dir.display().to_string(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), PathBuf::from("1/a"));
+        assert_eq!(index_path("cc"), PathBuf::from("2/cc"));
+        assert_eq!(index_path("syn"), PathBuf::from("3/s/syn"));
+        assert_eq!(index_path("Inflector"), PathBuf::from("in/fl/inflector"));
+    }
+
+    #[test]
+    fn index_entry() {
+        let manifest = r#"
+            [package]
+            name = "openssl-sys"
+            version = "0.9.72"
+            links = "openssl"
+
+            [dependencies]
+            libc = "0.2"
+            ffi = { package = "libfoo-sys", version = "1.0", optional = true, default-features = false, features = ["a"] }
+
+            [build-dependencies]
+            cc = "1.0"
+
+            [target.'cfg(target_env = "msvc")'.build-dependencies]
+            vcpkg = "0.2.8"
+
+            [features]
+            default = []
+            vendored = ["dep:ffi"]
+        "#;
+
+        let entry = IndexEntry::from_manifest(manifest, "abc123").unwrap();
+        let json = serde_json::to_value(&entry).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "name": "openssl-sys",
+                "vers": "0.9.72",
+                "deps": [
+                    {
+                        "name": "cc",
+                        "req": "1.0",
+                        "features": [],
+                        "optional": false,
+                        "default_features": true,
+                        "target": null,
+                        "kind": "build",
+                        "registry": null,
+                    },
+                    {
+                        "name": "ffi",
+                        "req": "1.0",
+                        "features": ["a"],
+                        "optional": true,
+                        "default_features": false,
+                        "target": null,
+                        "kind": "normal",
+                        "registry": null,
+                        "package": "libfoo-sys",
+                    },
+                    {
+                        "name": "libc",
+                        "req": "0.2",
+                        "features": [],
+                        "optional": false,
+                        "default_features": true,
+                        "target": null,
+                        "kind": "normal",
+                        "registry": null,
+                    },
+                    {
+                        "name": "vcpkg",
+                        "req": "0.2.8",
+                        "features": [],
+                        "optional": false,
+                        "default_features": true,
+                        "target": "cfg(target_env = \"msvc\")",
+                        "kind": "build",
+                        "registry": null,
+                    },
+                ],
+                "cksum": "abc123",
+                "features": {"default": []},
+                "features2": {"vendored": ["dep:ffi"]},
+                "yanked": false,
+                "links": "openssl",
+                "v": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn populate_repository_cache() {
+        let annotations = crate::metadata::Annotations::new(
+            crate::test::metadata::alias(),
+            crate::test::lockfile::alias(),
+            crate::config::Config::default(),
+        )
+        .unwrap();
+        let mut context = Context::new(annotations).unwrap();
+
+        let ids: Vec<CrateId> = context
+            .crates
+            .iter()
+            .filter(|(_, ctx)| matches!(ctx.repository, Some(SourceAnnotation::Http { .. })))
+            .map(|(id, _)| id.clone())
+            .collect();
+        assert!(ids.len() > 2);
+
+        let crates_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+
+        // The first crate has a matching archive and the second does not
+        let content = b"mock crate";
+        let sha256 = hex::encode(Sha256::digest(content));
+        if let Some(SourceAnnotation::Http {
+            sha256: expected, ..
+        }) = &mut context.crates.get_mut(&ids[0]).unwrap().repository
+        {
+            *expected = Some(sha256.clone());
+        }
+        for (id, content) in [(&ids[0], &content[..]), (&ids[1], b"mismatched")] {
+            fs::write(
+                crates_dir
+                    .as_ref()
+                    .join(format!("{}-{}.crate", id.name, id.version)),
+                content,
+            )
+            .unwrap();
+        }
+
+        let report = populate_cache(&context, crates_dir.as_ref(), cache_dir.as_ref()).unwrap();
+
+        assert_eq!(report.mirrored, BTreeSet::from([ids[0].clone()]));
+        assert_eq!(report.mismatched, BTreeSet::from([ids[1].clone()]));
+        assert_eq!(report.missing.len(), ids.len() - 2);
+        assert!(!report.skipped.is_empty());
+        assert!(report.is_incomplete());
+
+        let cached = cache_dir
+            .as_ref()
+            .join("content_addressable/sha256")
+            .join(&sha256)
+            .join("file");
+        assert_eq!(fs::read(cached).unwrap(), content);
+    }
+
+    #[test]
+    fn mirror_local_registry() {
+        let annotations = crate::metadata::Annotations::new(
+            crate::test::metadata::alias(),
+            crate::test::lockfile::alias(),
+            crate::config::Config::default(),
+        )
+        .unwrap();
+        let mut context = Context::new(annotations).unwrap();
+
+        let id = context
+            .crates
+            .iter()
+            .find(|(_, ctx)| matches!(ctx.repository, Some(SourceAnnotation::Http { .. })))
+            .map(|(id, _)| id.clone())
+            .unwrap();
+
+        // Package a minimal crate the way `cargo package` does
+        let manifest = format!(
+            "[package]\nname = \"{}\"\nversion = \"{}\"\n\n[dependencies]\nlibc = \"0.2\"\n",
+            id.name, id.version
+        );
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (path, content) in [("src/lib.rs", ""), ("Cargo.toml", manifest.as_str())] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(
+                    &mut header,
+                    format!("{}-{}/{}", id.name, id.version, path),
+                    content.as_bytes(),
+                )
+                .unwrap();
+        }
+        let content = builder.into_inner().unwrap().finish().unwrap();
+
+        let sha256 = hex::encode(Sha256::digest(&content));
+        if let Some(SourceAnnotation::Http {
+            sha256: expected, ..
+        }) = &mut context.crates.get_mut(&id).unwrap().repository
+        {
+            *expected = Some(sha256.clone());
+        }
+
+        let crates_dir = tempfile::tempdir().unwrap();
+        let registry_dir = tempfile::tempdir().unwrap();
+        let file_name = format!("{}-{}.crate", id.name, id.version);
+        fs::write(crates_dir.as_ref().join(&file_name), &content).unwrap();
+
+        let report = mirror_crates(&context, crates_dir.as_ref(), registry_dir.as_ref()).unwrap();
+        assert_eq!(report.mirrored, BTreeSet::from([id.clone()]));
+
+        // The archive is copied to the root of the registry
+        assert_eq!(
+            fs::read(registry_dir.as_ref().join(&file_name)).unwrap(),
+            content
+        );
+
+        // And an index entry is generated from its manifest
+        let index = fs::read_to_string(
+            registry_dir
+                .as_ref()
+                .join("index")
+                .join(index_path(&id.name)),
+        )
+        .unwrap();
+        let entries: Vec<serde_json::Value> = index
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["name"], id.name.as_str());
+        assert_eq!(entries[0]["vers"], id.version.as_str());
+        assert_eq!(entries[0]["cksum"], sha256.as_str());
+        assert_eq!(entries[0]["deps"][0]["name"], "libc");
+    }
+
+    #[test]
+    fn cache_paths() {
+        assert_eq!(
+            repository_cache_path("abc123"),
+            PathBuf::from("content_addressable/sha256/abc123/file")
+        );
+    }
+
+    #[test]
+    fn cargo_config() {
+        assert_eq!(
+            render_cargo_config(Path::new("/tmp/registry")),
+            "[source.crates-io]\nreplace-with = \"cargo-bazel-mirror\"\n\n[source.cargo-bazel-mirror]\nlocal-registry = \"/tmp/registry\"\n"
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Tools for migrating [cargo-raze](https://github.com/google/cargo-raze) settings to a [Config]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, CrateAnnotations, CrateId, RenderConfig, VendorMode};
+
+/// The repository name used when the raze settings do not provide a `gen_workspace_prefix`
+const DEFAULT_REPOSITORY_NAME: &str = "crate_index";
+
+/// Workspace wide cargo-raze settings from `[workspace.metadata.raze]` or `[package.metadata.raze]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RazeSettings {
+    workspace_path: Option<String>,
+    genmode: Option<String>,
+    gen_workspace_prefix: Option<String>,
+    targets: Option<Vec<String>>,
+    default_gen_buildrs: Option<bool>,
+    crates: BTreeMap<String, BTreeMap<String, RazeCrateSettings>>,
+
+    /// Settings which have no equivalent in cargo-bazel
+    #[serde(flatten)]
+    unsupported: BTreeMap<String, toml::Value>,
+}
+
+/// Per crate cargo-raze settings from `[*.metadata.raze.crates.<name>.<version>]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RazeCrateSettings {
+    gen_buildrs: Option<bool>,
+    additional_deps: Vec<String>,
+    additional_flags: Vec<String>,
+    additional_env: BTreeMap<String, String>,
+    buildrs_additional_deps: Vec<String>,
+    buildrs_additional_environment_variables: BTreeMap<String, String>,
+    data_attr: Option<String>,
+    compile_data_attr: Option<String>,
+    patches: Vec<String>,
+    patch_args: Vec<String>,
+    patch_tool: Option<String>,
+
+    /// Settings which have no equivalent in cargo-bazel
+    #[serde(flatten)]
+    unsupported: BTreeMap<String, toml::Value>,
+}
+
+/// A cargo-raze setting which could not be translated
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct UnsupportedSetting {
+    /// The manifest the setting was found in
+    pub manifest: PathBuf,
+
+    /// The path of the setting within the manifest. Eg. `package.metadata.raze.crates.openssl-sys.0.9.72.skipped_deps`
+    pub key: String,
+
+    /// The value of the setting
+    pub value: String,
+}
+
+impl fmt::Display for UnsupportedSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: `{} = {}` has no equivalent and was not migrated",
+            self.manifest.display(),
+            self.key,
+            self.value
+        )
+    }
+}
+
+/// The result of translating cargo-raze settings into a [Config]
+#[derive(Debug)]
+pub struct RazeMigration {
+    /// The translated config
+    pub config: Config,
+
+    /// Settings which could not be translated
+    pub unsupported: BTreeSet<UnsupportedSetting>,
+}
+
+impl Default for RazeMigration {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                rendering: default_render_config(DEFAULT_REPOSITORY_NAME),
+                ..Config::default()
+            },
+            unsupported: BTreeSet::new(),
+        }
+    }
+}
+
+impl RazeMigration {
+    /// Translate the raze settings from a collection of Cargo manifests
+    pub fn from_manifests(manifests: &[PathBuf]) -> Result<Self> {
+        let mut migration = Self::default();
+        for manifest in manifests.iter() {
+            let content = fs::read_to_string(manifest)
+                .with_context(|| format!("Failed to read manifest: {}", manifest.display()))?;
+            migration
+                .add_manifest(manifest, &content)
+                .with_context(|| format!("Failed to migrate manifest: {}", manifest.display()))?;
+        }
+
+        Ok(migration)
+    }
+
+    /// Translate the raze settings of a single Cargo manifest
+    fn add_manifest(&mut self, manifest: &Path, content: &str) -> Result<()> {
+        let value: toml::Value = toml::from_str(content)?;
+
+        for section in ["workspace", "package"] {
+            let raze = value
+                .get(section)
+                .and_then(|section| section.get("metadata"))
+                .and_then(|metadata| metadata.get("raze"));
+
+            if let Some(raze) = raze {
+                let settings: RazeSettings = raze
+                    .clone()
+                    .try_into()
+                    .with_context(|| format!("Failed to parse `{}.metadata.raze`", section))?;
+                self.add_settings(manifest, &format!("{}.metadata.raze", section), settings);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record a setting which could not be translated
+    fn report_unsupported(&mut self, manifest: &Path, key: String, value: String) {
+        self.unsupported.insert(UnsupportedSetting {
+            manifest: manifest.to_path_buf(),
+            key,
+            value,
+        });
+    }
+
+    fn add_settings(&mut self, manifest: &Path, prefix: &str, settings: RazeSettings) {
+        if let Some(name) = settings.gen_workspace_prefix {
+            self.config.rendering.repository_name = name;
+        }
+
+        if let Some(workspace_path) = settings.workspace_path {
+            self.config.rendering.crates_module_template = format!("{}:{{file}}", workspace_path);
+        }
+
+        match settings.genmode.as_deref() {
+            Some("Vendored") => self.config.rendering.vendor_mode = Some(VendorMode::Local),
+            Some("Remote") => self.config.rendering.vendor_mode = Some(VendorMode::Remote),
+            Some(other) => self.report_unsupported(
+                manifest,
+                format!("{}.genmode", prefix),
+                format!("\"{}\"", other),
+            ),
+            None => {}
+        }
+
+        if let Some(targets) = settings.targets {
+            self.config.supported_platform_triples.extend(targets);
+        }
+
+        if let Some(gen_buildrs) = settings.default_gen_buildrs {
+            self.config.generate_build_scripts = gen_buildrs;
+        }
+
+        for (key, value) in settings.unsupported {
+            self.report_unsupported(manifest, format!("{}.{}", prefix, key), value.to_string());
+        }
+
+        for (name, versions) in settings.crates {
+            for (version, crate_settings) in versions {
+                let prefix = format!("{}.crates.{}.{}", prefix, name, version);
+                let annotation = self.translate_crate_settings(manifest, &prefix, crate_settings);
+                let crate_id = CrateId::new(name.clone(), version);
+
+                let annotation = match self.config.annotations.remove(&crate_id) {
+                    Some(existing) => existing + annotation,
+                    None => annotation,
+                };
+                self.config.annotations.insert(crate_id, annotation);
+            }
+        }
+    }
+
+    fn translate_crate_settings(
+        &mut self,
+        manifest: &Path,
+        prefix: &str,
+        settings: RazeCrateSettings,
+    ) -> CrateAnnotations {
+        let key = |name: &str| format!("{}.{}", prefix, name);
+
+        let non_empty_set = |values: Vec<String>| match values.is_empty() {
+            true => None,
+            false => Some(values.into_iter().collect::<BTreeSet<String>>()),
+        };
+        let non_empty_vec = |values: Vec<String>| match values.is_empty() {
+            true => None,
+            false => Some(values),
+        };
+        let non_empty_map = |values: BTreeMap<String, String>| match values.is_empty() {
+            true => None,
+            false => Some(values),
+        };
+
+        let mut annotation = CrateAnnotations {
+            gen_build_script: settings.gen_buildrs,
+            deps: non_empty_set(settings.additional_deps),
+            rustc_flags: non_empty_vec(settings.additional_flags),
+            rustc_env: non_empty_map(settings.additional_env),
+            build_script_deps: non_empty_set(settings.buildrs_additional_deps),
+            build_script_env: non_empty_map(settings.buildrs_additional_environment_variables),
+            patches: non_empty_set(settings.patches),
+            patch_args: non_empty_vec(settings.patch_args),
+            patch_tool: settings.patch_tool,
+            ..CrateAnnotations::default()
+        };
+
+        if let Some(data_attr) = settings.data_attr {
+            match parse_data_attr(&data_attr) {
+                Some(DataAttr::Glob(globs)) => annotation.data_glob = Some(globs),
+                Some(DataAttr::Labels(labels)) => annotation.data = Some(labels),
+                None => {
+                    self.report_unsupported(manifest, key("data_attr"), format!("{:?}", data_attr))
+                }
+            }
+        }
+
+        if let Some(compile_data_attr) = settings.compile_data_attr {
+            match parse_data_attr(&compile_data_attr) {
+                Some(DataAttr::Glob(globs)) => annotation.compile_data_glob = Some(globs),
+                Some(DataAttr::Labels(labels)) => annotation.compile_data = Some(labels),
+                None => self.report_unsupported(
+                    manifest,
+                    key("compile_data_attr"),
+                    format!("{:?}", compile_data_attr),
+                ),
+            }
+        }
+
+        for (name, value) in settings.unsupported {
+            self.report_unsupported(manifest, key(&name), value.to_string());
+        }
+
+        annotation
+    }
+}
+
+/// Build a [RenderConfig] using the same defaults as a deserialized one
+fn default_render_config(repository_name: &str) -> RenderConfig {
+    serde_json::from_value(serde_json::json!({
+        "repository_name": repository_name,
+    }))
+    .expect("A repository name is the only required field of a RenderConfig")
+}
+
+/// The interpretation of a raze `data_attr` or `compile_data_attr` Starlark expression
+#[derive(Debug, PartialEq, Eq)]
+enum DataAttr {
+    Glob(BTreeSet<String>),
+    Labels(BTreeSet<String>),
+}
+
+/// Parse the simple forms of a raze data expression: `glob(["pattern", ...])` or `["label", ...]`.
+/// More complicated expressions (Eg. with `exclude` or concatenation) are not supported.
+fn parse_data_attr(expr: &str) -> Option<DataAttr> {
+    let expr = expr.trim();
+    match expr.strip_prefix("glob(").and_then(|e| e.strip_suffix(')')) {
+        Some(inner) => parse_string_list(inner).map(DataAttr::Glob),
+        None => parse_string_list(expr).map(DataAttr::Labels),
+    }
+}
+
+/// Parse a Starlark list of string literals
+fn parse_string_list(expr: &str) -> Option<BTreeSet<String>> {
+    let inner = expr.trim().strip_prefix('[')?.strip_suffix(']')?;
+    inner
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.strip_prefix('"')
+                .and_then(|i| i.strip_suffix('"'))
+                .or_else(|| item.strip_prefix('\'').and_then(|i| i.strip_suffix('\'')))
+                .filter(|i| !i.contains(['"', '\'']))
+                .map(str::to_owned)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn data_attrs() {
+        assert_eq!(
+            parse_data_attr("glob([\"**/*.txt\", 'data/**'])"),
+            Some(DataAttr::Glob(BTreeSet::from([
+                "**/*.txt".to_owned(),
+                "data/**".to_owned()
+            ])))
+        );
+        assert_eq!(
+            parse_data_attr("[\"@foo//:bar\",]"),
+            Some(DataAttr::Labels(BTreeSet::from(["@foo//:bar".to_owned()])))
+        );
+        assert_eq!(
+            parse_data_attr("glob([\"**\"], exclude = [\"tests/**\"])"),
+            None
+        );
+        assert_eq!(parse_data_attr("[\"a\"] + [\"b\"]"), None);
+        assert_eq!(parse_data_attr("select({})"), None);
+    }
+
+    #[test]
+    fn migrate_manifest() {
+        let manifest = r#"
+            [package]
+            name = "raze_example"
+            version = "0.1.0"
+
+            [package.metadata.raze]
+            workspace_path = "//cargo"
+            genmode = "Remote"
+            gen_workspace_prefix = "raze"
+            targets = ["x86_64-unknown-linux-gnu"]
+            default_gen_buildrs = true
+            package_aliases_dir = "cargo"
+
+            [package.metadata.raze.crates.openssl-sys.'0.9.72']
+            gen_buildrs = false
+            additional_deps = ["@openssl//:ssl"]
+            additional_flags = ["--cfg=ossl111"]
+            buildrs_additional_environment_variables = { OPENSSL_NO_VENDOR = "1" }
+            data_attr = "glob([\"src/**\"])"
+            patches = ["//patches:openssl-sys.patch"]
+            patch_args = ["-p1"]
+            skipped_deps = ["vcpkg-0.2.15"]
+        "#;
+
+        let mut migration = RazeMigration::default();
+        migration
+            .add_manifest(Path::new("Cargo.toml"), manifest)
+            .unwrap();
+
+        let config = &migration.config;
+        assert_eq!(config.rendering.repository_name, "raze");
+        assert_eq!(config.rendering.crates_module_template, "//cargo:{file}");
+        assert!(matches!(
+            config.rendering.vendor_mode,
+            Some(VendorMode::Remote)
+        ));
+        assert!(config.generate_build_scripts);
+        assert_eq!(
+            config.supported_platform_triples,
+            BTreeSet::from(["x86_64-unknown-linux-gnu".to_owned()])
+        );
+
+        let annotation =
+            &config.annotations[&CrateId::new("openssl-sys".to_owned(), "0.9.72".to_owned())];
+        assert_eq!(annotation.gen_build_script, Some(false));
+        assert_eq!(
+            annotation.deps,
+            Some(BTreeSet::from(["@openssl//:ssl".to_owned()]))
+        );
+        assert_eq!(
+            annotation.rustc_flags,
+            Some(vec!["--cfg=ossl111".to_owned()])
+        );
+        assert_eq!(
+            annotation.build_script_env,
+            Some(BTreeMap::from([(
+                "OPENSSL_NO_VENDOR".to_owned(),
+                "1".to_owned()
+            )]))
+        );
+        assert_eq!(
+            annotation.data_glob,
+            Some(BTreeSet::from(["src/**".to_owned()]))
+        );
+        assert_eq!(
+            annotation.patches,
+            Some(BTreeSet::from(["//patches:openssl-sys.patch".to_owned()]))
+        );
+        assert_eq!(annotation.patch_args, Some(vec!["-p1".to_owned()]));
+
+        let unsupported: Vec<&str> = migration
+            .unsupported
+            .iter()
+            .map(|setting| setting.key.as_str())
+            .collect();
+        assert_eq!(
+            unsupported,
+            vec![
+                "package.metadata.raze.crates.openssl-sys.0.9.72.skipped_deps",
+                "package.metadata.raze.package_aliases_dir",
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_manifests() {
+        let workspace = r#"
+            [workspace]
+            members = ["a"]
+
+            [workspace.metadata.raze.crates.libc.'*']
+            additional_deps = ["//:a"]
+        "#;
+        let member = r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+
+            [package.metadata.raze.crates.libc.'*']
+            additional_deps = ["//:b"]
+        "#;
+
+        let mut migration = RazeMigration::default();
+        migration
+            .add_manifest(Path::new("Cargo.toml"), workspace)
+            .unwrap();
+        migration
+            .add_manifest(Path::new("a/Cargo.toml"), member)
+            .unwrap();
+
+        let annotation =
+            &migration.config.annotations[&CrateId::new("libc".to_owned(), "*".to_owned())];
+        assert_eq!(
+            annotation.deps,
+            Some(BTreeSet::from(["//:a".to_owned(), "//:b".to_owned()]))
+        );
+        assert!(migration.unsupported.is_empty());
+        assert_eq!(migration.config.rendering.repository_name, "crate_index");
+    }
+}
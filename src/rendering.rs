@@ -2,7 +2,8 @@
 
 mod template_engine;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -13,6 +14,7 @@ use crate::config::RenderConfig;
 use crate::context::Context;
 use crate::rendering::template_engine::TemplateEngine;
 use crate::splicing::default_splicing_package_crate_id;
+use crate::utils::diff::unified_diff;
 use crate::utils::starlark::Label;
 
 pub struct Renderer {
@@ -75,6 +77,8 @@ impl Renderer {
             .filter(|(id, _)| *id != &default_splicing_package_id)
             // Do not render local packages
             .filter(|(id, _)| !context.workspace_members.contains_key(id))
+            // Do not render crates replaced by existing targets
+            .filter(|(id, _)| context.crates[id].override_target.is_none())
             .map(|(id, content)| {
                 let ctx = &context.crates[id];
                 let label = match render_build_file_template(
@@ -152,6 +156,123 @@ pub fn write_outputs(
     Ok(())
 }
 
+/// A file on disk whose content differs from its rendered output
+#[derive(Debug, PartialEq, Eq)]
+pub struct StaleOutput {
+    /// The content of the file on disk
+    pub actual: String,
+
+    /// The rendered content of the file
+    pub expected: String,
+}
+
+/// The result of comparing rendered outputs against the files on disk
+#[derive(Debug, Default)]
+pub struct OutputCheck {
+    /// Files whose content differs from the rendered outputs. Diffs are only computed when
+    /// the check is displayed.
+    pub stale: BTreeMap<PathBuf, StaleOutput>,
+
+    /// Rendered outputs which do not exist on disk
+    pub missing: BTreeSet<PathBuf>,
+
+    /// Files or directories on disk which would not be produced
+    pub extra: BTreeSet<PathBuf>,
+}
+
+impl OutputCheck {
+    /// Compare the expected content of `path` (relative to `out_dir`) to the file on disk
+    pub fn compare(&mut self, out_dir: &Path, path: &Path, expected: &str) -> Result<()> {
+        let file = out_dir.join(path);
+        if !file.exists() {
+            self.missing.insert(path.to_path_buf());
+            return Ok(());
+        }
+
+        let actual = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read file: {}", file.display()))?;
+
+        if actual != expected {
+            self.stale.insert(
+                path.to_path_buf(),
+                StaleOutput {
+                    actual,
+                    expected: expected.to_owned(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if all outputs are up to date
+    pub fn is_empty(&self) -> bool {
+        self.stale.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+impl fmt::Display for OutputCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, stale) in self.stale.iter() {
+            let diff = unified_diff(
+                &stale.actual,
+                &stale.expected,
+                &format!("a/{}", path.display()),
+                &format!("b/{}", path.display()),
+            );
+            write!(f, "{}", diff.unwrap_or_default())?;
+        }
+        for path in self.missing.iter() {
+            writeln!(f, "Missing file: {}", path.display())?;
+        }
+        for path in self.extra.iter() {
+            writeln!(f, "Extra file: {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare a set of rendered outputs to the files in `out_dir` without modifying anything.
+pub fn check_outputs(outputs: &BTreeMap<PathBuf, String>, out_dir: &Path) -> Result<OutputCheck> {
+    let mut check = OutputCheck::default();
+    for (path, content) in outputs.iter() {
+        check.compare(out_dir, path, content)?;
+    }
+
+    Ok(check)
+}
+
+/// Locate entries in `dir` (a subdirectory of `out_dir`) which are not part of `outputs`.
+/// Directories which contain rendered outputs are not inspected further as they're expected
+/// to contain vendored sources. Other directories are reported as a whole.
+pub fn find_extra_outputs(
+    outputs: &BTreeMap<PathBuf, String>,
+    out_dir: &Path,
+    dir: &Path,
+) -> Result<BTreeSet<PathBuf>> {
+    let mut extra = BTreeSet::new();
+    let search_dir = out_dir.join(dir);
+    if !search_dir.exists() {
+        return Ok(extra);
+    }
+
+    let entries = fs::read_dir(&search_dir)
+        .with_context(|| format!("Failed to read directory: {}", search_dir.display()))?;
+    for entry in entries {
+        let path = dir.join(entry?.file_name());
+        let is_dir = out_dir.join(&path).is_dir();
+        let known = match is_dir {
+            true => outputs.keys().any(|output| output.starts_with(&path)),
+            false => outputs.contains_key(&path),
+        };
+        if !known {
+            extra.insert(path);
+        }
+    }
+
+    Ok(extra)
+}
+
 /// Render the Bazel label of a crate
 pub fn render_crate_bazel_label(
     template: &str,
@@ -192,6 +313,17 @@ pub fn render_module_label(template: &str, name: &str) -> Result<Label> {
     Label::from_str(&template.replace("{file}", name))
 }
 
+/// Render the repository-qualified label of the package containing the crates module. Crate
+/// targets are aliased from this package, so it must be able to see them.
+pub fn render_module_package_label(template: &str, repository_name: &str) -> Result<String> {
+    let label = render_module_label(template, "BUILD.bazel")?;
+    Ok(format!(
+        "@{}//{}:__pkg__",
+        label.repository.as_deref().unwrap_or(repository_name),
+        label.package.as_deref().unwrap_or_default(),
+    ))
+}
+
 /// Render the Bazel label of a platform triple
 pub fn render_platform_constraint_label(template: &str, triple: &str) -> String {
     template.replace("{triple}", triple)
@@ -209,11 +341,12 @@ fn render_build_file_template(template: &str, name: &str, version: &str) -> Resu
 mod test {
     use super::*;
 
-    use crate::config::{Config, CrateId, VendorMode};
-    use crate::context::crate_context::{CrateContext, Rule};
+    use crate::config::{Config, CrateAnnotations, CrateId, VendorMode};
+    use crate::context::crate_context::{CrateContext, CrateDependency, Rule};
     use crate::context::{BuildScriptAttributes, Context, TargetAttributes};
     use crate::metadata::Annotations;
     use crate::test;
+    use crate::utils::starlark::Glob;
 
     fn mock_render_config() -> RenderConfig {
         serde_json::from_value(serde_json::json!({
@@ -366,6 +499,256 @@ mod test {
         assert!(build_file_content.contains("# Hello World from additive section!"));
     }
 
+    #[test]
+    fn render_platform_specific_extras() {
+        let linux = "cfg(target_os = \"linux\")".to_owned();
+
+        let mut context = Context::default();
+        context.conditions.insert(
+            linux.clone(),
+            BTreeSet::from(["x86_64-unknown-linux-gnu".to_owned()]),
+        );
+
+        let mut crate_context = CrateContext {
+            name: "mock_crate".to_owned(),
+            version: "0.1.0".to_owned(),
+            targets: vec![
+                Rule::Library(mock_target_attributes()),
+                Rule::BuildScript(TargetAttributes {
+                    crate_name: "build_script_build".to_owned(),
+                    crate_root: Some("build.rs".to_owned()),
+                    ..TargetAttributes::default()
+                }),
+            ],
+            build_script_attrs: Some(BuildScriptAttributes::default()),
+            ..CrateContext::default()
+        };
+        let attrs = &mut crate_context.common_attrs;
+        attrs.extra_deps.insert("//:common".to_owned(), None);
+        attrs
+            .extra_deps
+            .insert("@openssl//:ssl".to_owned(), Some(linux.clone()));
+        attrs
+            .rustc_flags
+            .insert("-Clink-arg=-lrt".to_owned(), Some(linux.clone()));
+        crate_context
+            .build_script_attrs
+            .as_mut()
+            .unwrap()
+            .build_script_env
+            .insert(
+                BTreeMap::from([("OPENSSL_STATIC".to_owned(), "1".to_owned())]),
+                Some(linux),
+            );
+        context.crates.insert(
+            CrateId::new("mock_crate".to_owned(), "0.1.0".to_owned()),
+            crate_context,
+        );
+
+        let renderer = Renderer::new(mock_render_config());
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        let platform = "\"@rules_rust//rust/platform:x86_64-unknown-linux-gnu\",";
+        let library = build_file_content
+            .split("cargo_build_script(")
+            .next()
+            .unwrap();
+        let build_script = build_file_content
+            .split("cargo_build_script(")
+            .nth(1)
+            .unwrap();
+
+        // Common values are rendered once outside of any `select`
+        assert_eq!(library.matches("\"//:common\",").count(), 1);
+        assert!(library.contains(platform));
+        assert!(library.contains("\"@openssl//:ssl\","));
+        assert!(library.contains("\"-Clink-arg=-lrt\","));
+        assert!(build_script.contains(platform));
+        assert!(build_script.contains("\"OPENSSL_STATIC\": \"1\","));
+    }
+
+    #[test]
+    fn render_ordered_rustc_flags() {
+        let linux = "cfg(target_os = \"linux\")".to_owned();
+
+        let mut context = Context::default();
+        context.conditions.insert(
+            linux.clone(),
+            BTreeSet::from(["x86_64-unknown-linux-gnu".to_owned()]),
+        );
+
+        let mut crate_context = CrateContext {
+            name: "mock_crate".to_owned(),
+            version: "0.1.0".to_owned(),
+            targets: vec![Rule::Library(mock_target_attributes())],
+            ..CrateContext::default()
+        };
+        let flags = &mut crate_context.common_attrs.rustc_flags;
+        for flag in ["--cfg", "zeta", "-C", "link-arg=-Wl,foo", "--cfg", "alpha"] {
+            flags.insert(flag.to_owned(), None);
+        }
+        flags.insert("-Clink-arg=-lrt".to_owned(), Some(linux));
+        context.crates.insert(
+            CrateId::new("mock_crate".to_owned(), "0.1.0".to_owned()),
+            crate_context,
+        );
+
+        let renderer = Renderer::new(mock_render_config());
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // Flags are neither sorted nor deduplicated. Common flags are rendered as a plain list
+        // and platform flags use a select which does not deduplicate its values.
+        let flags = r#"
+    ] + [
+        "--cfg",
+        "zeta",
+        "-C",
+        "link-arg=-Wl,foo",
+        "--cfg",
+        "alpha",
+    ] + select_with_or(
+        {
+            # cfg(target_os = "linux")
+            (
+                "@rules_rust//rust/platform:x86_64-unknown-linux-gnu",
+            ): [
+                "-Clink-arg=-lrt",
+            ],
+            "//conditions:default": [],
+        },
+        # The order of values is significant
+        deduplicate = False,
+    ),"#;
+        assert!(build_file_content.contains(flags));
+    }
+
+    #[test]
+    fn render_target_attribute_overrides() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), "0.1.0".to_owned());
+        let mut crate_context = CrateContext {
+            name: crate_id.name.clone(),
+            version: crate_id.version.clone(),
+            targets: vec![
+                Rule::Library(TargetAttributes {
+                    srcs: Glob {
+                        include: vec!["**/*.rs".to_owned()],
+                        exclude: vec!["tests/fixtures/**".to_owned()],
+                    },
+                    ..mock_target_attributes()
+                }),
+                Rule::BuildScript(TargetAttributes {
+                    crate_name: "build_script_build".to_owned(),
+                    crate_root: Some("build.rs".to_owned()),
+                    ..TargetAttributes::default()
+                }),
+            ],
+            build_script_attrs: Some(BuildScriptAttributes::default()),
+            ..CrateContext::default()
+        };
+        crate_context.common_attrs.tags = vec!["no-remote-exec".to_owned()];
+        crate_context.common_attrs.visibility = BTreeSet::from(["@//:__subpackages__".to_owned()]);
+        crate_context.common_attrs.disable_pipelining = true;
+        context.crates.insert(crate_id, crate_context);
+
+        let renderer = Renderer::new(mock_render_config());
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // Tags apply to both the library and the build script
+        assert_eq!(
+            build_file_content
+                .matches("tags = [\n        \"no-remote-exec\",")
+                .count(),
+            2
+        );
+        // The package of the crates module is always able to see the crate's targets
+        assert!(build_file_content.contains(
+            "visibility = [\n        \"@//:__subpackages__\",\n        \"@test_rendering//:__pkg__\",\n    ],"
+        ));
+        assert!(build_file_content.contains("disable_pipelining = True,"));
+        assert!(build_file_content.contains("crate_root = \"src/root.rs\","));
+        assert!(build_file_content
+            .contains("exclude = [\n            \"tests/fixtures/**\",\n        ],"));
+
+        // Build scripts remain private
+        assert!(build_file_content.contains("visibility = [\"//visibility:private\"],"));
+    }
+
+    #[test]
+    fn render_restricted_visibility_with_dependents() {
+        let mut context = Context::default();
+        let restricted_id = CrateId::new("restricted".to_owned(), "0.1.0".to_owned());
+        let dependent_id = CrateId::new("dependent".to_owned(), "0.2.0".to_owned());
+
+        let mut restricted = CrateContext {
+            name: restricted_id.name.clone(),
+            version: restricted_id.version.clone(),
+            targets: vec![Rule::Library(mock_target_attributes())],
+            ..CrateContext::default()
+        };
+        restricted.common_attrs.visibility = BTreeSet::from(["//visibility:private".to_owned()]);
+        context.crates.insert(restricted_id.clone(), restricted);
+
+        let mut dependent = CrateContext {
+            name: dependent_id.name.clone(),
+            version: dependent_id.version.clone(),
+            targets: vec![Rule::Library(mock_target_attributes())],
+            ..CrateContext::default()
+        };
+        dependent.common_attrs.deps.insert(
+            CrateDependency {
+                id: restricted_id,
+                target: "restricted".to_owned(),
+                alias: None,
+            },
+            None,
+        );
+        context.crates.insert(dependent_id, dependent);
+
+        let renderer = Renderer::new(mock_render_config());
+        let output = renderer.render(&context).unwrap();
+
+        // The crates which depend on a restricted crate are always able to see it
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.restricted-0.1.0.bazel"))
+            .unwrap();
+        assert!(build_file_content.contains(
+            "visibility = [\n        \"@test_rendering//:__pkg__\",\n        \"@test_rendering__dependent-0.2.0//:__pkg__\",\n    ],"
+        ));
+        assert!(!build_file_content.contains("//visibility:private"));
+
+        // Crates without a visibility annotation are unaffected
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.dependent-0.2.0.bazel"))
+            .unwrap();
+        assert!(!build_file_content.contains("    visibility = ["));
+    }
+
+    #[test]
+    fn module_package_label() {
+        assert_eq!(
+            render_module_package_label("//:{file}", "crate_index").unwrap(),
+            "@crate_index//:__pkg__"
+        );
+        assert_eq!(
+            render_module_package_label("@workspace//3rdparty/crates:{file}", "crate_index")
+                .unwrap(),
+            "@workspace//3rdparty/crates:__pkg__"
+        );
+    }
+
     #[test]
     fn render_aliases() {
         let annotations = Annotations::new(
@@ -385,6 +768,35 @@ mod test {
         assert!(build_file_content.contains(r#"name = "names-0.12.0__names","#));
     }
 
+    #[test]
+    fn render_override_target() {
+        let mut config = Config::default();
+        config.annotations.insert(
+            CrateId::new("log".to_owned(), "0.4.14".to_owned()),
+            CrateAnnotations {
+                override_target: Some("//third_party/log".to_owned()),
+                ..CrateAnnotations::default()
+            },
+        );
+        let annotations =
+            Annotations::new(test::metadata::alias(), test::lockfile::alias(), config).unwrap();
+        let context = Context::new(annotations).unwrap();
+
+        let renderer = Renderer::new(mock_render_config());
+        let output = renderer.render(&context).unwrap();
+
+        // No BUILD file or repository is generated for the replaced crate
+        assert!(!output.contains_key(&PathBuf::from("BUILD.log-0.4.14.bazel")));
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+        assert!(!defs_module.contains("test_rendering__log-0.4.14"));
+        assert!(defs_module.contains("test_rendering__log-0.3.9"));
+
+        // Dependents and the workspace member maps use the replacement label
+        let dependent = output.get(&PathBuf::from("BUILD.log-0.3.9.bazel")).unwrap();
+        assert!(dependent.contains("\"//third_party/log\","));
+        assert!(defs_module.contains("\"//third_party/log\""));
+    }
+
     #[test]
     fn render_crate_repositories() {
         let mut context = Context::default();
@@ -467,4 +879,72 @@ mod test {
         // Local vendoring does not produce a `crates.bzl` file.
         assert!(output.get(&PathBuf::from("crates.bzl")).is_none());
     }
+
+    #[test]
+    fn check_outputs_reports_differences() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let out_dir = temp_dir.as_ref();
+        fs::create_dir_all(out_dir.join("crates/stale-0.1.0")).unwrap();
+        fs::create_dir_all(out_dir.join("crates/current-0.1.0/src")).unwrap();
+        fs::write(out_dir.join("crates/BUILD.bazel"), "unrelated\n").unwrap();
+        fs::write(out_dir.join("crates/defs.bzl"), "old\n").unwrap();
+        fs::write(
+            out_dir.join("crates/current-0.1.0/BUILD.bazel"),
+            "current\n",
+        )
+        .unwrap();
+        fs::write(out_dir.join("crates/current-0.1.0/src/lib.rs"), "").unwrap();
+
+        let outputs = BTreeMap::from([
+            (PathBuf::from("crates/defs.bzl"), "new\n".to_owned()),
+            (
+                PathBuf::from("crates/current-0.1.0/BUILD.bazel"),
+                "current\n".to_owned(),
+            ),
+            (
+                PathBuf::from("crates/missing-0.1.0/BUILD.bazel"),
+                "missing\n".to_owned(),
+            ),
+        ]);
+
+        let mut check = check_outputs(&outputs, out_dir).unwrap();
+        check.extra = find_extra_outputs(&outputs, out_dir, Path::new("crates")).unwrap();
+
+        assert_eq!(
+            check.stale,
+            BTreeMap::from([(
+                PathBuf::from("crates/defs.bzl"),
+                StaleOutput {
+                    actual: "old\n".to_owned(),
+                    expected: "new\n".to_owned(),
+                }
+            )])
+        );
+        assert_eq!(
+            check.missing,
+            BTreeSet::from([PathBuf::from("crates/missing-0.1.0/BUILD.bazel")])
+        );
+        assert_eq!(
+            check.extra,
+            BTreeSet::from([
+                PathBuf::from("crates/BUILD.bazel"),
+                PathBuf::from("crates/stale-0.1.0"),
+            ])
+        );
+        assert!(!check.is_empty());
+        assert!(check.to_string().starts_with(
+            "--- a/crates/defs.bzl\n+++ b/crates/defs.bzl\n@@ -1,1 +1,1 @@\n-old\n+new\n"
+        ));
+    }
+
+    #[test]
+    fn check_outputs_up_to_date() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let outputs = BTreeMap::from([(PathBuf::from("defs.bzl"), "content\n".to_owned())]);
+        write_outputs(outputs.clone(), temp_dir.as_ref(), false).unwrap();
+
+        let check = check_outputs(&outputs, temp_dir.as_ref()).unwrap();
+        assert!(check.is_empty());
+        assert_eq!(check.to_string(), "");
+    }
 }
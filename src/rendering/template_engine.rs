@@ -1,6 +1,6 @@
 //! A template engine backed by [Tera] for rendering Files.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use anyhow::{Context as AnyhowContext, Result};
 use serde_json::{from_value, to_value, Value};
@@ -10,7 +10,7 @@ use crate::config::{CrateId, RenderConfig};
 use crate::context::Context;
 use crate::rendering::{
     render_crate_bazel_label, render_crate_bazel_repository, render_crate_build_file,
-    render_module_label, render_platform_constraint_label,
+    render_module_label, render_module_package_label, render_platform_constraint_label,
 };
 use crate::utils::sanitize_module_name;
 use crate::utils::sanitize_repository_name;
@@ -19,6 +19,7 @@ use crate::utils::starlark::{SelectStringDict, SelectStringList};
 pub struct TemplateEngine {
     engine: Tera,
     context: tera::Context,
+    render_config: RenderConfig,
 }
 
 impl TemplateEngine {
@@ -60,6 +61,13 @@ impl TemplateEngine {
                     "/src/rendering/templates/partials/crate/deps.j2"
                 )),
             ),
+            (
+                "partials/crate/extra_deps.j2",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/src/rendering/templates/partials/crate/extra_deps.j2"
+                )),
+            ),
             (
                 "partials/crate/library.j2",
                 include_str!(concat!(
@@ -123,6 +131,13 @@ impl TemplateEngine {
                     "/src/rendering/templates/partials/starlark/selectable_list.j2"
                 )),
             ),
+            (
+                "partials/starlark/selectable_ordered_list.j2",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/src/rendering/templates/partials/starlark/selectable_ordered_list.j2"
+                )),
+            ),
             (
                 "partials/header.j2",
                 include_str!(concat!(
@@ -206,6 +221,7 @@ impl TemplateEngine {
         Self {
             engine: tera,
             context,
+            render_config: render_config.clone(),
         }
     }
 
@@ -213,6 +229,42 @@ impl TemplateEngine {
         self.context.clone()
     }
 
+    /// Determine the `visibility` of a crate's library, proc-macro and binary targets. A crate
+    /// with a restricted visibility must still be visible to the aliases in the crates module
+    /// and to the crates which depend on it, otherwise the crate could not be built at all.
+    fn crate_visibility(&self, ctx: &Context, crate_id: &CrateId) -> Result<BTreeSet<String>> {
+        let visibility = &ctx.crates[crate_id].common_attrs.visibility;
+        if visibility.is_empty() || visibility.contains("//visibility:public") {
+            return Ok(visibility.clone());
+        }
+
+        let mut labels: BTreeSet<String> = visibility
+            .iter()
+            .filter(|label| *label != "//visibility:private")
+            .cloned()
+            .collect();
+
+        labels.insert(
+            render_module_package_label(
+                &self.render_config.crates_module_template,
+                &self.render_config.repository_name,
+            )
+            .context("Failed to resolve the package of the crates module")?,
+        );
+
+        labels.extend(ctx.crate_dependents(crate_id).into_iter().map(|id| {
+            sanitize_repository_name(&render_crate_bazel_label(
+                &self.render_config.crate_label_template,
+                &self.render_config.repository_name,
+                &id.name,
+                &id.version,
+                "__pkg__",
+            ))
+        }));
+
+        Ok(labels)
+    }
+
     pub fn render_crate_build_files<'a>(
         &self,
         ctx: &'a Context,
@@ -231,6 +283,7 @@ impl TemplateEngine {
                 context.insert("crate_id", &id);
                 context.insert("common_aliases", &aliases);
                 context.insert("build_aliases", &build_aliases);
+                context.insert("visibility", &self.crate_visibility(ctx, id)?);
 
                 let content = self
                     .engine
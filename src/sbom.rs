@@ -0,0 +1,635 @@
+//! Software bill of materials (SBOM) exports of a [Context] in the
+//! [CycloneDX](https://cyclonedx.org/docs/1.4/json/) and [SPDX](https://spdx.github.io/spdx-spec/v2.3/)
+//! formats.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::str::FromStr;
+
+use serde::Serialize;
+use sha2::{Digest as Sha2Digest, Sha256};
+
+use crate::config::{Commitish, CrateId};
+use crate::context::crate_context::CrateContext;
+use crate::context::graph::{DependencyEdge, DependencyKind};
+use crate::context::Context;
+use crate::licenses::LicenseExpr;
+use crate::metadata::SourceAnnotation;
+
+/// The value SPDX uses for unknown fields
+const NOASSERTION: &str = "NOASSERTION";
+
+/// The crates and relationships described by an SBOM
+pub struct Sbom<'a> {
+    context: &'a Context,
+
+    /// The workspace member the SBOM describes. If unset, the whole workspace is described.
+    root: Option<&'a CrateId>,
+
+    /// All crates included in the SBOM
+    crates: BTreeSet<&'a CrateId>,
+
+    /// Dependencies between the included crates
+    edges: BTreeSet<(&'a CrateId, &'a CrateId, DependencyKind)>,
+
+    /// The creation time of the SBOM in seconds since the Unix epoch
+    created: u64,
+}
+
+impl<'a> Sbom<'a> {
+    /// Collect the crates to describe. If `root` is provided, only the crates it transitively
+    /// depends on (excluding dev dependencies) are included.
+    pub fn new(context: &'a Context, root: Option<&'a CrateId>, created: u64) -> Self {
+        let mut crates = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+
+        let mut add_edge = |edge: &DependencyEdge<'a>| {
+            edges.insert((edge.source, edge.target, edge.kind));
+        };
+
+        match root {
+            Some(root) => {
+                let mut queue = VecDeque::from([root]);
+                while let Some(id) = queue.pop_front() {
+                    if !crates.insert(id) {
+                        continue;
+                    }
+                    for edge in context.dependency_edges(id).iter().filter(|edge| {
+                        !matches!(
+                            edge.kind,
+                            DependencyKind::NormalDev | DependencyKind::ProcMacroDev
+                        )
+                    }) {
+                        add_edge(edge);
+                        queue.push_back(edge.target);
+                    }
+                }
+            }
+            None => {
+                crates.extend(context.crates.keys());
+                context.all_dependency_edges().iter().for_each(add_edge);
+            }
+        }
+
+        Self {
+            context,
+            root,
+            crates,
+            edges,
+            created,
+        }
+    }
+
+    /// The name of the document. Either the name of the root crate or a generic workspace name.
+    fn name(&self) -> String {
+        match self.root {
+            Some(root) => format!("{}-{}", root.name, root.version),
+            None => "workspace".to_owned(),
+        }
+    }
+
+    fn crate_context(&self, id: &CrateId) -> Option<&'a CrateContext> {
+        self.context.crates.get(id)
+    }
+
+    /// Render the SBOM as a CycloneDX 1.4 document
+    pub fn to_cyclonedx(&self) -> CycloneDxBom {
+        let component = |id: &CrateId| {
+            let ctx = self.crate_context(id);
+            let mut hashes = Vec::new();
+            let mut external_references = Vec::new();
+            match ctx.and_then(|ctx| ctx.repository.as_ref()) {
+                Some(SourceAnnotation::Http { url, sha256, .. }) => {
+                    if let Some(sha256) = sha256 {
+                        hashes.push(CycloneDxHash {
+                            alg: "SHA-256".to_owned(),
+                            content: sha256.clone(),
+                        });
+                    }
+                    external_references.push(CycloneDxExternalReference {
+                        reference_type: "distribution".to_owned(),
+                        url: url.clone(),
+                        comment: None,
+                    });
+                }
+                Some(SourceAnnotation::Git {
+                    remote, commitish, ..
+                }) => external_references.push(CycloneDxExternalReference {
+                    reference_type: "vcs".to_owned(),
+                    url: remote.clone(),
+                    comment: Some(describe_commitish(commitish)),
+                }),
+                None => {}
+            }
+
+            CycloneDxComponent {
+                component_type: match self.context.workspace_members.contains_key(id) {
+                    true => "application".to_owned(),
+                    false => "library".to_owned(),
+                },
+                bom_ref: purl(id),
+                name: id.name.clone(),
+                version: id.version.clone(),
+                purl: purl(id),
+                licenses: ctx
+                    .and_then(|ctx| normalize_license(ctx.license.as_ref()?))
+                    .map(|expression| vec![CycloneDxLicense { expression }])
+                    .unwrap_or_default(),
+                hashes,
+                external_references,
+            }
+        };
+
+        let mut dependencies: BTreeMap<&CrateId, BTreeSet<String>> = self
+            .crates
+            .iter()
+            .map(|id| (*id, BTreeSet::new()))
+            .collect();
+        for (source, target, _) in self.edges.iter() {
+            dependencies.entry(source).or_default().insert(purl(target));
+        }
+
+        CycloneDxBom {
+            bom_format: "CycloneDX".to_owned(),
+            spec_version: "1.4".to_owned(),
+            version: 1,
+            metadata: CycloneDxMetadata {
+                timestamp: format_timestamp(self.created),
+                tools: vec![CycloneDxTool {
+                    vendor: "bazelbuild".to_owned(),
+                    name: "cargo-bazel".to_owned(),
+                    version: env!("CARGO_PKG_VERSION").to_owned(),
+                }],
+                component: self.root.map(component),
+            },
+            components: self
+                .crates
+                .iter()
+                .filter(|id| Some(**id) != self.root)
+                .map(|id| component(id))
+                .collect(),
+            dependencies: dependencies
+                .into_iter()
+                .map(|(id, depends_on)| CycloneDxDependency {
+                    reference: purl(id),
+                    depends_on: depends_on.into_iter().collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Render the SBOM as an SPDX 2.3 document
+    pub fn to_spdx(&self) -> SpdxDocument {
+        let packages: Vec<SpdxPackage> = self
+            .crates
+            .iter()
+            .map(|id| {
+                let ctx = self.crate_context(id);
+                let (download_location, checksums) =
+                    match ctx.and_then(|ctx| ctx.repository.as_ref()) {
+                        Some(SourceAnnotation::Http { url, sha256, .. }) => (
+                            url.clone(),
+                            sha256
+                                .iter()
+                                .map(|sha256| SpdxChecksum {
+                                    algorithm: "SHA256".to_owned(),
+                                    checksum_value: sha256.clone(),
+                                })
+                                .collect(),
+                        ),
+                        Some(SourceAnnotation::Git {
+                            remote, commitish, ..
+                        }) => (git_download_location(remote, commitish), Vec::new()),
+                        None => (NOASSERTION.to_owned(), Vec::new()),
+                    };
+
+                SpdxPackage {
+                    spdx_id: spdx_id(id),
+                    name: id.name.clone(),
+                    version_info: id.version.clone(),
+                    download_location,
+                    files_analyzed: false,
+                    checksums,
+                    license_concluded: NOASSERTION.to_owned(),
+                    license_declared: ctx
+                        .and_then(|ctx| normalize_license(ctx.license.as_ref()?))
+                        .unwrap_or_else(|| NOASSERTION.to_owned()),
+                    copyright_text: NOASSERTION.to_owned(),
+                    external_refs: vec![SpdxExternalRef {
+                        reference_category: "PACKAGE-MANAGER".to_owned(),
+                        reference_type: "purl".to_owned(),
+                        reference_locator: purl(id),
+                    }],
+                }
+            })
+            .collect();
+
+        // The document describes the root crate or every workspace member
+        let described: Vec<&CrateId> = match self.root {
+            Some(root) => vec![root],
+            None => self.context.workspace_members.keys().collect(),
+        };
+        let mut relationships: Vec<SpdxRelationship> = described
+            .into_iter()
+            .map(|id| SpdxRelationship {
+                spdx_element_id: "SPDXRef-DOCUMENT".to_owned(),
+                relationship_type: "DESCRIBES".to_owned(),
+                related_spdx_element: spdx_id(id),
+            })
+            .collect();
+        relationships.extend(self.edges.iter().map(|(source, target, kind)| {
+            // Build and dev dependencies are expressed from the perspective of the dependency
+            match kind {
+                DependencyKind::Normal | DependencyKind::ProcMacro => SpdxRelationship {
+                    spdx_element_id: spdx_id(source),
+                    relationship_type: "DEPENDS_ON".to_owned(),
+                    related_spdx_element: spdx_id(target),
+                },
+                DependencyKind::NormalDev | DependencyKind::ProcMacroDev => SpdxRelationship {
+                    spdx_element_id: spdx_id(target),
+                    relationship_type: "DEV_DEPENDENCY_OF".to_owned(),
+                    related_spdx_element: spdx_id(source),
+                },
+                DependencyKind::Build | DependencyKind::BuildProcMacro => SpdxRelationship {
+                    spdx_element_id: spdx_id(target),
+                    relationship_type: "BUILD_DEPENDENCY_OF".to_owned(),
+                    related_spdx_element: spdx_id(source),
+                },
+            }
+        }));
+
+        // The namespace must be unique to the contents of the document
+        let mut hasher = Sha256::new();
+        for id in self.crates.iter() {
+            hasher.update(id.to_string().as_bytes());
+            hasher.update(b"\n");
+        }
+        let name = self.name();
+
+        SpdxDocument {
+            spdx_version: "SPDX-2.3".to_owned(),
+            data_license: "CC0-1.0".to_owned(),
+            spdx_id: "SPDXRef-DOCUMENT".to_owned(),
+            document_namespace: format!(
+                "https://spdx.org/spdxdocs/{}-{}",
+                name,
+                hex::encode(hasher.finalize())
+            ),
+            name,
+            creation_info: SpdxCreationInfo {
+                created: format_timestamp(self.created),
+                creators: vec![format!("Tool: cargo-bazel-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            packages,
+            relationships,
+        }
+    }
+}
+
+/// The [package URL](https://github.com/package-url/purl-spec) of a crate
+fn purl(id: &CrateId) -> String {
+    format!("pkg:cargo/{}@{}", id.name, id.version)
+}
+
+/// A unique SPDX identifier for a crate. Only letters, numbers, `.`, and `-` are allowed.
+fn spdx_id(id: &CrateId) -> String {
+    let sanitized: String = format!("{}-{}", id.name, id.version)
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                true => c,
+                false => '-',
+            },
+        )
+        .collect();
+    format!("SPDXRef-Package-{}", sanitized)
+}
+
+/// Convert a Cargo license string to a normalized SPDX license expression
+fn normalize_license(license: &str) -> Option<String> {
+    LicenseExpr::from_str(license)
+        .ok()
+        .map(|expr| expr.to_string())
+}
+
+fn describe_commitish(commitish: &Commitish) -> String {
+    match commitish {
+        Commitish::Tag(tag) => format!("tag: {}", tag),
+        Commitish::Branch(branch) => format!("branch: {}", branch),
+        Commitish::Rev(rev) => format!("rev: {}", rev),
+    }
+}
+
+/// A download location for a git repository in the form `git+<remote>@<revision>`
+fn git_download_location(remote: &str, commitish: &Commitish) -> String {
+    let remote = match remote.starts_with("git+") {
+        true => remote.to_owned(),
+        false => format!("git+{}", remote),
+    };
+    match commitish {
+        Commitish::Tag(rev) | Commitish::Branch(rev) | Commitish::Rev(rev) => {
+            format!("{}@{}", remote, rev)
+        }
+    }
+}
+
+/// Format seconds since the Unix epoch as an ISO 8601 UTC timestamp
+fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Convert days since the epoch to a civil date.
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        (time % 3_600) / 60,
+        time % 60
+    )
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CycloneDxBom {
+    bom_format: String,
+    spec_version: String,
+    version: u32,
+    metadata: CycloneDxMetadata,
+    components: Vec<CycloneDxComponent>,
+    dependencies: Vec<CycloneDxDependency>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxMetadata {
+    timestamp: String,
+    tools: Vec<CycloneDxTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<CycloneDxComponent>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxTool {
+    vendor: String,
+    name: String,
+    version: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    component_type: String,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+    version: String,
+    purl: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<CycloneDxLicense>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<CycloneDxHash>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    external_references: Vec<CycloneDxExternalReference>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxLicense {
+    expression: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxHash {
+    alg: String,
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxExternalReference {
+    #[serde(rename = "type")]
+    reference_type: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxDocument {
+    spdx_version: String,
+    data_license: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    name: String,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    relationships: Vec<SpdxRelationship>,
+}
+
+#[derive(Debug, Serialize)]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    name: String,
+    version_info: String,
+    download_location: String,
+    files_analyzed: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checksums: Vec<SpdxChecksum>,
+    license_concluded: String,
+    license_declared: String,
+    copyright_text: String,
+    external_refs: Vec<SpdxExternalRef>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxChecksum {
+    algorithm: String,
+    checksum_value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxExternalRef {
+    reference_category: String,
+    reference_type: String,
+    reference_locator: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: String,
+    related_spdx_element: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::config::Config;
+    use crate::metadata::Annotations;
+
+    fn mock_context(metadata: cargo_metadata::Metadata, lockfile: cargo_lock::Lockfile) -> Context {
+        let config = Config {
+            generate_build_scripts: true,
+            ..Config::default()
+        };
+        let annotations = Annotations::new(metadata, lockfile, config).unwrap();
+
+        Context::new(annotations).unwrap()
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn cyclonedx() {
+        let context = mock_context(
+            crate::test::metadata::build_scripts(),
+            crate::test::lockfile::build_scripts(),
+        );
+        let root = context.workspace_members.keys().next().unwrap();
+        let bom = serde_json::to_value(Sbom::new(&context, Some(root), 0).to_cyclonedx()).unwrap();
+
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["metadata"]["timestamp"], "1970-01-01T00:00:00Z");
+        assert_eq!(bom["metadata"]["component"]["type"], "application");
+        assert_eq!(
+            bom["metadata"]["component"]["purl"],
+            "pkg:cargo/build-scripts@0.1.0"
+        );
+
+        let openssl = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == "openssl")
+            .unwrap();
+        assert_eq!(openssl["purl"], "pkg:cargo/openssl@0.10.36");
+        assert_eq!(openssl["licenses"][0]["expression"], "Apache-2.0");
+        assert_eq!(openssl["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(openssl["externalReferences"][0]["type"], "distribution");
+
+        let openssl_deps = bom["dependencies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["ref"] == "pkg:cargo/openssl@0.10.36")
+            .unwrap();
+        assert!(openssl_deps["dependsOn"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("pkg:cargo/openssl-sys@0.9.72")));
+    }
+
+    #[test]
+    fn spdx() {
+        let context = mock_context(
+            crate::test::metadata::build_scripts(),
+            crate::test::lockfile::build_scripts(),
+        );
+        let document = serde_json::to_value(Sbom::new(&context, None, 0).to_spdx()).unwrap();
+
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["SPDXID"], "SPDXRef-DOCUMENT");
+
+        let openssl_sys = document["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "openssl-sys")
+            .unwrap();
+        assert_eq!(openssl_sys["SPDXID"], "SPDXRef-Package-openssl-sys-0.9.72");
+        assert_eq!(openssl_sys["licenseDeclared"], "MIT");
+        assert_eq!(openssl_sys["checksums"][0]["algorithm"], "SHA256");
+        assert_eq!(
+            openssl_sys["externalRefs"][0]["referenceLocator"],
+            "pkg:cargo/openssl-sys@0.9.72"
+        );
+
+        let relationships = document["relationships"].as_array().unwrap();
+        assert!(relationships.contains(&serde_json::json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": "SPDXRef-Package-build-scripts-0.1.0",
+        })));
+        assert!(relationships.contains(&serde_json::json!({
+            "spdxElementId": "SPDXRef-Package-openssl-0.10.36",
+            "relationshipType": "DEPENDS_ON",
+            "relatedSpdxElement": "SPDXRef-Package-openssl-sys-0.9.72",
+        })));
+        assert!(relationships.contains(&serde_json::json!({
+            "spdxElementId": "SPDXRef-Package-cc-1.0.72",
+            "relationshipType": "BUILD_DEPENDENCY_OF",
+            "relatedSpdxElement": "SPDXRef-Package-openssl-sys-0.9.72",
+        })));
+    }
+
+    #[test]
+    fn git_sources() {
+        let context = mock_context(
+            crate::test::metadata::git_repos(),
+            crate::test::lockfile::git_repos(),
+        );
+        let sbom = Sbom::new(&context, None, 0);
+
+        let document = serde_json::to_value(sbom.to_spdx()).unwrap();
+        let tracing = document["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "tracing")
+            .unwrap();
+        assert!(tracing["downloadLocation"]
+            .as_str()
+            .unwrap()
+            .starts_with("git+https://github.com/tokio-rs/tracing.git@"));
+
+        let bom = serde_json::to_value(sbom.to_cyclonedx()).unwrap();
+        let tracing = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == "tracing")
+            .unwrap();
+        assert_eq!(tracing["externalReferences"][0]["type"], "vcs");
+        assert!(tracing["externalReferences"][0]["comment"]
+            .as_str()
+            .unwrap()
+            .starts_with("rev: "));
+    }
+}
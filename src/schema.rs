@@ -0,0 +1,1468 @@
+//! [JSON Schemas](https://json-schema.org/) describing the files read and written by `cargo-bazel`
+
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use serde_json::{json, Map, Value};
+
+/// The files a schema can be produced for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    /// A `cargo-bazel` config. See [crate::config::Config]
+    Config,
+
+    /// The inputs to splicing. See [crate::splicing::SplicingManifest]
+    SplicingManifest,
+
+    /// A Bazel lockfile (`Cargo.Bazel.lock`). See [crate::context::Context]
+    Lockfile,
+}
+
+impl SchemaKind {
+    /// The name of the schema's file
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Config => "config.schema.json",
+            Self::SplicingManifest => "splicing_manifest.schema.json",
+            Self::Lockfile => "lockfile.schema.json",
+        }
+    }
+}
+
+impl FromStr for SchemaKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "config" => Ok(Self::Config),
+            "splicing-manifest" | "splicing_manifest" => Ok(Self::SplicingManifest),
+            "lockfile" => Ok(Self::Lockfile),
+            _ => bail!("Unknown SchemaKind: '{}'", s),
+        }
+    }
+}
+
+/// Produce the JSON Schema of a file. The schema's `$id` contains the version of `cargo-bazel`
+/// so consumers can track which contract a file was written against.
+pub fn schema(kind: SchemaKind) -> Value {
+    let (title, root, definitions) = match kind {
+        SchemaKind::Config => ("cargo-bazel config", config(), config_definitions()),
+        SchemaKind::SplicingManifest => (
+            "cargo-bazel splicing manifest",
+            splicing_manifest(),
+            splicing_manifest_definitions(),
+        ),
+        SchemaKind::Lockfile => ("cargo-bazel lockfile", lockfile(), lockfile_definitions()),
+    };
+
+    let mut schema = Map::new();
+    schema.insert(
+        "$schema".to_owned(),
+        json!("http://json-schema.org/draft-07/schema#"),
+    );
+    schema.insert(
+        "$id".to_owned(),
+        json!(format!(
+            "urn:cargo-bazel:{}:{}",
+            env!("CARGO_PKG_VERSION"),
+            kind.file_name()
+        )),
+    );
+    schema.insert("title".to_owned(), json!(title));
+    if let Value::Object(root) = root {
+        schema.extend(root);
+    }
+    schema.insert("definitions".to_owned(), Value::Object(definitions));
+
+    Value::Object(schema)
+}
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/definitions/{}", name) })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "anyOf": [schema, { "type": "null" }] })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn string_list() -> Value {
+    json!({ "type": "array", "items": string() })
+}
+
+fn string_set() -> Value {
+    json!({ "type": "array", "items": string(), "uniqueItems": true })
+}
+
+fn string_map() -> Value {
+    json!({ "type": "object", "additionalProperties": string() })
+}
+
+fn described(mut schema: Value, description: &str) -> Value {
+    if let Value::Object(obj) = &mut schema {
+        obj.insert("description".to_owned(), json!(description));
+    }
+    schema
+}
+
+/// A crate identifier in the form `{name} {version}`
+fn crate_id() -> Value {
+    json!({
+        "type": "string",
+        "pattern": "^\\S+ \\S+$",
+        "description": "A crate name and version separated by a space. Eg. `serde 1.0.136`",
+    })
+}
+
+/// A selectable list. See [crate::utils::starlark::SelectList]
+fn select_list(items: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "common": { "type": "array", "items": items },
+            "selects": {
+                "type": "object",
+                "additionalProperties": { "type": "array", "items": items },
+            },
+        },
+    })
+}
+
+/// A selectable dict. See [crate::utils::starlark::SelectDict]
+fn select_dict() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "common": string_map(),
+            "selects": { "type": "object", "additionalProperties": string_map() },
+        },
+    })
+}
+
+fn object(properties: Vec<(&str, Value)>, required: &[&str], strict: bool) -> Value {
+    let properties: Map<String, Value> = properties
+        .into_iter()
+        .map(|(name, schema)| (name.to_owned(), schema))
+        .collect();
+
+    let mut obj = Map::new();
+    obj.insert("type".to_owned(), json!("object"));
+    obj.insert("properties".to_owned(), Value::Object(properties));
+    if !required.is_empty() {
+        obj.insert("required".to_owned(), json!(required));
+    }
+    if strict {
+        obj.insert("additionalProperties".to_owned(), json!(false));
+    }
+    Value::Object(obj)
+}
+
+/// An externally tagged enum where each variant wraps a single value
+fn tagged(variants: Vec<(&str, Value)>) -> Value {
+    let one_of: Vec<Value> = variants
+        .into_iter()
+        .map(|(variant, schema)| object(vec![(variant, schema)], &[variant], true))
+        .collect();
+    json!({ "oneOf": one_of })
+}
+
+fn config() -> Value {
+    object(
+        vec![
+            (
+                "generate_build_scripts",
+                described(
+                    json!({ "type": "boolean" }),
+                    "Whether or not to generate Cargo build scripts by default",
+                ),
+            ),
+            (
+                "generate_binaries",
+                described(
+                    reference("GenBinaries"),
+                    "Which binaries to generate targets for by default",
+                ),
+            ),
+            (
+                "annotations",
+                described(
+                    json!({
+                        "type": "object",
+                        "additionalProperties": reference("CrateAnnotations"),
+                    }),
+                    "Additional settings to apply to generated crates keyed by a crate name \
+                     pattern and version requirement. Eg. `openssl-sys *`",
+                ),
+            ),
+            (
+                "default_annotations",
+                described(
+                    nullable(reference("CrateAnnotations")),
+                    "Settings to apply to all third party crates beneath those in `annotations`",
+                ),
+            ),
+            ("rendering", reference("RenderConfig")),
+            (
+                "cargo_config",
+                described(json!({}), "The contents of a Cargo configuration file"),
+            ),
+            (
+                "supported_platform_triples",
+                described(
+                    string_set(),
+                    "A set of platform triples to use in generated select statements",
+                ),
+            ),
+            ("license_policy", nullable(reference("LicensePolicy"))),
+        ],
+        &["generate_build_scripts", "rendering"],
+        true,
+    )
+}
+
+fn config_definitions() -> Map<String, Value> {
+    let mut defs = Map::new();
+
+    defs.insert(
+        "GenBinaries".to_owned(),
+        described(
+            json!({ "anyOf": [{ "type": "boolean" }, string_set()] }),
+            "Either whether or not to generate targets for all binaries or the names of the \
+             binaries to generate targets for",
+        ),
+    );
+
+    defs.insert(
+        "RenderConfig".to_owned(),
+        object(
+            vec![
+                ("repository_name", string()),
+                ("build_file_template", string()),
+                ("crate_label_template", string()),
+                ("crates_module_template", string()),
+                ("crate_repository_template", string()),
+                ("default_package_name", nullable(string())),
+                ("platforms_template", string()),
+                (
+                    "vendor_mode",
+                    nullable(json!({ "type": "string", "enum": ["local", "remote"] })),
+                ),
+            ],
+            &["repository_name"],
+            true,
+        ),
+    );
+
+    defs.insert(
+        "LicensePolicy".to_owned(),
+        object(
+            vec![("allow", string_set()), ("deny", string_set())],
+            &[],
+            true,
+        ),
+    );
+
+    defs.insert("CrateAnnotations".to_owned(), crate_annotations());
+
+    defs
+}
+
+fn crate_annotations() -> Value {
+    let labels = |description: &str| described(nullable(string_set()), description);
+    let globs = |description: &str| described(nullable(string_set()), description);
+    let env = |description: &str| described(nullable(string_map()), description);
+
+    object(
+        vec![
+            (
+                "gen_build_script",
+                described(
+                    nullable(json!({ "type": "boolean" })),
+                    "Whether or not to generate a `cargo_build_script` target",
+                ),
+            ),
+            (
+                "gen_binaries",
+                described(
+                    nullable(reference("GenBinaries")),
+                    "Which of the crate's binaries to generate targets for",
+                ),
+            ),
+            ("deps", labels("Labels to add to the `deps` attribute")),
+            (
+                "proc_macro_deps",
+                labels("Labels to add to the `proc_macro_deps` attribute"),
+            ),
+            (
+                "crate_features",
+                described(nullable(string_set()), "Features to enable"),
+            ),
+            (
+                "crate_features_remove",
+                described(
+                    nullable(string_set()),
+                    "Features to remove from those Cargo resolved",
+                ),
+            ),
+            (
+                "crate_features_override",
+                described(
+                    nullable(string_set()),
+                    "Features to use in place of those Cargo resolved",
+                ),
+            ),
+            ("data", labels("Labels to add to the `data` attribute")),
+            (
+                "data_glob",
+                globs("Glob patterns to add to the `data` attribute"),
+            ),
+            (
+                "compile_data",
+                labels("Labels to add to the `compile_data` attribute"),
+            ),
+            (
+                "compile_data_glob",
+                globs("Glob patterns to add to the `compile_data` attribute"),
+            ),
+            (
+                "rustc_env",
+                env("Variables to add to the `rustc_env` attribute"),
+            ),
+            (
+                "rustc_env_files",
+                labels("Labels to add to the `rustc_env_files` attribute"),
+            ),
+            (
+                "rustc_flags",
+                described(
+                    nullable(string_list()),
+                    "Flags to add to the `rustc_flags` attribute in order",
+                ),
+            ),
+            (
+                "tags",
+                described(
+                    nullable(string_set()),
+                    "Tags to add to all of the crate's targets",
+                ),
+            ),
+            (
+                "visibility",
+                labels("The `visibility` of the crate's library, proc-macro and binary targets"),
+            ),
+            (
+                "crate_root",
+                described(
+                    nullable(string()),
+                    "The `crate_root` of the crate's library or proc-macro target",
+                ),
+            ),
+            (
+                "srcs_exclude",
+                globs("Glob patterns to exclude from the `srcs` attribute"),
+            ),
+            (
+                "disable_pipelining",
+                described(
+                    nullable(json!({ "type": "boolean" })),
+                    "Whether or not to disable pipelining of the crate's library target",
+                ),
+            ),
+            (
+                "build_script_deps",
+                labels("Labels to add to the build script's `deps` attribute"),
+            ),
+            (
+                "build_script_proc_macro_deps",
+                labels("Labels to add to the build script's `proc_macro_deps` attribute"),
+            ),
+            (
+                "build_script_data",
+                labels("Labels to add to the build script's `data` attribute"),
+            ),
+            (
+                "build_script_tools",
+                labels("Labels to add to the build script's `tools` attribute"),
+            ),
+            (
+                "build_script_data_glob",
+                globs("Glob patterns to add to the build script's `data` attribute"),
+            ),
+            (
+                "build_script_env",
+                env("Variables to add to the build script's `build_script_env` attribute"),
+            ),
+            (
+                "build_script_rustc_env",
+                env("Variables to add to the build script's `rustc_env` attribute"),
+            ),
+            (
+                "additive_build_file_content",
+                described(
+                    nullable(string()),
+                    "Text to append to the crate's BUILD file",
+                ),
+            ),
+            (
+                "shallow_since",
+                described(
+                    nullable(string()),
+                    "The `shallow_since` attribute of git repositories",
+                ),
+            ),
+            (
+                "patch_args",
+                described(
+                    nullable(string_list()),
+                    "The `patch_args` attribute of the crate's repository",
+                ),
+            ),
+            (
+                "patch_tool",
+                described(
+                    nullable(string()),
+                    "The `patch_tool` attribute of the crate's repository",
+                ),
+            ),
+            (
+                "patches",
+                labels("The `patches` attribute of the crate's repository"),
+            ),
+            (
+                "platform_annotations",
+                described(
+                    nullable(json!({
+                        "type": "object",
+                        "additionalProperties": reference("CrateAnnotations"),
+                    })),
+                    "Annotations which only apply to the platform triple or `cfg(...)` \
+                     expression they're keyed by",
+                ),
+            ),
+            (
+                "override_target",
+                described(
+                    nullable(string()),
+                    "The label of an existing target which replaces the crate",
+                ),
+            ),
+            (
+                "skip_default_annotations",
+                described(
+                    nullable(json!({ "type": "boolean" })),
+                    "Whether or not to skip the `default_annotations` of the config",
+                ),
+            ),
+        ],
+        &[],
+        false,
+    )
+}
+
+fn splicing_manifest() -> Value {
+    object(
+        vec![
+            (
+                "direct_packages",
+                described(
+                    json!({
+                        "type": "object",
+                        "additionalProperties": reference("DependencyDetail"),
+                    }),
+                    "Packages written directly to the rule keyed by name",
+                ),
+            ),
+            (
+                "manifests",
+                described(
+                    string_map(),
+                    "A mapping of Cargo manifest paths to the labels representing them",
+                ),
+            ),
+            (
+                "cargo_config",
+                described(nullable(string()), "The path of a Cargo config file"),
+            ),
+            (
+                "resolver_version",
+                json!({ "type": "string", "enum": ["1", "2"] }),
+            ),
+        ],
+        &["direct_packages", "manifests", "resolver_version"],
+        true,
+    )
+}
+
+fn splicing_manifest_definitions() -> Map<String, Value> {
+    let mut defs = Map::new();
+
+    // See `cargo_toml::DependencyDetail`
+    defs.insert(
+        "DependencyDetail".to_owned(),
+        object(
+            vec![
+                ("version", nullable(string())),
+                ("registry", nullable(string())),
+                ("registry-index", nullable(string())),
+                ("path", nullable(string())),
+                ("git", nullable(string())),
+                ("branch", nullable(string())),
+                ("tag", nullable(string())),
+                ("rev", nullable(string())),
+                ("features", string_list()),
+                ("optional", json!({ "type": "boolean" })),
+                ("default-features", nullable(json!({ "type": "boolean" }))),
+                ("package", nullable(string())),
+            ],
+            &[],
+            false,
+        ),
+    );
+
+    defs
+}
+
+fn lockfile() -> Value {
+    object(
+        vec![
+            (
+                "checksum",
+                described(
+                    nullable(string()),
+                    "The collective checksum of all inputs to the lockfile",
+                ),
+            ),
+            ("checksum_inputs", nullable(reference("DigestInputs"))),
+            (
+                "crates",
+                json!({
+                    "type": "object",
+                    "propertyNames": crate_id(),
+                    "additionalProperties": reference("CrateContext"),
+                }),
+            ),
+            (
+                "binary_crates",
+                json!({ "type": "array", "items": crate_id(), "uniqueItems": true }),
+            ),
+            (
+                "workspace_members",
+                json!({
+                    "type": "object",
+                    "propertyNames": crate_id(),
+                    "additionalProperties": string(),
+                }),
+            ),
+            (
+                "conditions",
+                described(
+                    json!({ "type": "object", "additionalProperties": string_set() }),
+                    "A mapping of `cfg` expressions to the platform triples they match",
+                ),
+            ),
+        ],
+        &["crates", "binary_crates", "workspace_members", "conditions"],
+        false,
+    )
+}
+
+fn lockfile_definitions() -> Map<String, Value> {
+    let mut defs = Map::new();
+
+    defs.insert(
+        "DigestInputs".to_owned(),
+        object(
+            vec![
+                ("cargo_bazel_version", string()),
+                ("context", string()),
+                ("config", string()),
+                ("splicing_manifest", string()),
+                ("cargo_version", string()),
+                ("rustc_version", string()),
+            ],
+            &[
+                "cargo_bazel_version",
+                "context",
+                "config",
+                "splicing_manifest",
+                "cargo_version",
+                "rustc_version",
+            ],
+            false,
+        ),
+    );
+
+    defs.insert(
+        "CrateContext".to_owned(),
+        object(
+            vec![
+                ("name", string()),
+                ("version", string()),
+                ("repository", nullable(reference("SourceAnnotation"))),
+                (
+                    "targets",
+                    json!({ "type": "array", "items": reference("Rule") }),
+                ),
+                ("library_target_name", nullable(string())),
+                ("common_attrs", reference("CommonAttributes")),
+                ("build_script_attrs", reference("BuildScriptAttributes")),
+                ("license", nullable(string())),
+                ("additive_build_file_content", string()),
+                ("override_target", string()),
+            ],
+            &[],
+            false,
+        ),
+    );
+
+    let git = object(
+        vec![
+            ("remote", string()),
+            ("commitish", reference("Commitish")),
+            ("shallow_since", string()),
+            ("strip_prefix", string()),
+            ("patch_args", string_list()),
+            ("patch_tool", string()),
+            ("patches", string_set()),
+        ],
+        &["remote", "commitish"],
+        false,
+    );
+    let http = object(
+        vec![
+            ("url", string()),
+            ("sha256", string()),
+            ("patch_args", string_list()),
+            ("patch_tool", string()),
+            ("patches", string_set()),
+        ],
+        &["url"],
+        false,
+    );
+    defs.insert(
+        "SourceAnnotation".to_owned(),
+        tagged(vec![("Git", git), ("Http", http)]),
+    );
+
+    defs.insert(
+        "Commitish".to_owned(),
+        tagged(vec![
+            ("Tag", string()),
+            ("Branch", string()),
+            ("Rev", string()),
+        ]),
+    );
+
+    defs.insert(
+        "Rule".to_owned(),
+        tagged(vec![
+            ("BuildScript", reference("TargetAttributes")),
+            ("ProcMacro", reference("TargetAttributes")),
+            ("Library", reference("TargetAttributes")),
+            ("Binary", reference("TargetAttributes")),
+        ]),
+    );
+
+    defs.insert(
+        "TargetAttributes".to_owned(),
+        object(
+            vec![
+                ("crate_name", string()),
+                ("crate_root", nullable(string())),
+                ("srcs", reference("Glob")),
+            ],
+            &[],
+            false,
+        ),
+    );
+
+    defs.insert(
+        "Glob".to_owned(),
+        object(
+            vec![("include", string_list()), ("exclude", string_list())],
+            &["include", "exclude"],
+            false,
+        ),
+    );
+
+    defs.insert(
+        "CrateDependency".to_owned(),
+        object(
+            vec![
+                ("id", crate_id()),
+                ("target", string()),
+                ("alias", string()),
+            ],
+            &["id", "target"],
+            false,
+        ),
+    );
+
+    let deps = || select_list(reference("CrateDependency"));
+    let strings = || select_list(string());
+    // Older lockfiles store extra dependencies as a plain list
+    let extra_deps = || json!({ "anyOf": [strings(), string_set()] });
+
+    defs.insert(
+        "CommonAttributes".to_owned(),
+        object(
+            vec![
+                ("compile_data", strings()),
+                ("compile_data_glob", string_set()),
+                ("crate_features", string_set()),
+                ("data", strings()),
+                ("data_glob", string_set()),
+                ("deps", deps()),
+                ("extra_deps", extra_deps()),
+                ("deps_dev", deps()),
+                ("edition", string()),
+                ("linker_script", string()),
+                ("proc_macro_deps", deps()),
+                ("extra_proc_macro_deps", extra_deps()),
+                ("proc_macro_deps_dev", deps()),
+                ("rustc_env", select_dict()),
+                ("rustc_env_files", strings()),
+                ("rustc_flags", strings()),
+                ("version", string()),
+                ("tags", string_list()),
+                ("visibility", string_set()),
+                ("disable_pipelining", json!({ "type": "boolean" })),
+            ],
+            &[],
+            false,
+        ),
+    );
+
+    defs.insert(
+        "BuildScriptAttributes".to_owned(),
+        object(
+            vec![
+                ("compile_data", strings()),
+                ("data", strings()),
+                ("data_glob", string_set()),
+                ("deps", deps()),
+                ("extra_deps", extra_deps()),
+                ("build_script_env", select_dict()),
+                ("extra_proc_macro_deps", extra_deps()),
+                ("proc_macro_deps", deps()),
+                ("rustc_env", select_dict()),
+                ("rustc_flags", strings()),
+                ("rustc_env_files", strings()),
+                ("tools", strings()),
+                ("links", string()),
+            ],
+            &[],
+            false,
+        ),
+    );
+
+    defs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::BTreeSet;
+
+    use regex::Regex;
+    use serde::de::{
+        self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess,
+        Visitor,
+    };
+    use serde::Deserialize;
+
+    use crate::config::{Config, CrateAnnotations, LicensePolicy, RenderConfig};
+    use crate::context::crate_context::{
+        BuildScriptAttributes, CommonAttributes, CrateContext, CrateDependency, Rule,
+        TargetAttributes,
+    };
+    use crate::context::Context;
+    use crate::lockfile::DigestInputs;
+    use crate::metadata::{Annotations, SourceAnnotation};
+    use crate::splicing::SplicingManifest;
+    use crate::utils::starlark::Glob;
+
+    type Error = de::value::Error;
+
+    /// A deserializer which records the fields of the struct (or struct variant) being
+    /// deserialized instead of producing a value.
+    struct FieldsDeserializer<'a> {
+        variant: &'static str,
+        fields: &'a mut Vec<&'static str>,
+    }
+
+    impl<'de, 'a> de::Deserializer<'de> for FieldsDeserializer<'a> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+            Err(de::Error::custom("Only structs and enums are supported"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Error> {
+            self.fields.extend(fields);
+            Err(de::Error::custom("Fields recorded"))
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            if self.variant.is_empty() {
+                self.fields.extend(variants);
+                return Err(de::Error::custom("Variants recorded"));
+            }
+            visitor.visit_enum(self)
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map identifier ignored_any
+        }
+    }
+
+    impl<'de, 'a> EnumAccess<'de> for FieldsDeserializer<'a> {
+        type Error = Error;
+        type Variant = Self;
+
+        fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+            let variant: de::value::StrDeserializer<Error> = self.variant.into_deserializer();
+            Ok((seed.deserialize(variant)?, self))
+        }
+    }
+
+    impl<'de, 'a> VariantAccess<'de> for FieldsDeserializer<'a> {
+        type Error = Error;
+
+        fn unit_variant(self) -> Result<(), Error> {
+            Err(de::Error::custom("Not a struct variant"))
+        }
+
+        fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+            self,
+            _seed: T,
+        ) -> Result<T::Value, Error> {
+            Err(de::Error::custom("Not a struct variant"))
+        }
+
+        fn tuple_variant<V: Visitor<'de>>(
+            self,
+            _len: usize,
+            _visitor: V,
+        ) -> Result<V::Value, Error> {
+            Err(de::Error::custom("Not a struct variant"))
+        }
+
+        fn struct_variant<V: Visitor<'de>>(
+            self,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Error> {
+            self.fields.extend(fields);
+            Err(de::Error::custom("Fields recorded"))
+        }
+    }
+
+    /// The serialized names of the fields of a struct, the variants of an enum or the
+    /// fields of a struct variant if `variant` is set.
+    fn serde_fields<'de, T: Deserialize<'de>>(variant: &'static str) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        let _ = T::deserialize(FieldsDeserializer {
+            variant,
+            fields: &mut fields,
+        });
+        assert!(!fields.is_empty());
+        fields.sort_unstable();
+        fields
+    }
+
+    fn properties(schema: &Value) -> Vec<&str> {
+        let mut names: Vec<&str> = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Check `value` against the subset of JSON Schema used by this module, returning a
+    /// description of each mismatch.
+    fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Vec<String> {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/definitions/");
+            assert!(root["definitions"].get(name).is_some(), "{}", reference);
+            return validate(root, &root["definitions"][name], value, path);
+        }
+        if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+            return match any_of
+                .iter()
+                .any(|schema| validate(root, schema, value, path).is_empty())
+            {
+                true => Vec::new(),
+                false => vec![format!("{}: {} matches no `anyOf` schema", path, value)],
+            };
+        }
+        if let Some(one_of) = schema.get("oneOf").and_then(Value::as_array) {
+            let matches = one_of
+                .iter()
+                .filter(|schema| validate(root, schema, value, path).is_empty())
+                .count();
+            return match matches {
+                1 => Vec::new(),
+                _ => vec![format!(
+                    "{}: {} matches {} `oneOf` schemas",
+                    path, value, matches
+                )],
+            };
+        }
+
+        if let Some(kind) = schema.get("type").and_then(Value::as_str) {
+            let matches = match kind {
+                "null" => value.is_null(),
+                "boolean" => value.is_boolean(),
+                "string" => value.is_string(),
+                "array" => value.is_array(),
+                "object" => value.is_object(),
+                _ => panic!("Unsupported schema type: {}", kind),
+            };
+            if !matches {
+                return vec![format!("{}: {} is not of type {}", path, value, kind)];
+            }
+        }
+
+        let mut errors = Vec::new();
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !values.contains(value) {
+                errors.push(format!("{}: {} is not one of {:?}", path, value, values));
+            }
+        }
+        if let (Some(pattern), Some(text)) = (
+            schema.get("pattern").and_then(Value::as_str),
+            value.as_str(),
+        ) {
+            if !Regex::new(pattern).unwrap().is_match(text) {
+                errors.push(format!("{}: {} does not match {}", path, value, pattern));
+            }
+        }
+        if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+            for (index, item) in array.iter().enumerate() {
+                errors.extend(validate(root, items, item, &format!("{}[{}]", path, index)));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for required in schema_required(schema) {
+                if !object.contains_key(&required) {
+                    errors.push(format!("{}: missing required property {}", path, required));
+                }
+            }
+            for (key, value) in object.iter() {
+                let path = format!("{}/{}", path, key);
+                if let Some(names) = schema.get("propertyNames") {
+                    errors.extend(validate(root, names, &json!(key), &path));
+                }
+                match (
+                    schema.get("properties").and_then(|props| props.get(key)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(schema), _) => errors.extend(validate(root, schema, value, &path)),
+                    (None, Some(Value::Bool(false))) => {
+                        errors.push(format!("{}: unexpected property", path))
+                    }
+                    (None, Some(schema)) => errors.extend(validate(root, schema, value, &path)),
+                    (None, None) => {}
+                }
+            }
+        }
+
+        errors
+    }
+
+    fn assert_valid(schema: &Value, value: &Value) {
+        let errors = validate(schema, schema, value, "");
+        assert!(errors.is_empty(), "{:#?}", errors);
+    }
+
+    fn schema_required(schema: &Value) -> Vec<String> {
+        let mut names: Vec<String> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| {
+                required
+                    .iter()
+                    .map(|name| name.as_str().unwrap().to_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort_unstable();
+        names
+    }
+
+    /// The fields of the object at `pointer` within a serialized `instance` of `T` which cannot
+    /// be omitted when deserializing it.
+    fn required_fields<T: DeserializeOwned>(instance: &Value, pointer: &str) -> Vec<String> {
+        let mut names: Vec<String> = instance
+            .pointer(pointer)
+            .and_then(Value::as_object)
+            .unwrap_or_else(|| panic!("No object at {}", pointer))
+            .keys()
+            .filter(|key| {
+                let mut value = instance.clone();
+                value
+                    .pointer_mut(pointer)
+                    .and_then(Value::as_object_mut)
+                    .unwrap()
+                    .remove(*key);
+                serde_json::from_value::<T>(value).is_err()
+            })
+            .cloned()
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// The names of all properties of an object schema which are not set in `value`
+    fn unset_properties<'a>(schema: &'a Value, value: &Value) -> Vec<&'a str> {
+        properties(schema)
+            .into_iter()
+            .filter(|name| value.get(name).is_none())
+            .collect()
+    }
+
+    fn variants(schema: &Value) -> Vec<&str> {
+        let mut names: Vec<&str> = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|variant| variant["properties"].as_object().unwrap().keys())
+            .map(String::as_str)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn config_schema_matches_types() {
+        let schema = schema(SchemaKind::Config);
+        let defs = &schema["definitions"];
+
+        assert_eq!(properties(&schema), serde_fields::<Config>(""));
+        assert_eq!(
+            properties(&defs["RenderConfig"]),
+            serde_fields::<RenderConfig>("")
+        );
+        assert_eq!(
+            properties(&defs["CrateAnnotations"]),
+            serde_fields::<CrateAnnotations>("")
+        );
+        assert_eq!(
+            properties(&defs["LicensePolicy"]),
+            serde_fields::<LicensePolicy>("")
+        );
+    }
+
+    #[test]
+    fn splicing_manifest_schema_matches_types() {
+        let schema = schema(SchemaKind::SplicingManifest);
+        let defs = &schema["definitions"];
+
+        assert_eq!(properties(&schema), serde_fields::<SplicingManifest>(""));
+        assert_eq!(
+            properties(&defs["DependencyDetail"]),
+            serde_fields::<cargo_toml::DependencyDetail>("")
+        );
+    }
+
+    #[test]
+    fn lockfile_schema_matches_types() {
+        let schema = schema(SchemaKind::Lockfile);
+        let defs = &schema["definitions"];
+
+        assert_eq!(properties(&schema), serde_fields::<Context>(""));
+        assert_eq!(
+            properties(&defs["DigestInputs"]),
+            serde_fields::<DigestInputs>("")
+        );
+        assert_eq!(
+            properties(&defs["CrateContext"]),
+            serde_fields::<CrateContext>("")
+        );
+        assert_eq!(
+            properties(&defs["CommonAttributes"]),
+            serde_fields::<CommonAttributes>("")
+        );
+        assert_eq!(
+            properties(&defs["BuildScriptAttributes"]),
+            serde_fields::<BuildScriptAttributes>("")
+        );
+        assert_eq!(
+            properties(&defs["TargetAttributes"]),
+            serde_fields::<TargetAttributes>("")
+        );
+        assert_eq!(
+            properties(&defs["CrateDependency"]),
+            serde_fields::<CrateDependency>("")
+        );
+        assert_eq!(properties(&defs["Glob"]), serde_fields::<Glob>(""));
+        assert_eq!(variants(&defs["Rule"]), serde_fields::<Rule>(""));
+        assert_eq!(
+            variants(&defs["SourceAnnotation"]),
+            serde_fields::<SourceAnnotation>("")
+        );
+        for variant in ["Git", "Http"] {
+            let schema = &defs["SourceAnnotation"]["oneOf"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find_map(|schema| schema["properties"].get(variant))
+                .unwrap();
+            assert_eq!(
+                properties(schema),
+                serde_fields::<SourceAnnotation>(variant)
+            );
+        }
+    }
+
+    #[test]
+    fn config_schema_types() {
+        let schema = schema(SchemaKind::Config);
+        let defs = &schema["definitions"];
+
+        let annotation = json!({
+            "gen_build_script": true,
+            "gen_binaries": ["cli"],
+            "deps": ["//:deps"],
+            "proc_macro_deps": ["//:proc_macro_deps"],
+            "crate_features": ["feature"],
+            "crate_features_remove": ["removed"],
+            "crate_features_override": ["override"],
+            "data": ["//:data"],
+            "data_glob": ["data/**"],
+            "compile_data": ["//:compile_data"],
+            "compile_data_glob": ["compile_data/**"],
+            "rustc_env": {"KEY": "VALUE"},
+            "rustc_env_files": ["//:env_file"],
+            "rustc_flags": ["--cfg", "feature"],
+            "tags": ["tag"],
+            "visibility": ["//visibility:public"],
+            "crate_root": "src/lib.rs",
+            "srcs_exclude": ["src/excluded.rs"],
+            "disable_pipelining": true,
+            "build_script_deps": ["//:build_script_deps"],
+            "build_script_proc_macro_deps": ["//:build_script_proc_macro_deps"],
+            "build_script_data": ["//:build_script_data"],
+            "build_script_tools": ["//:build_script_tools"],
+            "build_script_data_glob": ["build/**"],
+            "build_script_env": {"KEY": "VALUE"},
+            "build_script_rustc_env": {"KEY": "VALUE"},
+            "additive_build_file_content": "# Content",
+            "shallow_since": "1577836800 +0000",
+            "patch_args": ["-p1"],
+            "patch_tool": "patch",
+            "patches": ["//:fix.patch"],
+            "platform_annotations": {
+                "x86_64-unknown-linux-gnu": {"deps": ["//:linux"]},
+            },
+            "override_target": "//:override",
+            "skip_default_annotations": true,
+        });
+        let config: Config = serde_json::from_value(json!({
+            "generate_build_scripts": true,
+            "generate_binaries": false,
+            "annotations": {"openssl-sys *": annotation},
+            "default_annotations": {"rustc_flags": ["--cap-lints=allow"]},
+            "rendering": {
+                "repository_name": "crates",
+                "default_package_name": "package",
+                "vendor_mode": "remote",
+            },
+            "cargo_config": {"net": {"offline": true}},
+            "supported_platform_triples": ["x86_64-unknown-linux-gnu"],
+            "license_policy": {"allow": ["MIT"], "deny": ["GPL-3.0"]},
+        }))
+        .unwrap();
+        let value = serde_json::to_value(&config).unwrap();
+
+        // The instance covers every property so all of their types are checked
+        assert_eq!(unset_properties(&schema, &value), Vec::<&str>::new());
+        assert_eq!(
+            unset_properties(&defs["RenderConfig"], &value["rendering"]),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            unset_properties(
+                &defs["CrateAnnotations"],
+                &value["annotations"]["openssl-sys *"]
+            ),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            unset_properties(&defs["LicensePolicy"], &value["license_policy"]),
+            Vec::<&str>::new()
+        );
+        assert_valid(&schema, &value);
+
+        assert_eq!(
+            schema_required(&schema),
+            required_fields::<Config>(&value, "")
+        );
+        assert_eq!(
+            schema_required(&defs["RenderConfig"]),
+            required_fields::<Config>(&value, "/rendering")
+        );
+        assert_eq!(
+            schema_required(&defs["CrateAnnotations"]),
+            required_fields::<Config>(&value, "/annotations/openssl-sys *")
+        );
+        assert_eq!(
+            schema_required(&defs["LicensePolicy"]),
+            required_fields::<Config>(&value, "/license_policy")
+        );
+    }
+
+    #[test]
+    fn splicing_manifest_schema_types() {
+        let schema = schema(SchemaKind::SplicingManifest);
+
+        let manifest: SplicingManifest = serde_json::from_value(json!({
+            "direct_packages": {
+                "serde": {
+                    "version": "1.0",
+                    "registry": "registry",
+                    "registry-index": "https://example.com/index",
+                    "path": "serde",
+                    "git": "https://github.com/serde-rs/serde.git",
+                    "branch": "main",
+                    "tag": "v1.0.136",
+                    "rev": "abc123",
+                    "features": ["derive"],
+                    "optional": true,
+                    "default-features": false,
+                    "package": "serde",
+                },
+            },
+            "manifests": {"/tmp/Cargo.toml": "//:Cargo.toml"},
+            "cargo_config": "/tmp/config.toml",
+            "resolver_version": "2",
+        }))
+        .unwrap();
+        let value = serde_json::to_value(&manifest).unwrap();
+
+        assert_eq!(unset_properties(&schema, &value), Vec::<&str>::new());
+        assert_eq!(
+            unset_properties(
+                &schema["definitions"]["DependencyDetail"],
+                &value["direct_packages"]["serde"]
+            ),
+            Vec::<&str>::new()
+        );
+        assert_valid(&schema, &value);
+
+        assert_eq!(
+            schema_required(&schema),
+            required_fields::<SplicingManifest>(&value, "")
+        );
+        assert_eq!(
+            schema_required(&schema["definitions"]["DependencyDetail"]),
+            required_fields::<SplicingManifest>(&value, "/direct_packages/serde")
+        );
+    }
+
+    #[test]
+    fn lockfile_schema_types() {
+        let schema = schema(SchemaKind::Lockfile);
+        let defs = &schema["definitions"];
+
+        let fixtures = [
+            (
+                crate::test::metadata::alias(),
+                crate::test::lockfile::alias(),
+            ),
+            (
+                crate::test::metadata::build_scripts(),
+                crate::test::lockfile::build_scripts(),
+            ),
+            (
+                crate::test::metadata::crate_types(),
+                crate::test::lockfile::crate_types(),
+            ),
+            (
+                crate::test::metadata::git_repos(),
+                crate::test::lockfile::git_repos(),
+            ),
+        ];
+
+        let mut checked = BTreeSet::new();
+        for (metadata, lockfile) in fixtures {
+            let config = Config {
+                generate_build_scripts: true,
+                ..Config::default()
+            };
+            let annotations = Annotations::new(metadata, lockfile, config).unwrap();
+            let context = Context::new(annotations).unwrap();
+            let value = serde_json::to_value(&context).unwrap();
+            assert_valid(&schema, &value);
+
+            let mut check = |name: &'static str, definition: &Value, pointer: String| {
+                assert_eq!(
+                    schema_required(definition),
+                    required_fields::<Context>(&value, &pointer),
+                    "{} at {}",
+                    name,
+                    pointer
+                );
+                checked.insert(name);
+            };
+
+            assert_eq!(
+                schema_required(&schema),
+                required_fields::<Context>(&value, "")
+            );
+            for (id, crate_context) in value["crates"].as_object().unwrap() {
+                let pointer = format!("/crates/{}", id);
+                check("CrateContext", &defs["CrateContext"], pointer.clone());
+                check(
+                    "CommonAttributes",
+                    &defs["CommonAttributes"],
+                    format!("{}/common_attrs", pointer),
+                );
+                if crate_context.get("build_script_attrs").is_some() {
+                    check(
+                        "BuildScriptAttributes",
+                        &defs["BuildScriptAttributes"],
+                        format!("{}/build_script_attrs", pointer),
+                    );
+                }
+                for (index, rule) in crate_context["targets"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .enumerate()
+                {
+                    let (kind, attrs) = rule.as_object().unwrap().iter().next().unwrap();
+                    check(
+                        "TargetAttributes",
+                        &defs["TargetAttributes"],
+                        format!("{}/targets/{}/{}", pointer, index, kind),
+                    );
+                    if attrs.get("srcs").is_some() {
+                        check(
+                            "Glob",
+                            &defs["Glob"],
+                            format!("{}/targets/{}/{}/srcs", pointer, index, kind),
+                        );
+                    }
+                }
+                if let Some(deps) = crate_context["common_attrs"]
+                    .pointer("/deps/common")
+                    .and_then(Value::as_array)
+                {
+                    if !deps.is_empty() {
+                        check(
+                            "CrateDependency",
+                            &defs["CrateDependency"],
+                            format!("{}/common_attrs/deps/common/0", pointer),
+                        );
+                    }
+                }
+                if let Some(repository) = crate_context["repository"].as_object() {
+                    let (variant, _) = repository.iter().next().unwrap();
+                    let name = match variant.as_str() {
+                        "Git" => "Git",
+                        _ => "Http",
+                    };
+                    let definition = defs["SourceAnnotation"]["oneOf"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .find_map(|schema| schema["properties"].get(name))
+                        .unwrap();
+                    check(
+                        name,
+                        definition,
+                        format!("{}/repository/{}", pointer, variant),
+                    );
+                }
+            }
+        }
+
+        // The fixtures cover all definitions with fields
+        assert_eq!(
+            checked,
+            BTreeSet::from([
+                "BuildScriptAttributes",
+                "CommonAttributes",
+                "CrateContext",
+                "CrateDependency",
+                "Git",
+                "Glob",
+                "Http",
+                "TargetAttributes",
+            ])
+        );
+    }
+
+    #[test]
+    fn lockfiles_match_schema() {
+        let schema = schema(SchemaKind::Lockfile);
+        let lockfiles = [
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.Bazel.lock")),
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/examples/cargo_aliases/Cargo.Bazel.lock"
+            )),
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/examples/cargo_workspace/Cargo.Bazel.lock"
+            )),
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/examples/extra_workspace_members/Cargo.Bazel.lock"
+            )),
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/examples/multi_package/Cargo.Bazel.lock"
+            )),
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/examples/no_cargo_manifests/Cargo.Bazel.lock"
+            )),
+        ];
+
+        // Every checked in lockfile is fully described by the schema
+        for lockfile in lockfiles {
+            assert_valid(&schema, &serde_json::from_str(lockfile).unwrap());
+        }
+
+        // Legacy values are accepted and mismatches are found anywhere in the lockfile
+        let mut lockfile: Value = serde_json::from_str(lockfiles[0]).unwrap();
+        let attrs = "/crates/log 0.4.14/common_attrs";
+        lockfile.pointer_mut(attrs).unwrap()["extra_deps"] = json!(["@zlib"]);
+        assert_valid(&schema, &lockfile);
+        lockfile.pointer_mut(attrs).unwrap()["deps"]["common"] = json!(["cfg-if 1.0.0"]);
+        assert_eq!(
+            validate(&schema, &schema, &lockfile, ""),
+            vec![format!(
+                "{}/deps/common[0]: \"cfg-if 1.0.0\" is not of type object",
+                attrs
+            )]
+        );
+
+        assert!(schema["$id"]
+            .as_str()
+            .unwrap()
+            .contains(env!("CARGO_PKG_VERSION")));
+    }
+}
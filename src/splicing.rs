@@ -15,7 +15,7 @@ use hex::ToHex;
 use serde::{Deserialize, Serialize};
 
 use crate::config::CrateId;
-use crate::metadata::LockGenerator;
+use crate::metadata::{CargoUpdateRequest, LockGenerator};
 use crate::utils::starlark::Label;
 
 use self::cargo_config::CargoConfig;
@@ -467,6 +467,7 @@ pub fn read_manifest(manifest: &Path) -> Result<Manifest> {
 pub fn generate_lockfile(
     manifest_path: &SplicedManifest,
     existing_lock: &Option<PathBuf>,
+    updates: &[CargoUpdateRequest],
     cargo_bin: &Path,
     rustc_bin: &Path,
 ) -> Result<cargo_lock::Lockfile> {
@@ -484,7 +485,7 @@ pub fn generate_lockfile(
 
     // Generate the new lockfile
     let lockfile = LockGenerator::new(PathBuf::from(cargo_bin), PathBuf::from(rustc_bin))
-        .generate(manifest_path.as_path_buf(), existing_lock)?;
+        .generate(manifest_path.as_path_buf(), existing_lock, updates)?;
 
     // Write the lockfile to disk
     if !root_lockfile_path.exists() {
@@ -1,5 +1,6 @@
 //! Common utilities
 
+pub mod diff;
 pub mod starlark;
 
 /// Convert a string into a valid crate module name by applying transforms to invalid characters
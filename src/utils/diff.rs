@@ -0,0 +1,307 @@
+//! A minimal line based [unified diff](https://www.gnu.org/software/diffutils/manual/html_node/Unified-Format.html)
+//! implementation.
+
+/// The number of unchanged lines to show around each change
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// The furthest reaching x position of each diagonal `k` in Myers' algorithm
+struct V {
+    offset: isize,
+    values: Vec<usize>,
+}
+
+impl V {
+    fn new(max_d: usize) -> Self {
+        Self {
+            offset: max_d as isize,
+            values: vec![0; 2 * max_d + 2],
+        }
+    }
+}
+
+impl std::ops::Index<isize> for V {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.values[(k + self.offset) as usize]
+    }
+}
+
+impl std::ops::IndexMut<isize> for V {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.values[(k + self.offset) as usize]
+    }
+}
+
+/// Find the start of the middle snake of the shortest edit script transforming `old` into
+/// `new` by searching forwards and backwards at the same time. Both slices must be non-empty.
+fn find_middle_snake(old: &[&str], new: &[&str], vf: &mut V, vb: &mut V) -> (usize, usize) {
+    let (n, m) = (old.len(), new.len());
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+    vf[1] = 0;
+    vb[1] = 0;
+
+    let max_d = ((n + m).div_ceil(2) + 1) as isize;
+    for d in 0..max_d {
+        // Extend the furthest reaching forward paths
+        for k in (-d..=d).step_by(2) {
+            let mut x = match k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
+                true => vf[k + 1],
+                false => vf[k - 1] + 1,
+            };
+            let mut y = (x as isize - k) as usize;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && old[x] == new[y] {
+                x += 1;
+                y += 1;
+            }
+            vf[k] = x;
+
+            if odd && (k - delta).abs() < d && vf[k] + vb[delta - k] >= n {
+                return (x0, y0);
+            }
+        }
+
+        // Extend the furthest reaching backward paths
+        for k in (-d..=d).step_by(2) {
+            let mut x = match k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
+                true => vb[k + 1],
+                false => vb[k - 1] + 1,
+            };
+            let mut y = (x as isize - k) as usize;
+            while x < n && y < m && old[n - x - 1] == new[m - y - 1] {
+                x += 1;
+                y += 1;
+            }
+            vb[k] = x;
+
+            if !odd && (k - delta).abs() <= d && vb[k] + vf[delta - k] >= n {
+                return (n - x, m - y);
+            }
+        }
+    }
+
+    unreachable!("Every pair of sequences has a middle snake")
+}
+
+/// Recursively compute the edit script of `old` and `new` by splitting both at their middle snake.
+fn conquer<'a>(
+    old: &[&'a str],
+    new: &[&'a str],
+    vf: &mut V,
+    vb: &mut V,
+    ops: &mut Vec<(Op, &'a str)>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    ops.extend(old[..prefix].iter().map(|l| (Op::Equal, *l)));
+    if old_mid.is_empty() {
+        ops.extend(new_mid.iter().map(|l| (Op::Insert, *l)));
+    } else if new_mid.is_empty() {
+        ops.extend(old_mid.iter().map(|l| (Op::Delete, *l)));
+    } else {
+        let (x, y) = find_middle_snake(old_mid, new_mid, vf, vb);
+        conquer(&old_mid[..x], &new_mid[..y], vf, vb, ops);
+        conquer(&old_mid[x..], &new_mid[y..], vf, vb, ops);
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| (Op::Equal, *l)));
+}
+
+/// Compute the shortest edit script transforming `old` into `new` using the linear space
+/// variant of [Myers' algorithm](http://www.xmailserver.org/diff2.pdf). Each op is paired with
+/// the line it refers to.
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let max_d = (old.len() + new.len()).div_ceil(2) + 1;
+    let mut vf = V::new(max_d);
+    let mut vb = V::new(max_d);
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    conquer(old, new, &mut vf, &mut vb, &mut ops);
+
+    ops
+}
+
+/// Produce a unified diff of two texts. Returns `None` if the texts are identical.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = edit_script(&old_lines, &new_lines);
+
+    // Track the number of old and new lines preceding each op
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for (op, _) in ops.iter() {
+        positions.push((old_pos, new_pos));
+        match op {
+            Op::Equal => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Op::Delete => old_pos += 1,
+            Op::Insert => new_pos += 1,
+        }
+    }
+
+    // Group changes which are close together into hunks
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, _) in ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+    {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // Only differences in trailing newlines remain
+    if hunks.is_empty() {
+        return Some(format!(
+            "--- {}\n+++ {}\n\\ Files differ only in trailing newlines\n",
+            old_label, new_label
+        ));
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, end) in hunks {
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|(op, _)| *op != Op::Insert).count();
+        let new_len = hunk.iter().filter(|(op, _)| *op != Op::Delete).count();
+        let (old_start, new_start) = positions[start];
+
+        // Ranges are 1-indexed unless they are empty
+        let range_start = |pos: usize, len: usize| if len == 0 { pos } else { pos + 1 };
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            range_start(old_start, old_len),
+            old_len,
+            range_start(new_start, new_len),
+            new_len
+        ));
+
+        for (op, line) in hunk {
+            let prefix = match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            };
+            diff.push(prefix);
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+
+    Some(diff)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identical() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), None);
+    }
+
+    #[test]
+    fn single_change() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+
+        assert_eq!(
+            unified_diff(old, new, "a/file", "b/file").unwrap(),
+            "--- a/file\n+++ b/file\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old = (1..=20).map(|i| format!("{}\n", i)).collect::<String>();
+        let new = (1..=20)
+            .filter(|i| *i != 19)
+            .map(|i| match i {
+                2 => "two\n".to_owned(),
+                _ => format!("{}\n", i),
+            })
+            .collect::<String>();
+
+        assert_eq!(
+            unified_diff(&old, &new, "a", "b").unwrap(),
+            "--- a\n+++ b\n@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n@@ -16,5 +16,4 @@\n 16\n 17\n 18\n-19\n 20\n"
+        );
+    }
+
+    #[test]
+    fn shortest_edit_script() {
+        // A simple linear congruential generator to produce many small inputs
+        let mut seed: u32 = 42;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as usize
+        };
+        let alphabet = ["a", "b", "c", "d"];
+
+        for _ in 0..500 {
+            let old: Vec<&str> = (0..next() % 12).map(|_| alphabet[next() % 4]).collect();
+            let new: Vec<&str> = (0..next() % 12).map(|_| alphabet[next() % 4]).collect();
+            let ops = edit_script(&old, &new);
+
+            // The script transforms `old` into `new`
+            let from: Vec<&str> = ops
+                .iter()
+                .filter(|(op, _)| *op != Op::Insert)
+                .map(|(_, line)| *line)
+                .collect();
+            let to: Vec<&str> = ops
+                .iter()
+                .filter(|(op, _)| *op != Op::Delete)
+                .map(|(_, line)| *line)
+                .collect();
+            assert_eq!(from, old);
+            assert_eq!(to, new);
+
+            // The script is as short as the one derived from the longest common subsequence
+            let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+            for i in (0..old.len()).rev() {
+                for j in (0..new.len()).rev() {
+                    lcs[i][j] = match old[i] == new[j] {
+                        true => lcs[i + 1][j + 1] + 1,
+                        false => lcs[i + 1][j].max(lcs[i][j + 1]),
+                    };
+                }
+            }
+            let edits = ops.iter().filter(|(op, _)| *op != Op::Equal).count();
+            assert_eq!(edits, old.len() + new.len() - 2 * lcs[0][0]);
+        }
+    }
+
+    #[test]
+    fn new_file() {
+        assert_eq!(
+            unified_diff("", "a\nb\n", "a", "b").unwrap(),
+            "--- a\n+++ b\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+    }
+}
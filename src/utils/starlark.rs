@@ -9,4 +9,5 @@ pub use label::*;
 pub use select::*;
 
 pub type SelectStringList = SelectList<String>;
+pub type SelectStringOrderedList = SelectOrderedList<String>;
 pub type SelectStringDict = SelectDict<String>;
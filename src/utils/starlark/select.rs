@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::{btree_set, BTreeMap, BTreeSet};
 use std::iter::once;
 
@@ -62,10 +62,45 @@ impl<T: Ord> SelectList<T> {
         }
     }
 
+    /// Retain only the values, in all configurations, for which `func` returns true
+    pub fn retain<F: Fn(&T) -> bool>(&mut self, func: F) {
+        self.common.retain(|value| func(value));
+        for values in self.selects.values_mut() {
+            values.retain(|value| func(value));
+        }
+        self.selects.retain(|_, values| !values.is_empty());
+    }
+
     /// Determine whether or not the select should be serialized
     pub fn should_skip_serializing(&self) -> bool {
         self.common.is_empty() && self.selects.is_empty()
     }
+
+    /// Deserialize a [SelectList] which may also be a plain list of values common to all
+    /// configurations, as older lockfiles store attributes which were not always selectable.
+    pub fn deserialize_list_or_select<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum ListOrSelect<T: Ord> {
+            List(BTreeSet<T>),
+            Select {
+                common: BTreeSet<T>,
+                selects: BTreeMap<String, BTreeSet<T>>,
+            },
+        }
+
+        Ok(match ListOrSelect::deserialize(deserializer)? {
+            ListOrSelect::List(common) => Self {
+                common,
+                selects: BTreeMap::new(),
+            },
+            ListOrSelect::Select { common, selects } => Self { common, selects },
+        })
+    }
 }
 
 impl<T: Ord> Select<T> for SelectList<T> {
@@ -93,6 +128,49 @@ impl<T: Ord, U: Ord> SelectMap<T, U> for SelectList<T> {
     }
 }
 
+/// A list of selectable values which, unlike [SelectList], preserves the order values
+/// are inserted in and allows duplicates. This is required for flag-like attributes
+/// (Eg. `rustc_flags`) where arguments may be paired.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Clone)]
+pub struct SelectOrderedList<T> {
+    common: Vec<T>,
+    selects: BTreeMap<String, Vec<T>>,
+}
+
+impl<T> Default for SelectOrderedList<T> {
+    fn default() -> Self {
+        Self {
+            common: Vec::new(),
+            selects: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> SelectOrderedList<T> {
+    // TODO: This should probably be added to the [Select] trait
+    pub fn insert(&mut self, value: T, configuration: Option<String>) {
+        match configuration {
+            None => self.common.push(value),
+            Some(cfg) => self.selects.entry(cfg).or_default().push(value),
+        };
+    }
+
+    /// Determine whether or not the select should be serialized
+    pub fn should_skip_serializing(&self) -> bool {
+        self.common.is_empty() && self.selects.is_empty()
+    }
+}
+
+impl<T> Select<T> for SelectOrderedList<T> {
+    fn configurations(&self) -> BTreeSet<Option<&String>> {
+        let configs = self.selects.keys().map(Some);
+        match self.common.is_empty() {
+            true => configs.collect(),
+            false => configs.chain(once(None)).collect(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Clone)]
 pub struct SelectDict<T: Ord> {
     common: BTreeMap<String, T>,